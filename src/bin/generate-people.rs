@@ -0,0 +1,110 @@
+use std::{
+    env,
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    process,
+};
+
+use tantivy_merge_policy_demo::generator::{
+    Locale,
+    PersonGenerator,
+};
+
+const USAGE: &str = "Usage: generate-people [--count <count>] [--seed <seed>] [--locales <locale>[,<locale>...]] [--output <people.json>]
+
+Writes a JSON array of people spread over the en_gb, en_us, cs_cz, fr and de locales,
+or the given ones, to the output or stdout. The same seed always yields the same people.";
+
+const DEFAULT_COUNT: usize = 1000;
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!();
+    eprintln!("{}", USAGE);
+    process::exit(EXIT_USAGE);
+}
+
+fn failure(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(EXIT_FAILURE);
+}
+
+fn flag_value(flag: &str, args: &mut impl Iterator<Item = String>) -> String {
+    match args.next() {
+        Some(value) => value,
+        None => usage_error(&format!("Missing value of {}", flag)),
+    }
+}
+
+fn parse_locales(value: &str) -> Vec<Locale> {
+    value
+        .split(',')
+        .map(|locale| match locale.trim().parse::<Locale>() {
+            Ok(locale) => locale,
+            Err(err) => usage_error(&err),
+        })
+        .collect()
+}
+
+fn main() {
+    let mut count = DEFAULT_COUNT;
+    let mut seed: u64 = 0;
+    let mut locales = Locale::ALL.to_vec();
+    let mut output: Option<String> = None;
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => {
+                let value = flag_value(&arg, &mut args);
+                count = match value.parse::<usize>() {
+                    Ok(count) => count,
+                    Err(_) => usage_error(&format!("Invalid --count {:?}", value)),
+                };
+            },
+            "--seed" => {
+                let value = flag_value(&arg, &mut args);
+                seed = match value.parse::<u64>() {
+                    Ok(seed) => seed,
+                    Err(_) => usage_error(&format!("Invalid --seed {:?}", value)),
+                };
+            },
+            "--locales" => locales = parse_locales(&flag_value(&arg, &mut args)),
+            "--output" => output = Some(flag_value(&arg, &mut args)),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ => usage_error(&format!("Unknown argument {:?}", arg)),
+        }
+    }
+
+    let people = PersonGenerator::new(seed)
+        .with_locales(locales)
+        .generate(count);
+
+    let writer: Box<dyn Write> = match &output {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(err) => failure(&format!("Failed to create {}: {}", path, err)),
+        },
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = BufWriter::new(writer);
+
+    if let Err(err) = serde_json::to_writer(&mut writer, &people) {
+        failure(&format!("Failed to write people: {}", err));
+    }
+
+    if let Err(err) = writer.flush() {
+        failure(&format!("Failed to write people: {}", err));
+    }
+}
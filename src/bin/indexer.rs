@@ -1,674 +1,551 @@
 use std::{
-    fs,
-    thread,
-    time::{
-        Instant,
-        Duration,
-    },
-};
-use serde::{
-    Serialize,
-    Deserialize,
-};
-use serde_json;
-use tantivy::{
-    Index,
-    IndexWriter,
+    env,
+    path::Path,
+    process,
+    time::Duration,
 };
 
+use tantivy::Index;
 use tantivy_merge_policy_demo::{
-    config,
-    models,
-    store,
+    config::Config,
+    harness::{
+        self,
+        CommitStrategy,
+        CpuPinning,
+        IndexStorage,
+        MergePolicyConfig,
+        RunOutcome,
+        RunResult,
+        Scenario,
+        ScenarioMatrix,
+        WaitMode,
+        WorkloadMix,
+    },
+    models::loader::PeopleLoader,
+    store::utils::SegmentInventory,
 };
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct RunResult {
-    total_index_time: String,
-    final_segment_file_counts: SegmentFileCounts,
-}
-
-#[derive(Debug, Deserialize, Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct SegmentFileCounts {
-    fast: u32,
-    fieldnorm: u32,
-    idx: u32,
-    pos: u32,
-    store: u32,
-    term: u32,
-}
-
-async fn get_index() -> Index {
-    match fs::remove_dir_all(config::INDEX_PEOPLE_PATH.clone()) {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to cleanup index directory: {}", err);
-        },
-    }
+const USAGE: &str = "Usage:
+  indexer run [--scenario <a-h>[,<a-h>...]] [--docs <count>] [--policy <policy>] [--commit <strategy>]
+              [--wait | --no-wait] [--timeout <duration>|none] [--filter-degenerate] [--storage directory|ram]
+              [--writer-threads <count>] [--cpus <list>|slot|unpinned] [--concurrency <runs>]
+              [--repetitions <count>] [--warmup <count>] [--workload <inserts>:<updates>:<deletes>[:<seed>]]
+              [--conversion-threads <count>] [--conversion-queue <docs>]
+              [--format table|json]
+  indexer matrix --config <matrix.toml|matrix.json> [--concurrency <runs>] [--format table|json]
+  indexer inspect <index-dir> [--format table|json]
 
-    match fs::create_dir_all(config::INDEX_PEOPLE_PATH.clone()) {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to prepare index directory: {}", err);
-        },
-    }
+Policies are given as `whenever`, `no_merge`, `log[:<min segments>:<max docs>:<min layer>:<level log>:<deletes ratio>]`,
+`target_docs:<docs>[:first_fit|best_fit]`, `target_bytes:<bytes>[:<segments>]`,
+`tiered[:<factor>[:<floor>[:<at once>[:<max docs>]]]]` or as JSON, durations as e.g.
+`500ms`, `60s` or `5m`.
 
-    match store::people::open_index(store::PERSON_SCHEMA.clone()).await {
-        Ok(index) => index,
-        Err(err) => {
-            panic!("Failed to open people index: {:?}", err);
-        },
-    }
-}
+Commit strategies are given as `single`, `every_document`, `every_docs:<docs>`,
+`interval:<millis>`, `bytes:<bytes>`, `poisson:<docs per second>:<commit millis>[:<seed>]`
+or `bursty:<min>-<max docs>:<min>-<max idle millis>[:<seed>]`, arrival pauses of the
+last two being left out of the index time and listed on their own.
 
-fn count_segment_file_counts() -> Result<SegmentFileCounts, std::io::Error> {
-    let directory = fs::read_dir(config::INDEX_PEOPLE_PATH.clone())?;
+--concurrency runs that many scenarios at once, 1 by default. --cpus pins the writers
+to a CPU list such as `0-3,6`, or with `slot` to an even share of the CPUs per concurrent
+run. Runs overlapping with others are listed along with whether their timing was skewed.
 
-    let mut segment_file_counts = SegmentFileCounts{
-        fast: 0,
-        fieldnorm: 0,
-        idx: 0,
-        pos: 0,
-        store: 0,
-        term: 0,
-    };
+--repetitions runs every scenario that many times after --warmup discarded runs, reporting
+the spread of the trials and the details of the median one.
 
-    for entry in directory {
-        match entry {
-            Ok(entry) => {
-                match entry.file_name().to_str() {
-                    Some(file_name) => {
-                        if file_name.ends_with("fast") {
-                            segment_file_counts.fast += 1;
-                        } else if file_name.ends_with("fieldnorm") {
-                            segment_file_counts.fieldnorm += 1;
-                        } else if file_name.ends_with("idx") {
-                            segment_file_counts.idx += 1;
-                        } else if file_name.ends_with("pos") {
-                            segment_file_counts.pos += 1;
-                        } else if file_name.ends_with("store") {
-                            segment_file_counts.store += 1;
-                        } else if file_name.ends_with("term") {
-                            segment_file_counts.term += 1;
-                        }
-                    },
-                    None => {},
-                };
-            },
-            Err(err) => {
-                println!("Failed to read dir entry: {}", err)
-            },
-        }
-    }
+--workload mixes inserts with updates (`delete_term` on the id and re-add) and deletes
+of earlier inserted people by the given weights, --docs then being the number of operations.
 
-    Ok(segment_file_counts)
-}
+--conversion-threads builds the documents on that many threads ahead of the writer, one per
+CPU by default, each holding up to --conversion-queue documents (256 by default) before
+blocking. The throughput of every stage is listed along with the time it spent blocked.
 
-#[tokio::main]
-async fn main() {
-    let people_data = match fs::read(config::DATA_PEOPLE_PATH.clone()) {
-        Ok(people_data) => people_data,
-        Err(err) => {
-            panic!("Failed to read the data: {}", err);
-        },
-    };
+`run` and `matrix` also accept the --settings, --data-people-path, --index-people-path,
+--merge-policy-log-path and --writer-memory-budget settings.
 
-    let people: Vec<models::person::Person> = match serde_json::from_slice(&people_data) {
-        Ok(people) => people,
-        Err(err) => {
-            panic!("Failed to parse the data: {}", err);
-        },
-    };
+Exit codes: 0 all runs completed, 1 failure, 2 invalid usage, 3 a run timed out";
 
-    /*
-     * Test A
-     *   - Single final commit / Merge policy: MergeWhenever / No waiting for merging
-     */
-    let result_a = run_a(people.clone()).await;
-    println!("{}", serde_json::json!(result_a));
-
-    /*
-     * Test B
-     *   - Single final commit / Merge policy: MergeWhenever / Wait for merging
-     */
-    // let result_b = run_b(people.clone()).await;
-    // println!("{}", serde_json::json!(result_b));
-
-    /*
-     * Test C
-     *   - Single final commit / Merge policy: TargetDocs / No waiting for merging
-     */
-    // let result_c = run_c(people.clone()).await;
-    // println!("{}", serde_json::json!(result_c));
-
-    /*
-     * Test D (Infinite loop!)
-     *   - Single final commit / Merge policy: TargetDocs / Wait for merging
-     */
-    // let result_d = run_d(people.clone()).await;
-    // println!("{}", serde_json::json!(result_d));
-
-    /*
-     * Test E
-     *   - Commit after every add_document / Merge policy: MergeWhenever / No waiting for merging
-     */
-    // let result_e = run_e(people.clone()).await;
-    // println!("{}", serde_json::json!(result_e));
-
-    /*
-     * Test F
-     *   - Commit after every add_document / Merge policy: MergeWhenever / Wait for merging
-     */
-    // let result_f = run_f(people.clone()).await;
-    // println!("{}", serde_json::json!(result_f));
-
-    /*
-     * Test G
-     *   - Commit after every add_document / Merge policy: TargetDocs / No waiting for merging
-     */
-    // let result_g = run_g(people.clone()).await;
-    // println!("{}", serde_json::json!(result_g));
-
-    /*
-     * Test H (Infinite loop!)
-     *   - Commit after every add_document / Merge policy: TargetDocs / Wait for merging
-     */
-    // let result_h = run_h(people).await;
-    // println!("{}", serde_json::json!(result_h));
-
-    println!("All done!! 🎉🎉🎉")
-}
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_TIMED_OUT: i32 = 3;
 
 /*
- * Test A
+ * Runs A-H from the README are available as presets:
+ *
+ *   - A: Single final commit / MergeWhenever / No waiting for merging
+ *   - B: Single final commit / MergeWhenever / Wait for merging
+ *   - C: Single final commit / TargetDocs / No waiting for merging
+ *   - D: Single final commit / TargetDocs / Wait for merging (Infinite loop, times out!)
+ *   - E: Commit after every add_document / MergeWhenever / No waiting for merging
+ *   - F: Commit after every add_document / MergeWhenever / Wait for merging
+ *   - G: Commit after every add_document / TargetDocs / No waiting for merging
+ *   - H: Commit after every add_document / TargetDocs / Wait for merging (Infinite loop, times out!)
  *
- *   - Single final commit
- *   - Merge policy: MergeWhenever
- *   - No waiting for merging
+ * Any other combination can be run via `indexer matrix`, the default matrix being
+ * equivalent to runs A-H.
  */
-async fn run_a(people: Vec<models::person::Person>) -> RunResult {
-    thread::sleep(Duration::from_secs(5));
 
-    let index = get_index().await;
-
-    let start_instant = Instant::now();
-
-    let mut writer: IndexWriter = match index.writer(50_000_000) {
-        Ok(writer) => writer,
-        Err(err) => {
-            panic!("Failed to init writer: {}", err);
-        },
-    };
+enum OutputFormat {
+    Table,
+    Json,
+}
 
-    let merge_policy = store::utils::MergeWheneverPossiblePolicy::new("a".to_string());
-    writer.set_merge_policy(merge_policy.as_box());
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!();
+    eprintln!("{}", USAGE);
+    process::exit(EXIT_USAGE);
+}
 
-    for person in people {
-        let document = match person.to_doc(store::PERSON_SCHEMA.clone()).await {
-            Ok(document) => document,
-            Err(err) => {
-                panic!("Failed to convert person into document: {}", err);
-            },
-        };
+fn failure(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(EXIT_FAILURE);
+}
 
-        match writer.add_document(document) {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to add document to writer: {}", err);
-            },
-        }
+fn flag_value(flag: &str, args: &mut impl Iterator<Item = String>) -> String {
+    match args.next() {
+        Some(value) => value,
+        None => usage_error(&format!("Missing value of {}", flag)),
     }
+}
 
-    match writer.commit() {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to commit the writer: {}", err);
-        },
+fn parse_format(value: &str) -> OutputFormat {
+    match value {
+        "table" => OutputFormat::Table,
+        "json" => OutputFormat::Json,
+        _ => usage_error(&format!("Unknown format {:?}", value)),
     }
+}
 
-    RunResult{
-        total_index_time: format!("{:?}", start_instant.elapsed()),
-        final_segment_file_counts: match count_segment_file_counts() {
-            Ok(segment_file_counts) => segment_file_counts,
-            Err(err) => {
-                panic!("Failed to count final segment files: {}", err);
-            },
-        },
+/// Positive count of threads or runs
+fn parse_count(flag: &str, value: &str) -> usize {
+    match value.parse::<usize>() {
+        Ok(count) if count > 0 => count,
+        _ => usage_error(&format!("Invalid {} {:?}", flag, value)),
     }
 }
 
-/*
- * Test B
- *
- *   - Single final commit
- *   - Merge policy: MergeWhenever
- *   - Wait for merging
- */
-async fn run_b(people: Vec<models::person::Person>) -> RunResult {
-    thread::sleep(Duration::from_secs(5));
-
-    let index = get_index().await;
+/// Accepts `none`, the `Debug` format of durations, minutes, hours and bare seconds
+fn parse_timeout(value: &str) -> Option<Duration> {
+    if value == "none" {
+        return None;
+    }
 
-    let start_instant = Instant::now();
+    if let Some(duration) = harness::parse_debug_duration(value) {
+        return Some(duration);
+    }
 
-    let mut writer: IndexWriter = match index.writer(50_000_000) {
-        Ok(writer) => writer,
-        Err(err) => {
-            panic!("Failed to init writer: {}", err);
-        },
+    let (number, multiplier) = if let Some(number) = value.strip_suffix('m') {
+        (number, 60.0)
+    } else if let Some(number) = value.strip_suffix('h') {
+        (number, 3600.0)
+    } else {
+        (value, 1.0)
     };
 
-    let merge_policy = store::utils::MergeWheneverPossiblePolicy::new("b".to_string());
-    writer.set_merge_policy(merge_policy.as_box());
-
-    for person in people {
-        let document = match person.to_doc(store::PERSON_SCHEMA.clone()).await {
-            Ok(document) => document,
-            Err(err) => {
-                panic!("Failed to convert person into document: {}", err);
-            },
-        };
-
-        match writer.add_document(document) {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to add document to writer: {}", err);
-            },
-        }
-    }
-
-    match writer.commit() {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to commit the writer: {}", err);
-        },
+    match number.parse::<f64>() {
+        Ok(number) if number >= 0.0 => Some(Duration::from_secs_f64(number * multiplier)),
+        _ => usage_error(&format!("Invalid duration {:?}", value)),
     }
+}
 
-    match writer.wait_merging_threads() {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to wait for merging threads: {}", err);
-        },
+/// Loads the config out of `args`, after `--help` which doesn't need any
+fn load_config(args: &mut Vec<String>) -> Config {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        process::exit(0);
     }
 
-    RunResult{
-        total_index_time: format!("{:?}", start_instant.elapsed()),
-        final_segment_file_counts: match count_segment_file_counts() {
-            Ok(segment_file_counts) => segment_file_counts,
-            Err(err) => {
-                panic!("Failed to count final segment files: {}", err);
-            },
-        },
+    match Config::load(args) {
+        Ok(config) => config,
+        Err(err) => usage_error(&err.to_string()),
     }
 }
 
-/*
- * Test C
- *
- *   - Single final commit
- *   - Merge policy: TargetDocs
- *   - No waiting for merging
- */
-async fn run_c(people: Vec<models::person::Person>) -> RunResult {
-    thread::sleep(Duration::from_secs(5));
-
-    let index = get_index().await;
-
-    let start_instant = Instant::now();
-
-    let mut writer: IndexWriter = match index.writer(50_000_000) {
-        Ok(writer) => writer,
-        Err(err) => {
-            panic!("Failed to init writer: {}", err);
-        },
+/// Streams the people from the data file, JSON array or NDJSON, gzipped or not,
+/// until `limit` people were read, without keeping them. Invalid records are reported
+/// and skipped as they will be by the runs, which read the file again.
+fn check_people(config: &Config, limit: usize) {
+    let loader = match PeopleLoader::open(&config.data_people_path) {
+        Ok(loader) => loader,
+        Err(err) => failure(&format!("Failed to read the data: {}", err)),
     };
 
-    let merge_policy = store::utils::TargetDocsPerSegmentPolicy::new("c".to_string(), 10000);
-    writer.set_merge_policy(merge_policy.as_box());
+    let mut valid_people: usize = 0;
+    let mut invalid_people: u64 = 0;
 
-    for person in people {
-        let document = match person.to_doc(store::PERSON_SCHEMA.clone()).await {
-            Ok(document) => document,
+    for person in loader {
+        match person {
+            Ok(_) => valid_people += 1,
+            Err(err) if err.is_fatal() => failure(&format!("Failed to read the data: {}", err)),
             Err(err) => {
-                panic!("Failed to convert person into document: {}", err);
-            },
-        };
-
-        match writer.add_document(document) {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to add document to writer: {}", err);
+                eprintln!("Skipping invalid person, {}", err);
+                invalid_people += 1;
             },
         }
-    }
 
-    match writer.commit() {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to commit the writer: {}", err);
-        },
+        if valid_people >= limit {
+            break;
+        }
     }
 
-    RunResult{
-        total_index_time: format!("{:?}", start_instant.elapsed()),
-        final_segment_file_counts: match count_segment_file_counts() {
-            Ok(segment_file_counts) => segment_file_counts,
-            Err(err) => {
-                panic!("Failed to count final segment files: {}", err);
-            },
-        },
+    if invalid_people > 0 {
+        eprintln!("Skipped {} invalid people of {:?}", invalid_people, config.data_people_path);
     }
 }
 
-/*
- * Test D (Infinite loop!)
- *
- *   - Single final commit
- *   - Merge policy: TargetDocs
- *   - Wait for merging
- */
-async fn run_d(people: Vec<models::person::Person>) -> RunResult {
-    thread::sleep(Duration::from_secs(5));
-
-    let index = get_index().await;
+/// Runs the scenarios on their own task so that a panicking run turns into a failure exit code
+async fn run(config: Config, scenarios: Vec<Scenario>, concurrency: usize, format: OutputFormat) {
+    for (index, scenario) in scenarios.iter().enumerate() {
+        if let Err(err) = scenario.validate() {
+            usage_error(&format!("Invalid scenario {}: {}", scenario.name, err));
+        }
 
-    let start_instant = Instant::now();
+        // Runs are told apart by name, e.g. in the merge policy logs
+        if scenarios[..index].iter().any(|other| other.name == scenario.name) {
+            usage_error(&format!("Duplicate scenario {}", scenario.name));
+        }
+    }
 
-    let mut writer: IndexWriter = match index.writer(50_000_000) {
-        Ok(writer) => writer,
-        Err(err) => {
-            panic!("Failed to init writer: {}", err);
-        },
+    // No run indexes more people than it has operations
+    let limit = scenarios
+        .iter()
+        .map(|scenario| scenario.doc_count)
+        .max()
+        .unwrap_or(0);
+    check_people(&config, limit);
+
+    let results = match tokio::spawn(async move {
+        harness::run_scenarios(&config, &scenarios, concurrency).await
+    }).await {
+        Ok(results) => results,
+        Err(err) => failure(&format!("Run failed: {}", err)),
     };
 
-    let merge_policy = store::utils::TargetDocsPerSegmentPolicy::new("d".to_string(), 10000);
-    writer.set_merge_policy(merge_policy.as_box());
+    print_results(&results, format);
 
-    for person in people {
-        let document = match person.to_doc(store::PERSON_SCHEMA.clone()).await {
-            Ok(document) => document,
-            Err(err) => {
-                panic!("Failed to convert person into document: {}", err);
-            },
-        };
+    eprintln!("All done!! 🎉🎉🎉");
 
-        match writer.add_document(document) {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to add document to writer: {}", err);
-            },
-        }
-    }
+    let timed_out = results
+        .iter()
+        .any(|result| result.outcome == RunOutcome::TimedOut);
 
-    match writer.commit() {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to commit the writer: {}", err);
-        },
+    if timed_out {
+        // Merging threads of a timed out run are still spinning, exiting stops them
+        process::exit(EXIT_TIMED_OUT);
     }
+}
 
-    match writer.wait_merging_threads() {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to wait for merging threads: {}", err);
+fn print_results(results: &[RunResult], format: OutputFormat) {
+    match format {
+        OutputFormat::Table => {
+            println!("{}", harness::markdown_table(results));
+
+            if let Some(findings_table) = harness::markdown_findings_table(results) {
+                println!();
+                println!("{}", findings_table);
+            }
+
+            if let Some(workload_table) = harness::markdown_workload_table(results) {
+                println!();
+                println!("{}", workload_table);
+            }
+
+            if let Some(trials_table) = harness::markdown_trials_table(results) {
+                println!();
+                println!("{}", trials_table);
+            }
+
+            if let Some(concurrency_table) = harness::markdown_concurrency_table(results) {
+                println!();
+                println!("{}", concurrency_table);
+            }
+
+            if let Some(pipeline_table) = harness::markdown_pipeline_table(results) {
+                println!();
+                println!("{}", pipeline_table);
+            }
         },
-    }
-
-    RunResult{
-        total_index_time: format!("{:?}", start_instant.elapsed()),
-        final_segment_file_counts: match count_segment_file_counts() {
-            Ok(segment_file_counts) => segment_file_counts,
-            Err(err) => {
-                panic!("Failed to count final segment files: {}", err);
-            },
+        OutputFormat::Json => {
+            for result in results {
+                println!("{}", serde_json::json!(result));
+            }
         },
     }
 }
 
-/*
- * Test E
- *
- *   - Commit after every add_document
- *   - Merge policy: MergeWhenever
- *   - No waiting for merging
- */
-async fn run_e(people: Vec<models::person::Person>) -> RunResult {
-    thread::sleep(Duration::from_secs(5));
-
-    let index = get_index().await;
-
-    let start_instant = Instant::now();
-
-    let mut writer: IndexWriter = match index.writer(50_000_000) {
-        Ok(writer) => writer,
-        Err(err) => {
-            panic!("Failed to init writer: {}", err);
-        },
-    };
-
-    let merge_policy = store::utils::MergeWheneverPossiblePolicy::new("e".to_string());
-    writer.set_merge_policy(merge_policy.as_box());
-
-    for person in people {
-        let document = match person.to_doc(store::PERSON_SCHEMA.clone()).await {
-            Ok(document) => document,
-            Err(err) => {
-                panic!("Failed to convert person into document: {}", err);
+async fn run_command(mut args: Vec<String>) {
+    let config = load_config(&mut args);
+
+    let mut presets: Vec<String> = Vec::new();
+    let mut doc_count: Option<usize> = None;
+    let mut merge_policy: Option<MergePolicyConfig> = None;
+    let mut commit_strategy: Option<CommitStrategy> = None;
+    let mut wait_mode: Option<WaitMode> = None;
+    let mut wait_timeout: Option<Option<Duration>> = None;
+    let mut filter_degenerate_candidates = false;
+    let mut index_storage = IndexStorage::default();
+    let mut writer_threads: Option<usize> = None;
+    let mut cpu_pinning = CpuPinning::default();
+    let mut concurrency: usize = 1;
+    let mut repetitions: usize = 1;
+    let mut warmup_runs: usize = 0;
+    let mut workload = WorkloadMix::default();
+    let mut conversion_threads: Option<usize> = None;
+    let mut conversion_queue = harness::DEFAULT_CONVERSION_QUEUE;
+    let mut format = OutputFormat::Json;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scenario" => {
+                let value = flag_value(&arg, &mut args);
+                presets.extend(value.split(',').map(|preset| preset.trim().to_lowercase()));
             },
-        };
-
-        match writer.add_document(document) {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to add document to writer: {}", err);
+            "--docs" => {
+                let value = flag_value(&arg, &mut args);
+                doc_count = match value.replace('_', "").parse::<usize>() {
+                    Ok(doc_count) => Some(doc_count),
+                    Err(_) => usage_error(&format!("Invalid doc count {:?}", value)),
+                };
             },
-        }
-
-        match writer.commit() {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to commit the writer: {}", err);
+            "--policy" => {
+                let value = flag_value(&arg, &mut args);
+                merge_policy = match value.parse::<MergePolicyConfig>() {
+                    Ok(merge_policy) => Some(merge_policy),
+                    Err(err) => usage_error(&err.to_string()),
+                };
             },
-        }
-    }
-
-    RunResult{
-        total_index_time: format!("{:?}", start_instant.elapsed()),
-        final_segment_file_counts: match count_segment_file_counts() {
-            Ok(segment_file_counts) => segment_file_counts,
-            Err(err) => {
-                panic!("Failed to count final segment files: {}", err);
+            "--commit" => {
+                commit_strategy = match flag_value(&arg, &mut args).parse::<CommitStrategy>() {
+                    Ok(commit_strategy) => Some(commit_strategy),
+                    Err(err) => usage_error(&err),
+                };
             },
-        },
-    }
-}
-
-/*
- * Test F
- *
- *   - Commit after every add_document
- *   - Merge policy: MergeWhenever
- *   - Wait for merging
- */
-async fn run_f(people: Vec<models::person::Person>) -> RunResult {
-    thread::sleep(Duration::from_secs(5));
-
-    let index = get_index().await;
-
-    let start_instant = Instant::now();
-
-    let mut writer: IndexWriter = match index.writer(50_000_000) {
-        Ok(writer) => writer,
-        Err(err) => {
-            panic!("Failed to init writer: {}", err);
-        },
-    };
-
-    let merge_policy = store::utils::MergeWheneverPossiblePolicy::new("f".to_string());
-    writer.set_merge_policy(merge_policy.as_box());
-
-    for person in people {
-        let document = match person.to_doc(store::PERSON_SCHEMA.clone()).await {
-            Ok(document) => document,
-            Err(err) => {
-                panic!("Failed to convert person into document: {}", err);
+            "--wait" => wait_mode = Some(WaitMode::WaitMergingThreads),
+            "--no-wait" => wait_mode = Some(WaitMode::NoWait),
+            "--timeout" => wait_timeout = Some(parse_timeout(&flag_value(&arg, &mut args))),
+            "--filter-degenerate" => filter_degenerate_candidates = true,
+            "--storage" => {
+                index_storage = match flag_value(&arg, &mut args).as_str() {
+                    "directory" => IndexStorage::Directory,
+                    "ram" => IndexStorage::Ram,
+                    value => usage_error(&format!("Unknown storage {:?}", value)),
+                };
             },
-        };
-
-        match writer.add_document(document) {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to add document to writer: {}", err);
+            "--writer-threads" => writer_threads = Some(parse_count(&arg, &flag_value(&arg, &mut args))),
+            "--cpus" => {
+                cpu_pinning = match flag_value(&arg, &mut args).parse::<CpuPinning>() {
+                    Ok(cpu_pinning) => cpu_pinning,
+                    Err(err) => usage_error(&err),
+                };
             },
-        }
-
-        match writer.commit() {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to commit the writer: {}", err);
+            "--concurrency" => concurrency = parse_count(&arg, &flag_value(&arg, &mut args)),
+            "--repetitions" => repetitions = parse_count(&arg, &flag_value(&arg, &mut args)),
+            "--warmup" => {
+                let value = flag_value(&arg, &mut args);
+                warmup_runs = match value.parse::<usize>() {
+                    Ok(warmup_runs) => warmup_runs,
+                    Err(_) => usage_error(&format!("Invalid {} {:?}", arg, value)),
+                };
             },
+            "--workload" => {
+                workload = match flag_value(&arg, &mut args).parse::<WorkloadMix>() {
+                    Ok(workload) => workload,
+                    Err(err) => usage_error(&err),
+                };
+            },
+            "--conversion-threads" => conversion_threads = Some(parse_count(&arg, &flag_value(&arg, &mut args))),
+            "--conversion-queue" => conversion_queue = parse_count(&arg, &flag_value(&arg, &mut args)),
+            "--format" => format = parse_format(&flag_value(&arg, &mut args)),
+            _ => usage_error(&format!("Unexpected argument {:?}", arg)),
         }
     }
 
-    match writer.wait_merging_threads() {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to wait for merging threads: {}", err);
-        },
+    if presets.is_empty() {
+        presets.push("a".to_string());
     }
 
-    RunResult{
-        total_index_time: format!("{:?}", start_instant.elapsed()),
-        final_segment_file_counts: match count_segment_file_counts() {
-            Ok(segment_file_counts) => segment_file_counts,
-            Err(err) => {
-                panic!("Failed to count final segment files: {}", err);
-            },
-        },
-    }
-}
-
-/*
- * Test G
- *
- *   - Commit after every add_document
- *   - Merge policy: TargetDocs
- *   - No waiting for merging
- */
-async fn run_g(people: Vec<models::person::Person>) -> RunResult {
-    thread::sleep(Duration::from_secs(5));
-
-    let index = get_index().await;
-
-    let start_instant = Instant::now();
+    let mut scenarios: Vec<Scenario> = Vec::new();
 
-    let mut writer: IndexWriter = match index.writer(50_000_000) {
-        Ok(writer) => writer,
-        Err(err) => {
-            panic!("Failed to init writer: {}", err);
-        },
-    };
-
-    let merge_policy = store::utils::TargetDocsPerSegmentPolicy::new("g".to_string(), 10000);
-    writer.set_merge_policy(merge_policy.as_box());
+    for preset in presets {
+        let scenario = match Scenario::preset(&preset) {
+            Some(scenario) => scenario,
+            None => usage_error(&format!("Unknown scenario {:?}", preset)),
+        };
 
-    for person in people {
-        let document = match person.to_doc(store::PERSON_SCHEMA.clone()).await {
-            Ok(document) => document,
-            Err(err) => {
-                panic!("Failed to convert person into document: {}", err);
-            },
+        let overridden = doc_count.is_some()
+            || merge_policy.is_some()
+            || commit_strategy.is_some()
+            || wait_mode.is_some();
+
+        // A preset with other settings isn't the README run anymore, it gets named after them
+        let scenario = if overridden {
+            Scenario::new(
+                commit_strategy.clone().unwrap_or(scenario.commit_strategy),
+                merge_policy.clone().unwrap_or(scenario.merge_policy),
+                wait_mode.unwrap_or(scenario.wait_mode),
+                doc_count.unwrap_or(scenario.doc_count),
+                config.writer_memory_budget,
+            )
+        } else {
+            scenario.with_writer_memory_budget(config.writer_memory_budget)
         };
 
-        match writer.add_document(document) {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to add document to writer: {}", err);
-            },
-        }
+        let scenario = match wait_timeout {
+            Some(wait_timeout) => scenario.with_wait_timeout(wait_timeout),
+            None => scenario,
+        };
 
-        match writer.commit() {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to commit the writer: {}", err);
-            },
-        }
+        scenarios.push(
+            scenario
+                .with_filter_degenerate_candidates(filter_degenerate_candidates)
+                .with_index_storage(index_storage)
+                .with_writer_threads(writer_threads)
+                .with_cpu_pinning(cpu_pinning.clone())
+                .with_repetitions(repetitions, warmup_runs)
+                .with_workload(workload)
+                .with_conversion(conversion_threads, conversion_queue),
+        );
     }
 
-    RunResult{
-        total_index_time: format!("{:?}", start_instant.elapsed()),
-        final_segment_file_counts: match count_segment_file_counts() {
-            Ok(segment_file_counts) => segment_file_counts,
-            Err(err) => {
-                panic!("Failed to count final segment files: {}", err);
-            },
-        },
-    }
+    run(config, scenarios, concurrency, format).await;
 }
 
-/*
- * Test H (Infinite loop!)
- *
- *   - Commit after every add_document
- *   - Merge policy: TargetDocs
- *   - Wait for merging
- */
-async fn run_h(people: Vec<models::person::Person>) -> RunResult {
-    thread::sleep(Duration::from_secs(5));
+async fn matrix_command(mut args: Vec<String>) {
+    let config = load_config(&mut args);
 
-    let index = get_index().await;
+    let mut matrix_path: Option<String> = None;
+    let mut concurrency: usize = 1;
+    let mut format = OutputFormat::Json;
 
-    let start_instant = Instant::now();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => matrix_path = Some(flag_value(&arg, &mut args)),
+            "--concurrency" => concurrency = parse_count(&arg, &flag_value(&arg, &mut args)),
+            "--format" => format = parse_format(&flag_value(&arg, &mut args)),
+            _ => usage_error(&format!("Unexpected argument {:?}", arg)),
+        }
+    }
 
-    let mut writer: IndexWriter = match index.writer(50_000_000) {
-        Ok(writer) => writer,
-        Err(err) => {
-            panic!("Failed to init writer: {}", err);
+    let matrix = match matrix_path {
+        Some(matrix_path) => match ScenarioMatrix::read(&matrix_path) {
+            Ok(matrix) => matrix,
+            Err(err) => usage_error(&format!("Failed to read scenario matrix {}: {}", matrix_path, err)),
         },
+        None => usage_error("Missing --config"),
     };
 
-    let merge_policy = store::utils::TargetDocsPerSegmentPolicy::new("h".to_string(), 10000);
-    writer.set_merge_policy(merge_policy.as_box());
-
-    for person in people {
-        let document = match person.to_doc(store::PERSON_SCHEMA.clone()).await {
-            Ok(document) => document,
-            Err(err) => {
-                panic!("Failed to convert person into document: {}", err);
-            },
-        };
-
-        match writer.add_document(document) {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to add document to writer: {}", err);
-            },
-        }
+    run(config, matrix.scenarios(), concurrency, format).await;
+}
 
-        match writer.commit() {
-            Ok(_) => {},
-            Err(err) => {
-                panic!("Failed to commit the writer: {}", err);
+fn inspect_command(args: Vec<String>) {
+    let mut index_path: Option<String> = None;
+    let mut format = OutputFormat::Table;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = parse_format(&flag_value(&arg, &mut args)),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
             },
+            _ if index_path.is_none() && !arg.starts_with("--") => index_path = Some(arg),
+            _ => usage_error(&format!("Unexpected argument {:?}", arg)),
         }
     }
 
-    match writer.wait_merging_threads() {
-        Ok(_) => {},
-        Err(err) => {
-            panic!("Failed to wait for merging threads: {}", err);
+    let index_path = match index_path {
+        Some(index_path) => index_path,
+        None => usage_error("Missing index directory"),
+    };
+
+    let index = match Index::open_in_dir(&index_path) {
+        Ok(index) => index,
+        Err(err) => failure(&format!("Failed to open index {}: {}", index_path, err)),
+    };
+
+    let segment_inventory = match SegmentInventory::read(&index, Some(Path::new(&index_path))) {
+        Ok(segment_inventory) => segment_inventory,
+        Err(err) => failure(&format!("Failed to read segments of {}: {}", index_path, err)),
+    };
+
+    match format {
+        OutputFormat::Table => {
+            println!("== {} ==", index_path);
+            println!(
+                "{:<32}  {:>8}  {:>8}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+                "segment", "docs", "deleted", "idx", "pos", "fast", "fieldnorm", "term", "store", "del", "bytes",
+            );
+
+            for segment in segment_inventory.segments.iter() {
+                let component_bytes = &segment.component_bytes;
+
+                println!(
+                    "{:<32}  {:>8}  {:>8}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+                    segment.id,
+                    segment.num_docs,
+                    segment.num_deleted_docs,
+                    component_bytes.postings,
+                    component_bytes.positions,
+                    component_bytes.fast_fields,
+                    component_bytes.field_norms,
+                    component_bytes.terms,
+                    component_bytes.store + component_bytes.temp_store,
+                    component_bytes.delete,
+                    component_bytes.total(),
+                );
+            }
+
+            println!(
+                "{:<32}  {:>8}  {:>8}  {:>76}  {:>10}",
+                format!("{} segments", segment_inventory.segments.len()),
+                segment_inventory.num_docs(),
+                segment_inventory.num_deleted_docs(),
+                "",
+                segment_inventory.segment_bytes(),
+            );
+
+            if !segment_inventory.orphan_files.is_empty() {
+                println!();
+                println!("{:<48}  {:>10}  {:>8}", "orphan file", "bytes", "managed");
+
+                for orphan_file in segment_inventory.orphan_files.iter() {
+                    println!(
+                        "{:<48}  {:>10}  {:>8}",
+                        orphan_file.path.display(),
+                        orphan_file.bytes,
+                        if orphan_file.managed { "yes" } else { "no" },
+                    );
+                }
+            }
+        },
+        OutputFormat::Json => {
+            println!("{}", serde_json::json!({
+                "path": index_path,
+                "segments": segment_inventory.segments,
+                "orphanFiles": segment_inventory.orphan_files,
+            }));
         },
     }
+}
 
-    RunResult{
-        total_index_time: format!("{:?}", start_instant.elapsed()),
-        final_segment_file_counts: match count_segment_file_counts() {
-            Ok(segment_file_counts) => segment_file_counts,
-            Err(err) => {
-                panic!("Failed to count final segment files: {}", err);
-            },
-        },
+#[tokio::main]
+async fn main() {
+    let mut args = env::args().skip(1);
+
+    let command = match args.next() {
+        Some(command) => command,
+        None => usage_error("Missing command"),
+    };
+    let args = args.collect::<Vec<String>>();
+
+    match command.as_str() {
+        "run" => run_command(args).await,
+        "matrix" => matrix_command(args).await,
+        "inspect" => inspect_command(args),
+        "-h" | "--help" => println!("{}", USAGE),
+        _ => usage_error(&format!("Unknown command {:?}", command)),
     }
 }
@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{
+        self,
+        Write,
+    },
+    process,
+};
+
+use tantivy_merge_policy_demo::{
+    harness::{
+        Distribution,
+        MergePolicyStats,
+    },
+    store::utils,
+};
+
+const USAGE: &str = "Usage: merge-stats [--format table|json] <calls.jsonl>...";
+
+enum OutputFormat {
+    Table,
+    Json,
+}
+
+fn write_histogram<W: Write>(out: &mut W, title: &str, key_name: &str, histogram: &BTreeMap<usize, u64>) -> io::Result<()> {
+    writeln!(out, "{}", title)?;
+    writeln!(out, "  {:>10}  {:>10}", key_name, "count")?;
+
+    for (key, count) in histogram {
+        writeln!(out, "  {:>10}  {:>10}", key, count)?;
+    }
+
+    Ok(())
+}
+
+fn write_distribution<W: Write>(out: &mut W, title: &str, distribution: &Distribution) -> io::Result<()> {
+    writeln!(
+        out,
+        "{:<28} n={} min={} median={} p95={} max={} mean={:.2} std_dev={:.2}",
+        title,
+        distribution.count,
+        distribution.min,
+        distribution.median,
+        distribution.p95,
+        distribution.max,
+        distribution.mean,
+        distribution.std_dev,
+    )
+}
+
+fn write_table<W: Write>(out: &mut W, file: &str, stats: &MergePolicyStats) -> io::Result<()> {
+    writeln!(out, "== {} ==", file)?;
+    writeln!(out, "{:<28} {}", "Calls", stats.calls)?;
+    writeln!(out, "{:<28} {}", "Policy time total (µs)", stats.total_policy_time_micros)?;
+    write_distribution(out, "Policy time per call (µs)", &stats.policy_time_micros)?;
+    write_distribution(out, "Calls per commit", &stats.calls_per_commit)?;
+    write_distribution(out, "Docs per candidate", &stats.candidate_docs)?;
+    write_histogram(out, "Input sizes", "segments", &stats.input_sizes)?;
+    write_histogram(out, "Output candidate counts", "candidates", &stats.output_candidate_counts)?;
+    write_histogram(out, "Candidate sizes", "segments", &stats.candidate_sizes)?;
+    writeln!(out)
+}
+
+fn main() {
+    let mut format = OutputFormat::Table;
+    let mut files: Vec<String> = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("table") => OutputFormat::Table,
+                    Some("json") => OutputFormat::Json,
+                    _ => {
+                        eprintln!("{}", USAGE);
+                        process::exit(2);
+                    },
+                };
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ => files.push(arg),
+        }
+    }
+
+    if files.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let mut all_stats: BTreeMap<String, MergePolicyStats> = BTreeMap::new();
+
+    for file in files {
+        let calls = match utils::read_merge_policy_calls(&file) {
+            Ok(calls) => calls,
+            Err(err) => {
+                panic!("Failed to read merge policy calls from {}: {}", file, err);
+            },
+        };
+
+        all_stats.insert(file, MergePolicyStats::from_calls(&calls));
+    }
+
+    match format {
+        OutputFormat::Table => {
+            let mut stdout = io::stdout().lock();
+
+            for (file, stats) in all_stats.iter() {
+                if let Err(err) = write_table(&mut stdout, file, stats) {
+                    panic!("Failed to write merge policy stats: {}", err);
+                }
+            }
+        },
+        OutputFormat::Json => {
+            match serde_json::to_string_pretty(&all_stats) {
+                Ok(json) => println!("{}", json),
+                Err(err) => {
+                    panic!("Failed to serialize merge policy stats: {}", err);
+                },
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy_merge_policy_demo::store::utils::{
+        MergePolicyCall,
+        RecordedSegment,
+    };
+
+    use super::*;
+
+    fn call(commit: u64, segments: &[(&str, u32)], candidates: &[&[&str]], elapsed_micros: u64) -> MergePolicyCall {
+        MergePolicyCall{
+            timestamp_micros: 0,
+            commit,
+            segments: segments
+                .iter()
+                .map(|(id, num_docs)| RecordedSegment{
+                    id: id.to_string(),
+                    num_docs: *num_docs,
+                    num_deleted_docs: 0,
+                })
+                .collect(),
+            candidates: candidates
+                .iter()
+                .map(|candidate| candidate.iter().map(|id| id.to_string()).collect())
+                .collect(),
+            elapsed_micros,
+        }
+    }
+
+    #[test]
+    fn table_of_fixed_calls() {
+        // No call during commit 1
+        let calls = vec![
+            call(0, &[("a", 10), ("b", 20)], &[], 4),
+            call(0, &[("a", 10), ("b", 20), ("c", 30)], &[&["a", "b", "c"]], 10),
+            call(2, &[("d", 60), ("e", 5), ("f", 5)], &[&["e", "f"]], 6),
+            call(2, &[("d", 60), ("e", 5), ("f", 5), ("g", 40), ("h", 40)], &[&["e", "f"], &["g", "h"]], 20),
+        ];
+
+        let mut out: Vec<u8> = Vec::new();
+        write_table(&mut out, "calls.jsonl", &MergePolicyStats::from_calls(&calls)).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), concat!(
+            "== calls.jsonl ==\n",
+            "Calls                        4\n",
+            "Policy time total (µs)       40\n",
+            "Policy time per call (µs)    n=4 min=4 median=6 p95=20 max=20 mean=10.00 std_dev=7.12\n",
+            "Calls per commit             n=3 min=0 median=2 p95=2 max=2 mean=1.33 std_dev=1.15\n",
+            "Docs per candidate           n=4 min=10 median=10 p95=80 max=80 mean=40.00 std_dev=35.59\n",
+            "Input sizes\n",
+            "    segments       count\n",
+            "           2           1\n",
+            "           3           2\n",
+            "           5           1\n",
+            "Output candidate counts\n",
+            "  candidates       count\n",
+            "           0           1\n",
+            "           1           2\n",
+            "           2           1\n",
+            "Candidate sizes\n",
+            "    segments       count\n",
+            "           2           3\n",
+            "           3           1\n",
+            "\n",
+        ));
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    env,
+    process,
+};
+
+use tantivy_merge_policy_demo::harness::{
+    self,
+    RunResult,
+};
+
+const USAGE: &str = "Usage: report <results.json>...";
+
+fn main() {
+    let files = env::args().skip(1).collect::<Vec<String>>();
+
+    if files.is_empty() || files.iter().any(|file| file == "-h" || file == "--help") {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let mut results: Vec<RunResult> = Vec::new();
+
+    for file in files {
+        match harness::read_run_results(&file) {
+            Ok(file_results) => results.extend(file_results),
+            Err(err) => {
+                panic!("Failed to read run results from {}: {}", file, err);
+            },
+        }
+    }
+
+    println!("{}", harness::markdown_table(&results));
+
+    if let Some(findings_table) = harness::markdown_findings_table(&results) {
+        println!();
+        println!("{}", findings_table);
+    }
+
+    if let Some(workload_table) = harness::markdown_workload_table(&results) {
+        println!();
+        println!("{}", workload_table);
+    }
+
+    if let Some(trials_table) = harness::markdown_trials_table(&results) {
+        println!();
+        println!("{}", trials_table);
+    }
+
+    if let Some(concurrency_table) = harness::markdown_concurrency_table(&results) {
+        println!();
+        println!("{}", concurrency_table);
+    }
+
+    if let Some(pipeline_table) = harness::markdown_pipeline_table(&results) {
+        println!();
+        println!("{}", pipeline_table);
+    }
+}
@@ -1,17 +1,560 @@
-use std::env;
+use std::{
+    env,
+    error,
+    fmt,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
 
-use lazy_static::lazy_static;
+use serde::{
+    Serialize,
+    Deserialize,
+};
 
-fn get_env(name: &str) -> String {
-    match env::var(name) {
-        Ok(value) => value,
-        Err(_) => {
-            panic!("Missing env variable {}", name);
+pub const DEFAULT_DATA_PEOPLE_PATH: &str = "data/people.json";
+pub const DEFAULT_INDEX_PEOPLE_PATH: &str = "data/people";
+pub const DEFAULT_WRITER_MEMORY_BUDGET: usize = 50_000_000;
+
+/// Smallest budget tantivy accepts per indexing thread of a writer, and so for a
+/// writer with a single thread
+pub const MIN_WRITER_MEMORY_BUDGET: usize = 15_000_000;
+/// Budget per indexing thread of a writer tantivy accepts only below this one
+pub const MAX_WRITER_MEMORY_BUDGET: usize = u32::MAX as usize - 1_000_000;
+
+/// Env variable pointing to the config file, overridden by `--settings`
+pub const SETTINGS_PATH_ENV: &str = "SETTINGS_PATH";
+
+// ConfigError
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Json(PathBuf, serde_json::Error),
+    Toml(PathBuf, toml::de::Error),
+    /// Flag given as the last argument, without its value
+    MissingFlagValue(String),
+    /// Setting and the reason its value was rejected
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "Failed to access {:?}: {}", path, err),
+            ConfigError::Json(path, err) => write!(f, "Invalid JSON config {:?}: {}", path, err),
+            ConfigError::Toml(path, err) => write!(f, "Invalid TOML config {:?}: {}", path, err),
+            ConfigError::MissingFlagValue(flag) => write!(f, "Missing value of {}", flag),
+            ConfigError::InvalidValue(name, message) => write!(f, "Invalid {}: {}", name, message),
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ConfigError::Io(_, err) => Some(err),
+            ConfigError::Json(_, err) => Some(err),
+            ConfigError::Toml(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+fn parse_memory_budget(name: &str, value: &str) -> Result<usize, ConfigError> {
+    match value.trim().replace('_', "").parse::<usize>() {
+        Ok(writer_memory_budget) => Ok(writer_memory_budget),
+        Err(err) => Err(ConfigError::InvalidValue(name.to_string(), format!("{:?} is not a byte count: {}", value, err))),
+    }
+}
+
+// ConfigOverrides
+
+/// Settings of a single source, `None` leaving the value of the sources below
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigOverrides {
+    #[serde(default)]
+    pub data_people_path: Option<PathBuf>,
+    #[serde(default)]
+    pub index_people_path: Option<PathBuf>,
+    #[serde(default)]
+    pub merge_policy_log_path: Option<PathBuf>,
+    #[serde(default)]
+    pub writer_memory_budget: Option<usize>,
+}
+
+impl ConfigOverrides {
+    /// Reads `DATA_PEOPLE_PATH`, `INDEX_PEOPLE_PATH`, `MERGE_POLICY_LOG_PATH` and
+    /// `WRITER_MEMORY_BUDGET`, empty variables being ignored
+    pub fn from_env() -> Result<ConfigOverrides, ConfigError> {
+        let var = |name: &str| match env::var(name) {
+            Ok(value) if !value.is_empty() => Some(value),
+            _ => None,
+        };
+
+        Ok(ConfigOverrides{
+            data_people_path: var("DATA_PEOPLE_PATH").map(PathBuf::from),
+            index_people_path: var("INDEX_PEOPLE_PATH").map(PathBuf::from),
+            merge_policy_log_path: var("MERGE_POLICY_LOG_PATH").map(PathBuf::from),
+            writer_memory_budget: match var("WRITER_MEMORY_BUDGET") {
+                Some(value) => Some(parse_memory_budget("WRITER_MEMORY_BUDGET", &value)?),
+                None => None,
+            },
+        })
+    }
+
+    /// Reads a `.toml` file, or JSON for any other extension, keys being the
+    /// lowercase env variable names
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<ConfigOverrides, ConfigError> {
+        let path = path.as_ref();
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                return Err(ConfigError::Io(path.to_path_buf(), err));
+            },
+        };
+
+        let is_toml = match path.extension() {
+            Some(extension) => extension == "toml",
+            None => false,
+        };
+
+        if is_toml {
+            toml::from_str(&content).map_err(|err| ConfigError::Toml(path.to_path_buf(), err))
+        } else {
+            serde_json::from_str(&content).map_err(|err| ConfigError::Json(path.to_path_buf(), err))
+        }
+    }
+
+    /// Takes `--data-people-path`, `--index-people-path`, `--merge-policy-log-path`
+    /// and `--writer-memory-budget` out of `args`, as `--flag value` or `--flag=value`,
+    /// leaving the other arguments in place. `--settings`, the config file, is taken out
+    /// as well and returned.
+    pub fn from_args(args: &mut Vec<String>) -> Result<(ConfigOverrides, Option<PathBuf>), ConfigError> {
+        let mut overrides = ConfigOverrides::default();
+        let mut config_path: Option<PathBuf> = None;
+        let mut remaining: Vec<String> = Vec::new();
+
+        let mut iter = args.drain(..);
+        while let Some(arg) = iter.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+
+            let known = matches!(
+                flag.as_str(),
+                "--settings"
+                    | "--data-people-path"
+                    | "--index-people-path"
+                    | "--merge-policy-log-path"
+                    | "--writer-memory-budget"
+            );
+
+            if !known {
+                remaining.push(arg);
+                continue;
+            }
+
+            let value = match inline_value.or_else(|| iter.next()) {
+                Some(value) => value,
+                None => {
+                    return Err(ConfigError::MissingFlagValue(flag));
+                },
+            };
+
+            match flag.as_str() {
+                "--settings" => config_path = Some(PathBuf::from(value)),
+                "--data-people-path" => overrides.data_people_path = Some(PathBuf::from(value)),
+                "--index-people-path" => overrides.index_people_path = Some(PathBuf::from(value)),
+                "--merge-policy-log-path" => overrides.merge_policy_log_path = Some(PathBuf::from(value)),
+                _ => overrides.writer_memory_budget = Some(parse_memory_budget(&flag, &value)?),
+            }
+        }
+
+        drop(iter);
+        *args = remaining;
+
+        Ok((overrides, config_path))
+    }
+
+    /// Values of `other` win over the ones of `self`
+    pub fn merge(self, other: ConfigOverrides) -> ConfigOverrides {
+        ConfigOverrides{
+            data_people_path: other.data_people_path.or(self.data_people_path),
+            index_people_path: other.index_people_path.or(self.index_people_path),
+            merge_policy_log_path: other.merge_policy_log_path.or(self.merge_policy_log_path),
+            writer_memory_budget: other.writer_memory_budget.or(self.writer_memory_budget),
+        }
+    }
+}
+
+// Config
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub data_people_path: PathBuf,
+    /// Every run of the harness indexes into its own directory created in there
+    pub index_people_path: PathBuf,
+    /// Directory receiving `<scenario>.jsonl` merge policy call logs, disabled when not set
+    pub merge_policy_log_path: Option<PathBuf>,
+    pub writer_memory_budget: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config{
+            data_people_path: PathBuf::from(DEFAULT_DATA_PEOPLE_PATH),
+            index_people_path: PathBuf::from(DEFAULT_INDEX_PEOPLE_PATH),
+            merge_policy_log_path: None,
+            writer_memory_budget: DEFAULT_WRITER_MEMORY_BUDGET,
+        }
+    }
+}
+
+impl Config {
+    /// Builds the config from, by increasing precedence, the defaults, the config
+    /// file (`--settings` or `SETTINGS_PATH`), the env and the flags taken out of `args`,
+    /// then creates the index and log directories when missing and validates it.
+    pub fn load(args: &mut Vec<String>) -> Result<Config, ConfigError> {
+        let (flags, config_path) = ConfigOverrides::from_args(args)?;
+
+        let config_path = match config_path {
+            Some(config_path) => Some(config_path),
+            None => match env::var(SETTINGS_PATH_ENV) {
+                Ok(value) if !value.is_empty() => Some(PathBuf::from(value)),
+                _ => None,
+            },
+        };
+
+        let file = match config_path {
+            Some(config_path) => ConfigOverrides::from_file(config_path)?,
+            None => ConfigOverrides::default(),
+        };
+
+        let overrides = file
+            .merge(ConfigOverrides::from_env()?)
+            .merge(flags);
+
+        let config = Config::default().with_overrides(overrides);
+        config.create_dirs()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn with_overrides(self, overrides: ConfigOverrides) -> Self {
+        Config{
+            data_people_path: overrides.data_people_path.unwrap_or(self.data_people_path),
+            index_people_path: overrides.index_people_path.unwrap_or(self.index_people_path),
+            merge_policy_log_path: overrides.merge_policy_log_path.or(self.merge_policy_log_path),
+            writer_memory_budget: overrides.writer_memory_budget.unwrap_or(self.writer_memory_budget),
+        }
+    }
+
+    /// Creates the index directory and the log one, if any, when missing
+    pub fn create_dirs(&self) -> Result<(), ConfigError> {
+        create_dir(&self.index_people_path)?;
+
+        if let Some(merge_policy_log_path) = self.merge_policy_log_path.as_ref() {
+            create_dir(merge_policy_log_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the data file exists, the index and log directories exist and are
+    /// writable, and the memory budget can feed a writer thread. The share of every
+    /// thread, a setting of the scenarios, is checked by `Scenario::validate`.
+    ///
+    /// Leaves the filesystem as it found it, see `create_dirs` for the directories.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match fs::metadata(&self.data_people_path) {
+            Ok(metadata) if metadata.is_file() => {},
+            Ok(_) => {
+                return Err(ConfigError::InvalidValue(
+                    "data_people_path".to_string(),
+                    format!("{:?} is not a file", self.data_people_path),
+                ));
+            },
+            Err(err) => {
+                return Err(ConfigError::Io(self.data_people_path.clone(), err));
+            },
+        }
+
+        check_writable_dir("index_people_path", &self.index_people_path)?;
+
+        if let Some(merge_policy_log_path) = self.merge_policy_log_path.as_ref() {
+            check_writable_dir("merge_policy_log_path", merge_policy_log_path)?;
+        }
+
+        if self.writer_memory_budget < MIN_WRITER_MEMORY_BUDGET {
+            return Err(ConfigError::InvalidValue(
+                "writer_memory_budget".to_string(),
+                format!(
+                    "{} is below the {} bytes of a single writer thread",
+                    self.writer_memory_budget,
+                    MIN_WRITER_MEMORY_BUDGET,
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn create_dir(path: &Path) -> Result<(), ConfigError> {
+    match fs::create_dir_all(path) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ConfigError::Io(path.to_path_buf(), err)),
+    }
+}
+
+fn check_writable_dir(name: &str, path: &Path) -> Result<(), ConfigError> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => {},
+        Ok(_) => {
+            return Err(ConfigError::InvalidValue(
+                name.to_string(),
+                format!("{:?} is not a directory", path),
+            ));
         },
+        Err(err) => {
+            return Err(ConfigError::Io(path.to_path_buf(), err));
+        },
+    }
+
+    // Permission bits don't tell much (e.g. as root or on read-only mounts), writing does
+    let probe_path = path.join(".write-probe");
+
+    match fs::write(&probe_path, b"") {
+        Ok(_) => {},
+        Err(err) => {
+            return Err(ConfigError::Io(path.to_path_buf(), err));
+        },
+    }
+
+    match fs::remove_file(&probe_path) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ConfigError::Io(probe_path, err)),
     }
 }
 
-lazy_static! {
-    pub static ref DATA_PEOPLE_PATH: String = get_env("DATA_PEOPLE_PATH");
-    pub static ref INDEX_PEOPLE_PATH: String = get_env("INDEX_PEOPLE_PATH");
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// Directory of its own for every test, removed once dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!("config-{}-{}", process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+
+            TempDir(path)
+        }
+
+        fn file(&self, name: &str, content: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn takes_the_flags_out_of_the_args() {
+        let mut remaining = args(&[
+            "run",
+            "--data-people-path",
+            "people.ndjson",
+            "--scenario",
+            "a",
+            "--index-people-path=/tmp/index",
+            "--writer-memory-budget=100_000_000",
+            "--settings",
+            "settings.toml",
+            "--docs=10",
+        ]);
+
+        let (overrides, config_path) = ConfigOverrides::from_args(&mut remaining).unwrap();
+
+        assert_eq!(overrides, ConfigOverrides{
+            data_people_path: Some(PathBuf::from("people.ndjson")),
+            index_people_path: Some(PathBuf::from("/tmp/index")),
+            merge_policy_log_path: None,
+            writer_memory_budget: Some(100_000_000),
+        });
+        assert_eq!(config_path, Some(PathBuf::from("settings.toml")));
+        assert_eq!(remaining, args(&["run", "--scenario", "a", "--docs=10"]));
+    }
+
+    #[test]
+    fn rejects_flags_without_values() {
+        let mut remaining = args(&["run", "--index-people-path"]);
+
+        match ConfigOverrides::from_args(&mut remaining) {
+            Err(ConfigError::MissingFlagValue(flag)) => assert_eq!(flag, "--index-people-path"),
+            other => panic!("Expected a missing value, got {:?}", other),
+        }
+
+        for budget in ["--writer-memory-budget=50MB", "--writer-memory-budget=-1"] {
+            match ConfigOverrides::from_args(&mut args(&[budget])) {
+                Err(ConfigError::InvalidValue(name, _)) => assert_eq!(name, "--writer-memory-budget"),
+                other => panic!("Expected an invalid value, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn parses_separated_budgets() {
+        assert_eq!(parse_memory_budget("budget", " 50_000_000 ").unwrap(), 50_000_000);
+        assert_eq!(parse_memory_budget("budget", "15000000").unwrap(), 15_000_000);
+        assert!(parse_memory_budget("budget", "1e6").is_err());
+    }
+
+    #[test]
+    fn reads_toml_and_json_files() {
+        let dir = TempDir::new("files");
+
+        let toml = dir.file("settings.toml", "index_people_path = \"index\"\nwriter_memory_budget = 20_000_000\n");
+        let json = dir.file("settings.json", r#"{"merge_policy_log_path": "logs"}"#);
+
+        assert_eq!(ConfigOverrides::from_file(toml).unwrap(), ConfigOverrides{
+            index_people_path: Some(PathBuf::from("index")),
+            writer_memory_budget: Some(20_000_000),
+            ..ConfigOverrides::default()
+        });
+        assert_eq!(ConfigOverrides::from_file(json).unwrap(), ConfigOverrides{
+            merge_policy_log_path: Some(PathBuf::from("logs")),
+            ..ConfigOverrides::default()
+        });
+
+        let unknown_toml = dir.file("unknown.toml", "index_path = \"index\"\n");
+        let unknown_json = dir.file("unknown.json", r#"{"writerMemoryBudget": 1}"#);
+
+        assert!(matches!(ConfigOverrides::from_file(unknown_toml), Err(ConfigError::Toml(..))));
+        assert!(matches!(ConfigOverrides::from_file(unknown_json), Err(ConfigError::Json(..))));
+        assert!(matches!(ConfigOverrides::from_file(dir.0.join("missing.json")), Err(ConfigError::Io(..))));
+    }
+
+    #[test]
+    fn later_sources_win() {
+        let file = ConfigOverrides{
+            data_people_path: Some(PathBuf::from("file.json")),
+            index_people_path: Some(PathBuf::from("file-index")),
+            merge_policy_log_path: Some(PathBuf::from("file-logs")),
+            writer_memory_budget: None,
+        };
+        let env = ConfigOverrides{
+            index_people_path: Some(PathBuf::from("env-index")),
+            writer_memory_budget: Some(30_000_000),
+            ..ConfigOverrides::default()
+        };
+        let flags = ConfigOverrides{
+            writer_memory_budget: Some(40_000_000),
+            ..ConfigOverrides::default()
+        };
+
+        let config = Config::default().with_overrides(file.merge(env).merge(flags));
+
+        assert_eq!(config, Config{
+            data_people_path: PathBuf::from("file.json"),
+            index_people_path: PathBuf::from("env-index"),
+            merge_policy_log_path: Some(PathBuf::from("file-logs")),
+            writer_memory_budget: 40_000_000,
+        });
+        assert_eq!(Config::default().with_overrides(ConfigOverrides::default()), Config::default());
+    }
+
+    /// The only test touching the env, which is shared by the tests running in parallel
+    #[test]
+    fn load_layers_the_defaults_file_env_and_flags() {
+        let dir = TempDir::new("load");
+        let data = dir.file("people.json", "[]");
+        let settings = dir.file("settings.toml", &format!(
+            "data_people_path = {:?}\nindex_people_path = {:?}\nwriter_memory_budget = 20_000_000\n",
+            data,
+            dir.0.join("file-index"),
+        ));
+
+        env::set_var("INDEX_PEOPLE_PATH", dir.0.join("env-index"));
+        env::set_var("WRITER_MEMORY_BUDGET", "30_000_000");
+        env::remove_var("DATA_PEOPLE_PATH");
+        env::remove_var("MERGE_POLICY_LOG_PATH");
+        env::remove_var(SETTINGS_PATH_ENV);
+
+        let mut remaining = args(&["run", &format!("--settings={}", settings.display()), "--writer-memory-budget", "40_000_000"]);
+        let config = Config::load(&mut remaining);
+
+        let mut too_small = args(&["--settings", &settings.to_string_lossy(), "--writer-memory-budget=1_000_000"]);
+        let too_small = Config::load(&mut too_small);
+
+        env::remove_var("INDEX_PEOPLE_PATH");
+        env::remove_var("WRITER_MEMORY_BUDGET");
+
+        assert_eq!(config.unwrap(), Config{
+            data_people_path: data,
+            index_people_path: dir.0.join("env-index"),
+            merge_policy_log_path: None,
+            writer_memory_budget: 40_000_000,
+        });
+        assert_eq!(remaining, args(&["run"]));
+        // Loading creates the index directory
+        assert!(dir.0.join("env-index").is_dir());
+
+        match too_small {
+            Err(ConfigError::InvalidValue(name, _)) => assert_eq!(name, "writer_memory_budget"),
+            other => panic!("Expected the budget to be rejected, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_leaves_the_filesystem_alone() {
+        let dir = TempDir::new("validate");
+        let config = Config{
+            data_people_path: dir.file("people.json", "[]"),
+            index_people_path: dir.0.join("index"),
+            merge_policy_log_path: Some(dir.0.join("logs")),
+            writer_memory_budget: MIN_WRITER_MEMORY_BUDGET,
+        };
+
+        assert!(matches!(config.validate(), Err(ConfigError::Io(..))));
+        assert!(!config.index_people_path.exists());
+
+        config.create_dirs().unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(fs::read_dir(&config.index_people_path).unwrap().count(), 0);
+
+        let config = Config{
+            writer_memory_budget: MIN_WRITER_MEMORY_BUDGET - 1,
+            ..config
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidValue(name, _)) if name == "writer_memory_budget"));
+
+        let config = Config{
+            data_people_path: dir.0.clone(),
+            writer_memory_budget: MIN_WRITER_MEMORY_BUDGET,
+            ..config
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidValue(name, _)) if name == "data_people_path"));
+    }
 }
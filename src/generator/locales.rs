@@ -0,0 +1,180 @@
+/// Word lists of a locale, names keep the diacritics of the language
+pub(super) struct LocaleData {
+    pub female_first_names: &'static [&'static str],
+    pub male_first_names: &'static [&'static str],
+    /// Last names of men, or of everyone when `female_last_names` is empty
+    pub last_names: &'static [&'static str],
+    pub female_last_names: &'static [&'static str],
+    pub cities: &'static [&'static str],
+    pub streets: &'static [&'static str],
+    pub email_providers: &'static [&'static str],
+}
+
+pub(super) const EN_GB: LocaleData = LocaleData{
+    female_first_names: &[
+        "Amelia", "Olivia", "Isla", "Emily", "Poppy", "Ava", "Isabella", "Jessica", "Lily", "Sophie",
+        "Grace", "Sophia", "Mia", "Evie", "Ruby", "Ella", "Scarlett", "Isabelle", "Chloe", "Sienna",
+        "Freya", "Phoebe", "Charlotte", "Daisy", "Alice", "Florence", "Eva", "Harriet", "Matilda", "Imogen",
+    ],
+    male_first_names: &[
+        "Oliver", "Jack", "Harry", "Jacob", "Charlie", "Thomas", "George", "Oscar", "James", "William",
+        "Noah", "Alfie", "Joshua", "Muhammad", "Henry", "Leo", "Archie", "Ethan", "Joseph", "Freddie",
+        "Samuel", "Alexander", "Logan", "Daniel", "Isaac", "Max", "Mohammed", "Benjamin", "Mason", "Lucas",
+    ],
+    last_names: &[
+        "Smith", "Jones", "Williams", "Taylor", "Brown", "Davies", "Evans", "Wilson", "Thomas", "Johnson",
+        "Roberts", "Robinson", "Thompson", "Wright", "Walker", "White", "Edwards", "Hughes", "Green", "Hall",
+        "Lewis", "Harris", "Clarke", "Patel", "Jackson", "Wood", "Turner", "Martin", "Cooper", "Hill",
+        "Ward", "Morris", "Moore", "Clark", "Lee", "King", "Baker", "Harrison", "Morgan", "Allen",
+        "James", "Scott", "Phillips", "Watson", "Davis", "Parker", "Price", "Bennett", "Young", "Griffiths",
+        "Mitchell", "Kelly", "Cook", "Carter", "Richardson", "Bailey", "Collins", "Bell", "Shaw", "Murphy",
+        "Wilkinson", "O'Connor", "Fletcher", "Chapman", "Hargreaves",
+    ],
+    female_last_names: &[],
+    cities: &[
+        "London", "Birmingham", "Manchester", "Leeds", "Sheffield", "Bristol", "Liverpool", "Leicester", "Coventry", "Nottingham",
+        "Newcastle upon Tyne", "Brighton and Hove", "Southampton", "Portsmouth", "Plymouth", "Reading", "Derby", "Oxford", "Cambridge", "York",
+        "Edinburgh", "Glasgow", "Aberdeen", "Inverness", "Cardiff", "Swansea", "Belfast", "Bath", "Norwich", "Exeter",
+        "Stoke-on-Trent", "Milton Keynes", "Kingston upon Hull", "Stratford-upon-Avon", "St Albans",
+    ],
+    streets: &[
+        "Church", "Station", "Victoria", "Green", "Manor", "Park", "Queens", "Kings", "Grange", "Mill",
+        "Wilkinson", "Windsor", "Highfield", "Springfield", "Chestnut", "Willow", "Orchard", "Meadow", "Oak", "Ash",
+        "Beech", "Cedar", "Elm", "Hawthorn", "Holly", "Primrose", "Rectory", "School", "Chapel", "Albert",
+    ],
+    email_providers: &["gmail.com", "yahoo.co.uk", "hotmail.co.uk", "outlook.com", "btinternet.com"],
+};
+
+pub(super) const EN_US: LocaleData = LocaleData{
+    female_first_names: &[
+        "Mary", "Patricia", "Jennifer", "Linda", "Elizabeth", "Barbara", "Susan", "Jessica", "Sarah", "Karen",
+        "Nancy", "Lisa", "Betty", "Margaret", "Sandra", "Ashley", "Kimberly", "Emily", "Donna", "Michelle",
+        "Madison", "Brittany", "Kayla", "Destiny", "Hailey", "Brianna", "Savannah", "Makayla", "Aubrey", "Peyton",
+    ],
+    male_first_names: &[
+        "James", "Robert", "John", "Michael", "David", "William", "Richard", "Joseph", "Thomas", "Charles",
+        "Christopher", "Daniel", "Matthew", "Anthony", "Mark", "Donald", "Steven", "Paul", "Andrew", "Joshua",
+        "Tyler", "Brandon", "Austin", "Hunter", "Cody", "Dakota", "Chase", "Garrett", "Colton", "Jaden",
+    ],
+    last_names: &[
+        "Smith", "Johnson", "Williams", "Brown", "Jones", "Garcia", "Miller", "Davis", "Rodriguez", "Martinez",
+        "Hernandez", "Lopez", "Gonzalez", "Wilson", "Anderson", "Thomas", "Taylor", "Moore", "Jackson", "Martin",
+        "Lee", "Perez", "Thompson", "White", "Harris", "Sanchez", "Clark", "Ramirez", "Lewis", "Robinson",
+        "Walker", "Young", "Allen", "King", "Wright", "Scott", "Torres", "Nguyen", "Hill", "Flores",
+        "Hettinger", "Schowalter", "Kuhlman", "O'Keefe", "McDermott", "Runolfsson", "Bahringer", "Wunsch", "Kshlerin", "VonRueden",
+    ],
+    female_last_names: &[],
+    cities: &[
+        "New York", "Los Angeles", "Chicago", "Houston", "Phoenix", "Philadelphia", "San Antonio", "San Diego", "Dallas", "San Jose",
+        "Austin", "Jacksonville", "Fort Worth", "Columbus", "Charlotte", "Indianapolis", "San Francisco", "Seattle", "Denver", "Nashville",
+        "Oklahoma City", "El Paso", "Boston", "Portland", "Las Vegas", "Detroit", "Memphis", "Louisville", "Baltimore", "Milwaukee",
+        "Albuquerque", "Tucson", "Fresno", "Sacramento", "Kansas City", "Salt Lake City", "Coeur d'Alene", "Winston-Salem", "St. Louis", "Boise",
+    ],
+    streets: &[
+        "Main", "Oak", "Pine", "Maple", "Cedar", "Elm", "Washington", "Lake", "Hill", "Park",
+        "Hettinger", "Sunset", "Jefferson", "Lincoln", "Madison", "Franklin", "Highland", "Ridge", "Walnut", "Spruce",
+        "Cherry", "Willow", "Meadow", "River", "Forest", "Spring", "Valley", "Adams", "Jackson", "Monroe",
+    ],
+    email_providers: &["gmail.com", "yahoo.com", "hotmail.com", "outlook.com", "aol.com"],
+};
+
+pub(super) const CS_CZ: LocaleData = LocaleData{
+    female_first_names: &[
+        "Jana", "Marie", "Eva", "Hana", "Anna", "Lenka", "Kateřina", "Lucie", "Věra", "Alena",
+        "Petra", "Veronika", "Jaroslava", "Tereza", "Martina", "Michaela", "Zdeňka", "Ludmila", "Helena", "Jitka",
+        "Božena", "Šárka", "Růžena", "Dagmar", "Vlasta", "Libuše", "Blažena", "Radka", "Žaneta", "Víta",
+    ],
+    male_first_names: &[
+        "Jiří", "Jan", "Petr", "Josef", "Pavel", "Martin", "Tomáš", "Jaroslav", "Miroslav", "Zdeněk",
+        "Václav", "Michal", "František", "Jakub", "Milan", "Karel", "Lukáš", "David", "Vladimír", "Ondřej",
+        "Stanislav", "Radek", "Luboš", "Matěj", "Dušan", "Vojtěch", "Bořivoj", "Přemysl", "Čestmír", "Vít",
+    ],
+    last_names: &[
+        "Novák", "Svoboda", "Novotný", "Dvořák", "Černý", "Procházka", "Kučera", "Veselý", "Horák", "Němec",
+        "Pokorný", "Marek", "Pospíšil", "Hájek", "Jelínek", "Král", "Růžička", "Beneš", "Fiala", "Sedláček",
+        "Doležal", "Zeman", "Kolář", "Navrátil", "Čermák", "Vaněk", "Urban", "Blažek", "Kříž", "Kovář",
+        "Hanousek", "Šimek", "Bartoš", "Vlček", "Polák", "Kopecký", "Musil", "Konečný", "Malý", "Štěpánek",
+    ],
+    female_last_names: &[
+        "Nováková", "Svobodová", "Novotná", "Dvořáková", "Černá", "Procházková", "Kučerová", "Veselá", "Horáková", "Němcová",
+        "Pokorná", "Marková", "Pospíšilová", "Hájková", "Jelínková", "Králová", "Růžičková", "Benešová", "Fialová", "Sedláčková",
+        "Doležalová", "Zemanová", "Kolářová", "Navrátilová", "Čermáková", "Vaňková", "Urbanová", "Blažková", "Křížová", "Kovářová",
+        "Hanousková", "Šimková", "Bartošová", "Vlčková", "Poláková", "Kopecká", "Musilová", "Konečná", "Malá", "Štěpánková",
+    ],
+    cities: &[
+        "Praha", "Brno", "Ostrava", "Plzeň", "Liberec", "Olomouc", "České Budějovice", "Hradec Králové", "Ústí nad Labem", "Pardubice",
+        "Zlín", "Havířov", "Kladno", "Most", "Opava", "Frýdek-Místek", "Karviná", "Jihlava", "Teplice", "Děčín",
+        "Karlovy Vary", "Chomutov", "Jablonec nad Nisou", "Mladá Boleslav", "Prostějov", "Přerov", "Česká Lípa", "Třebíč", "Třinec", "Tábor",
+        "Znojmo", "Příbram", "Cheb", "Kolín", "Trutnov", "Písek", "Kroměříž", "Šumperk", "Vsetín", "Nové Město nad Metují",
+    ],
+    streets: &[
+        "Elektrárenská", "Husova", "Masarykova", "Palackého", "Nádražní", "Školní", "Zahradní", "Komenského", "Sokolská", "Tyršova",
+        "Lidická", "Smetanova", "Dvořákova", "Jiráskova", "Nerudova", "Žižkova", "Křižíkova", "Vinohradská", "Sadová", "Lipová",
+        "Družstevní", "Polní", "Luční", "Květná", "Šafaříkova", "Čechova", "Mánesova", "Štefánikova", "Náměstí Míru", "Pražská",
+    ],
+    email_providers: &["seznam.cz", "email.cz", "centrum.cz", "atlas.cz", "post.cz", "gmail.com"],
+};
+
+pub(super) const FR: LocaleData = LocaleData{
+    female_first_names: &[
+        "Marie", "Camille", "Léa", "Manon", "Chloé", "Inès", "Océane", "Clémence", "Émilie", "Hélène",
+        "Élodie", "Zoé", "Léonie", "Anaïs", "Maëlle", "Noémie", "Céline", "Cécile", "Adèle", "Gaëlle",
+        "Béatrice", "Mathilde", "Pauline", "Juliette", "Margaux", "Aurélie", "Sophie", "Lucie", "Françoise", "Geneviève",
+    ],
+    male_first_names: &[
+        "Jean", "Pierre", "Louis", "Lucas", "Hugo", "Théo", "Mathéo", "Raphaël", "Gabriel", "Noé",
+        "Jérôme", "Frédéric", "Stéphane", "Sébastien", "Clément", "Benoît", "Joël", "Anaël", "Loïc", "Gaëtan",
+        "François", "Nicolas", "Julien", "Antoine", "Maxime", "Thomas", "Alexandre", "Étienne", "Rémi", "Valentin",
+    ],
+    last_names: &[
+        "Martin", "Bernard", "Dubois", "Thomas", "Robert", "Richard", "Petit", "Durand", "Leroy", "Moreau",
+        "Simon", "Laurent", "Lefèbvre", "Michel", "Garcia", "David", "Bertrand", "Roux", "Vincent", "Fournier",
+        "Morel", "Girard", "André", "Lefèvre", "Mercier", "Dupont", "Lambert", "Bonnet", "François", "Martinez",
+        "Légaré", "Bérubé", "Gagné", "Côté", "Lévesque", "Ménard", "Thérien", "Hébert", "Chevalier", "Benoît",
+    ],
+    female_last_names: &[],
+    cities: &[
+        "Paris", "Marseille", "Lyon", "Toulouse", "Nice", "Nantes", "Montpellier", "Strasbourg", "Bordeaux", "Lille",
+        "Rennes", "Reims", "Toulon", "Saint-Étienne", "Le Havre", "Grenoble", "Dijon", "Angers", "Nîmes", "Villeurbanne",
+        "Clermont-Ferrand", "Le Mans", "Aix-en-Provence", "Brest", "Tours", "Amiens", "Limoges", "Besançon", "Orléans", "Béziers",
+        "Sète", "Fréjus", "Évreux", "Châteauroux", "Créteil",
+    ],
+    streets: &[
+        "Rue de la Paix", "Rue du Chat-qui-Pêche", "Rue de Rivoli", "Rue des Écoles", "Rue de l'Église", "Rue du Moulin", "Rue de la Gare", "Rue Pasteur", "Rue Victor Hugo", "Rue Jean Jaurès",
+        "Rue de la Chaussée-d'Antin", "Rue du Faubourg Saint-Honoré", "Rue Saint-Dominique", "Rue de Vaugirard", "Rue des Rosiers", "Avenue des Champs-Élysées", "Avenue de la République", "Avenue Foch", "Avenue de l'Opéra", "Avenue du Général Leclerc",
+        "Boulevard Haussmann", "Boulevard Saint-Michel", "Boulevard de la Madeleine", "Boulevard Voltaire", "Place de la Concorde", "Place Vendôme", "Place du Théâtre", "Impasse des Lilas", "Quai d'Orsay", "Allée des Châtaigniers",
+    ],
+    email_providers: &["gmail.com", "yahoo.fr", "hotmail.fr", "orange.fr", "free.fr", "laposte.net"],
+};
+
+pub(super) const DE: LocaleData = LocaleData{
+    female_first_names: &[
+        "Anna", "Emma", "Mia", "Hannah", "Sophie", "Lena", "Lea", "Leonie", "Johanna", "Marie",
+        "Jördis", "Gisela", "Ursula", "Brigitte", "Käthe", "Jörgine", "Bärbel", "Renée", "Anneliese", "Gertrud",
+        "Charlotte", "Greta", "Frieda", "Clara", "Lotte", "Maximiliane", "Helene", "Ilse", "Margarete", "Henriette",
+    ],
+    male_first_names: &[
+        "Lukas", "Leon", "Finn", "Jonas", "Paul", "Felix", "Maximilian", "Elias", "Noah", "Ben",
+        "Jürgen", "Günter", "Jörg", "Björn", "Sören", "Klaus", "Dieter", "Horst", "Uwe", "Wolfgang",
+        "Friedrich", "Heinrich", "Karl", "Hans", "Matthäus", "Moritz", "Anton", "Emil", "Konrad", "Rüdiger",
+    ],
+    last_names: &[
+        "Müller", "Schmidt", "Schneider", "Fischer", "Weber", "Meyer", "Wagner", "Becker", "Schulz", "Hoffmann",
+        "Schäfer", "Koch", "Bauer", "Richter", "Klein", "Wolf", "Schröder", "Neumann", "Schwarz", "Zimmermann",
+        "Braun", "Krüger", "Hofmann", "Hartmann", "Lange", "Schmitt", "Werner", "Schmitz", "Krause", "Meier",
+        "Köhler", "Jäger", "Günther", "Böhm", "Weiß", "Groß", "Kühn", "Büttner", "Lührs", "Vogt",
+    ],
+    female_last_names: &[],
+    cities: &[
+        "Berlin", "Hamburg", "München", "Köln", "Frankfurt am Main", "Stuttgart", "Düsseldorf", "Leipzig", "Dortmund", "Essen",
+        "Bremen", "Dresden", "Hannover", "Nürnberg", "Duisburg", "Bochum", "Wuppertal", "Bielefeld", "Bonn", "Münster",
+        "Mönchengladbach", "Saarbrücken", "Lübeck", "Göttingen", "Würzburg", "Fürth", "Osnabrück", "Lüneburg", "Tübingen", "Gießen",
+        "Zwickau", "Görlitz", "Neuss", "Offenbach am Main", "Freiburg im Breisgau",
+    ],
+    streets: &[
+        "Hauptstr.", "Schulstr.", "Gartenstr.", "Bahnhofstr.", "Dorfstr.", "Bergstr.", "Birkenweg", "Lindenstr.", "Kirchstr.", "Waldstr.",
+        "Friedrich-Engels-Str.", "Goethestraße", "Schillerstraße", "Mozartstraße", "Am Mühlbach", "Königsallee", "Lessingstr.", "Blumenstr.", "Rosenweg", "Buchenweg",
+        "Wiesenstraße", "Brückenstraße", "Kastanienallee", "Fröbelstraße", "Tulpenweg", "Marktplatz", "Am Sägewerk", "Grüner Weg", "Heinrich-Heine-Str.", "Lärchenweg",
+    ],
+    email_providers: &["gmail.com", "web.de", "gmx.de", "t-online.de", "yahoo.de", "hotmail.de"],
+};
@@ -0,0 +1,384 @@
+mod locales;
+
+use std::{
+    fmt,
+    str::FromStr,
+};
+use rand::{
+    Rng,
+    SeedableRng,
+    rngs::StdRng,
+    seq::SliceRandom,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+
+use super::models::{
+    address::Address,
+    person::{
+        Person,
+        PersonSettings,
+    },
+};
+use locales::LocaleData;
+
+// Locale
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    EnGb,
+    EnUs,
+    CsCz,
+    Fr,
+    De,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 5] = [
+        Locale::EnGb,
+        Locale::EnUs,
+        Locale::CsCz,
+        Locale::Fr,
+        Locale::De,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::EnGb => "en_gb",
+            Locale::EnUs => "en_us",
+            Locale::CsCz => "cs_cz",
+            Locale::Fr => "fr",
+            Locale::De => "de",
+        }
+    }
+
+    /// Country of the addresses, as stored in `Address::country`
+    pub fn country(&self) -> &'static str {
+        match self {
+            Locale::EnGb => "gb",
+            Locale::EnUs => "us",
+            Locale::CsCz => "cz",
+            Locale::Fr => "fr",
+            Locale::De => "de",
+        }
+    }
+
+    fn data(&self) -> &'static LocaleData {
+        match self {
+            Locale::EnGb => &locales::EN_GB,
+            Locale::EnUs => &locales::EN_US,
+            Locale::CsCz => &locales::CS_CZ,
+            Locale::Fr => &locales::FR,
+            Locale::De => &locales::DE,
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match Locale::ALL.iter().find(|locale| locale.as_str() == value) {
+            Some(locale) => Ok(*locale),
+            None => Err(format!(
+                "Unknown locale {:?}, expected one of {}",
+                value,
+                Locale::ALL.map(|locale| locale.as_str()).join(", "),
+            )),
+        }
+    }
+}
+
+// Helpers
+
+fn pick<'a>(rng: &mut StdRng, values: &[&'a str]) -> &'a str {
+    values.choose(rng).copied().unwrap_or_default()
+}
+
+fn uppercase_letter(rng: &mut StdRng) -> char {
+    rng.gen_range(b'A'..=b'Z') as char
+}
+
+/// Random bytes formatted as a version 4 UUID
+fn uuid(rng: &mut StdRng) -> String {
+    let mut bytes: [u8; 16] = rng.gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+/// Drops the diacritics of the letters used by the locales, e.g. `Hanousková` to
+/// `Hanouskova`, and every character not allowed in the local part of an email
+fn email_part(name: &str) -> String {
+    let mut part = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        let folded = match c {
+            'á' | 'à' | 'â' | 'ä' => "a",
+            'Á' | 'À' | 'Â' | 'Ä' => "A",
+            'č' | 'ç' => "c",
+            'Č' | 'Ç' => "C",
+            'ď' => "d",
+            'Ď' => "D",
+            'é' | 'è' | 'ê' | 'ë' | 'ě' => "e",
+            'É' | 'È' | 'Ê' | 'Ë' | 'Ě' => "E",
+            'í' | 'î' | 'ï' => "i",
+            'Í' | 'Î' | 'Ï' => "I",
+            'ň' => "n",
+            'Ň' => "N",
+            'ó' | 'ô' | 'ö' => "o",
+            'Ó' | 'Ô' | 'Ö' => "O",
+            'ř' => "r",
+            'Ř' => "R",
+            'š' => "s",
+            'Š' => "S",
+            'ß' => "ss",
+            'ť' => "t",
+            'Ť' => "T",
+            'ú' | 'ù' | 'û' | 'ü' | 'ů' => "u",
+            'Ú' | 'Ù' | 'Û' | 'Ü' | 'Ů' => "U",
+            'ý' | 'ÿ' => "y",
+            'Ý' => "Y",
+            'ž' => "z",
+            'Ž' => "Z",
+            'æ' => "ae",
+            'œ' => "oe",
+            c if c.is_ascii_alphanumeric() => {
+                part.push(c);
+                continue;
+            },
+            _ => "",
+        };
+
+        part.push_str(folded);
+    }
+
+    part
+}
+
+// PersonGenerator
+
+/// Seeded generator of people spread uniformly over `locales`, the same seed always
+/// yielding the same people
+pub struct PersonGenerator {
+    rng: StdRng,
+    locales: Vec<Locale>,
+}
+
+impl PersonGenerator {
+    pub fn new(seed: u64) -> Self {
+        PersonGenerator{
+            rng: StdRng::seed_from_u64(seed),
+            locales: Locale::ALL.to_vec(),
+        }
+    }
+
+    pub fn with_locales(mut self, locales: Vec<Locale>) -> Self {
+        self.locales = locales;
+        self
+    }
+
+    pub fn next_person(&mut self) -> Person {
+        let locale = match self.locales.choose(&mut self.rng) {
+            Some(locale) => *locale,
+            None => Locale::EnGb,
+        };
+        let data = locale.data();
+
+        let id = uuid(&mut self.rng);
+        let female = self.rng.gen_bool(0.5);
+
+        let first_name = if female {
+            pick(&mut self.rng, data.female_first_names)
+        } else {
+            pick(&mut self.rng, data.male_first_names)
+        };
+        let last_name = if female && !data.female_last_names.is_empty() {
+            pick(&mut self.rng, data.female_last_names)
+        } else {
+            pick(&mut self.rng, data.last_names)
+        };
+        let email = self.email(data, first_name, last_name);
+
+        let address = Address{
+            country: Some(locale.country().to_string()),
+            zip_code: Some(self.zip_code(locale)),
+            city: Some(pick(&mut self.rng, data.cities).to_string()),
+            line_1: Some(self.street_address(locale, data)),
+            line_2: None,
+        };
+
+        Person{
+            id,
+            first_name: first_name.to_string(),
+            last_name: last_name.to_string(),
+            sex: if female { "female" } else { "male" }.to_string(),
+            email,
+            address: Some(address),
+            settings: Some(PersonSettings{
+                locale: Some(locale.to_string()),
+            }),
+        }
+    }
+
+    pub fn generate(&mut self, count: usize) -> Vec<Person> {
+        self.by_ref().take(count).collect()
+    }
+
+    fn email(&mut self, data: &LocaleData, first_name: &str, last_name: &str) -> String {
+        let first_name = email_part(first_name);
+        let last_name = email_part(last_name);
+
+        let local_part = match self.rng.gen_range(0..4) {
+            0 => format!("{}.{}", first_name, last_name),
+            1 => format!("{}_{}", first_name, last_name),
+            2 => format!("{}.{}{}", first_name, last_name, self.rng.gen_range(1..100)),
+            _ => format!("{}{}", first_name, self.rng.gen_range(1..100)),
+        };
+
+        format!("{}@{}", local_part, pick(&mut self.rng, data.email_providers))
+    }
+
+    fn zip_code(&mut self, locale: Locale) -> String {
+        let rng = &mut self.rng;
+
+        match locale {
+            // Outward and inward codes, e.g. `IV0 7KG`
+            Locale::EnGb => {
+                let area = if rng.gen_bool(0.5) {
+                    format!("{}{}", uppercase_letter(rng), uppercase_letter(rng))
+                } else {
+                    uppercase_letter(rng).to_string()
+                };
+
+                format!(
+                    "{}{} {}{}{}",
+                    area,
+                    rng.gen_range(0..30),
+                    rng.gen_range(0..10),
+                    uppercase_letter(rng),
+                    uppercase_letter(rng),
+                )
+            },
+            // ZIP or ZIP+4, e.g. `84737-7220`
+            Locale::EnUs => {
+                if rng.gen_bool(0.5) {
+                    format!("{:05}-{:04}", rng.gen_range(1000..100000), rng.gen_range(0..10000))
+                } else {
+                    format!("{:05}", rng.gen_range(1000..100000))
+                }
+            },
+            // PSČ, e.g. `162 63`
+            Locale::CsCz => format!("{} {:02}", rng.gen_range(100..800), rng.gen_range(0..100)),
+            // Département followed by the office, e.g. `74998`
+            Locale::Fr => format!("{:02}{:03}", rng.gen_range(1..96), rng.gen_range(0..1000)),
+            Locale::De => format!("{:05}", rng.gen_range(1067..100000)),
+        }
+    }
+
+    fn street_address(&mut self, locale: Locale, data: &LocaleData) -> String {
+        let street = pick(&mut self.rng, data.streets);
+        let rng = &mut self.rng;
+
+        match locale {
+            Locale::EnGb => {
+                let suffix = pick(rng, &["Road", "Street", "Lane", "Close", "Avenue", "Field", "Gardens", "Mews", "Way", "Crescent"]);
+                format!("{} {} {}", rng.gen_range(1..200), street, suffix)
+            },
+            Locale::EnUs => {
+                let suffix = pick(rng, &["Street", "Avenue", "Boulevard", "Drive", "Court", "Parkway", "Skyway", "Road", "Lane", "Way"]);
+                format!("{} {} {}", rng.gen_range(1..100000), street, suffix)
+            },
+            // Descriptive number, often followed by the orientation number, e.g. `Husova 1234/5`
+            Locale::CsCz => {
+                if rng.gen_bool(0.3) {
+                    format!("{} {}/{}", street, rng.gen_range(1..3000), rng.gen_range(1..100))
+                } else {
+                    format!("{} {}", street, rng.gen_range(1..300))
+                }
+            },
+            Locale::Fr => {
+                if rng.gen_bool(0.1) {
+                    format!("{} bis {}", rng.gen_range(1..300), street)
+                } else {
+                    format!("{} {}", rng.gen_range(1..300), street)
+                }
+            },
+            Locale::De => {
+                if rng.gen_bool(0.2) {
+                    format!("{} {}{}", street, rng.gen_range(1..200), rng.gen_range(b'a'..=b'f') as char)
+                } else {
+                    format!("{} {}", street, rng.gen_range(1..200))
+                }
+            },
+        }
+    }
+}
+
+impl Iterator for PersonGenerator {
+    type Item = Person;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_person())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn people(generator: PersonGenerator, count: usize) -> Vec<String> {
+        generator
+            .take(count)
+            .map(|person| format!("{:?}", person))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_yields_the_same_people() {
+        assert_eq!(people(PersonGenerator::new(7), 200), people(PersonGenerator::new(7), 200));
+
+        let other_seed = people(PersonGenerator::new(8), 200);
+        assert!(people(PersonGenerator::new(7), 200)
+            .iter()
+            .zip(&other_seed)
+            .all(|(person, other)| person != other));
+    }
+
+    #[test]
+    fn generate_carries_on_where_it_left() {
+        let mut generator = PersonGenerator::new(7);
+        let generated = generator
+            .generate(30)
+            .into_iter()
+            .chain(generator.generate(20))
+            .map(|person| format!("{:?}", person))
+            .collect::<Vec<String>>();
+
+        assert_eq!(generated, people(PersonGenerator::new(7), 50));
+    }
+
+    #[test]
+    fn people_come_from_the_given_locales() {
+        let generator = PersonGenerator::new(7).with_locales(vec![Locale::CsCz, Locale::Fr]);
+
+        for person in generator.take(100) {
+            let locale = person.settings.and_then(|settings| settings.locale).unwrap();
+            let country = person.address.and_then(|address| address.country).unwrap();
+
+            assert!(locale == Locale::CsCz.to_string() || locale == Locale::Fr.to_string(), "{}", locale);
+            assert_eq!(country, locale.parse::<Locale>().unwrap().country());
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use rand::{
+    Rng,
+    SeedableRng,
+    rngs::StdRng,
+};
+
+use super::super::models::person::Person;
+use super::scenario::CommitStrategy;
+
+// CommitTrigger
+
+/// Decides when the runner commits, and how documents arrive, for a commit strategy
+pub trait CommitTrigger: Send {
+    /// Time to wait before adding the next document, modelling the arrival pattern
+    fn before_document(&mut self) -> Option<Duration> {
+        None
+    }
+
+    /// Called after every added document, returns whether to commit now
+    fn after_document(&mut self, person: &Person) -> bool;
+
+    fn after_commit(&mut self) {}
+}
+
+impl CommitStrategy {
+    pub fn trigger(&self) -> Result<Box<dyn CommitTrigger>, String> {
+        self.validate()?;
+
+        Ok(match self {
+            CommitStrategy::Single => Box::new(Never),
+            CommitStrategy::EveryDocument => Box::new(EveryDocs::new(1)),
+            CommitStrategy::EveryDocs{ docs } => Box::new(EveryDocs::new(*docs)),
+            CommitStrategy::Interval{ millis } => Box::new(Interval::new(Duration::from_millis(*millis))),
+            CommitStrategy::Bytes{ bytes } => Box::new(Bytes::new(*bytes)),
+            CommitStrategy::Poisson{ docs_per_second, commit_millis, seed } => Box::new(Poisson::new(
+                *docs_per_second,
+                Duration::from_millis(*commit_millis),
+                *seed,
+            )),
+            CommitStrategy::Bursty{ min_burst_docs, max_burst_docs, min_idle_millis, max_idle_millis, seed } => {
+                Box::new(Bursty::new(
+                    (*min_burst_docs, *max_burst_docs),
+                    (*min_idle_millis, *max_idle_millis),
+                    *seed,
+                ))
+            },
+        })
+    }
+}
+
+// Never
+
+/// Only the final commit of the runner
+struct Never;
+
+impl CommitTrigger for Never {
+    fn after_document(&mut self, _person: &Person) -> bool {
+        false
+    }
+}
+
+// EveryDocs
+
+struct EveryDocs {
+    docs: usize,
+    pending: usize,
+}
+
+impl EveryDocs {
+    fn new(docs: usize) -> Self {
+        EveryDocs{
+            docs,
+            pending: 0,
+        }
+    }
+}
+
+impl CommitTrigger for EveryDocs {
+    fn after_document(&mut self, _person: &Person) -> bool {
+        self.pending += 1;
+        self.pending >= self.docs
+    }
+
+    fn after_commit(&mut self) {
+        self.pending = 0;
+    }
+}
+
+// Interval
+
+struct Interval {
+    interval: Duration,
+    committed_at: Instant,
+}
+
+impl Interval {
+    fn new(interval: Duration) -> Self {
+        Interval{
+            interval,
+            committed_at: Instant::now(),
+        }
+    }
+}
+
+impl CommitTrigger for Interval {
+    fn after_document(&mut self, _person: &Person) -> bool {
+        self.committed_at.elapsed() >= self.interval
+    }
+
+    fn after_commit(&mut self) {
+        self.committed_at = Instant::now();
+    }
+}
+
+// Bytes
+
+struct Bytes {
+    bytes: u64,
+    pending_bytes: u64,
+}
+
+impl Bytes {
+    fn new(bytes: u64) -> Self {
+        Bytes{
+            bytes,
+            pending_bytes: 0,
+        }
+    }
+}
+
+impl CommitTrigger for Bytes {
+    fn after_document(&mut self, person: &Person) -> bool {
+        // The JSON the person was read from stands in for the size of the document
+        self.pending_bytes += person.json_len() as u64;
+
+        self.pending_bytes >= self.bytes
+    }
+
+    fn after_commit(&mut self) {
+        self.pending_bytes = 0;
+    }
+}
+
+// Poisson
+
+struct Poisson {
+    mean_interarrival_secs: f64,
+    interval: Interval,
+    rng: StdRng,
+}
+
+impl Poisson {
+    fn new(docs_per_second: u64, commit_interval: Duration, seed: u64) -> Self {
+        Poisson{
+            mean_interarrival_secs: 1.0 / docs_per_second as f64,
+            interval: Interval::new(commit_interval),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl CommitTrigger for Poisson {
+    fn before_document(&mut self) -> Option<Duration> {
+        // Inverse transform sampling of the exponential distribution
+        let uniform: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+        Some(Duration::from_secs_f64(-uniform.ln() * self.mean_interarrival_secs))
+    }
+
+    fn after_document(&mut self, person: &Person) -> bool {
+        self.interval.after_document(person)
+    }
+
+    fn after_commit(&mut self) {
+        self.interval.after_commit();
+    }
+}
+
+// Bursty
+
+struct Bursty {
+    burst_docs: (usize, usize),
+    idle_millis: (u64, u64),
+    rng: StdRng,
+    /// Documents left in the current burst
+    remaining: usize,
+    /// Idle time to wait before the next burst
+    idle: Option<Duration>,
+}
+
+impl Bursty {
+    fn new(burst_docs: (usize, usize), idle_millis: (u64, u64), seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let remaining = rng.gen_range(burst_docs.0..=burst_docs.1);
+
+        Bursty{
+            burst_docs,
+            idle_millis,
+            rng,
+            remaining,
+            idle: None,
+        }
+    }
+}
+
+impl CommitTrigger for Bursty {
+    fn before_document(&mut self) -> Option<Duration> {
+        self.idle.take()
+    }
+
+    fn after_document(&mut self, _person: &Person) -> bool {
+        self.remaining = self.remaining.saturating_sub(1);
+        self.remaining == 0
+    }
+
+    fn after_commit(&mut self) {
+        self.remaining = self.rng.gen_range(self.burst_docs.0..=self.burst_docs.1);
+        self.idle = Some(Duration::from_millis(self.rng.gen_range(self.idle_millis.0..=self.idle_millis.1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::generator::PersonGenerator;
+
+    /// Documents added before every commit
+    fn commit_sizes(commit_strategy: CommitStrategy, people: &[Person]) -> Vec<usize> {
+        let mut trigger = commit_strategy.trigger().unwrap();
+        let mut sizes: Vec<usize> = Vec::new();
+        let mut pending = 0;
+
+        for person in people {
+            pending += 1;
+
+            if trigger.after_document(person) {
+                sizes.push(pending);
+                pending = 0;
+                trigger.after_commit();
+            }
+        }
+
+        sizes
+    }
+
+    #[test]
+    fn commits_every_docs() {
+        let people = PersonGenerator::new(5).generate(10);
+
+        assert_eq!(commit_sizes(CommitStrategy::Single, &people), Vec::<usize>::new());
+        assert_eq!(commit_sizes(CommitStrategy::EveryDocument, &people), vec![1; 10]);
+        assert_eq!(commit_sizes(CommitStrategy::EveryDocs{ docs: 4 }, &people), vec![4, 4]);
+    }
+
+    #[test]
+    fn commits_once_the_bytes_are_reached() {
+        let people = PersonGenerator::new(5).generate(100);
+        let bytes = people[..3].iter().map(|person| person.json_len() as u64).sum::<u64>();
+
+        let sizes = commit_sizes(CommitStrategy::Bytes{ bytes }, &people);
+
+        assert_eq!(sizes[0], 3);
+        assert!(sizes.iter().all(|size| (2..=5).contains(size)), "{:?}", sizes);
+    }
+}
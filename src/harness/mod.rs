@@ -0,0 +1,77 @@
+mod scenario;
+pub use scenario::{
+    Scenario,
+    ScenarioMatrix,
+    CommitStrategy,
+    CpuPinning,
+    IndexStorage,
+    MergePolicyConfig,
+    WaitMode,
+    WorkloadMix,
+    DEFAULT_DOC_COUNT,
+    DEFAULT_WRITER_MEMORY_BUDGET,
+    DEFAULT_TARGET_DOCS_PER_SEGMENT,
+    DEFAULT_WAIT_TIMEOUT,
+    DEFAULT_CONVERSION_QUEUE,
+};
+
+mod commit;
+pub use commit::CommitTrigger;
+
+mod pipeline;
+pub use pipeline::{
+    ConversionPipeline,
+    ConvertedOperation,
+    PipelineMetrics,
+    StageMetrics,
+};
+
+mod result;
+pub use result::{
+    RunResult,
+    RunOutcome,
+    RunConcurrency,
+    SegmentFileCounts,
+    TrialResult,
+};
+
+mod stats;
+pub use stats::{
+    MergePolicyStats,
+    Distribution,
+    TrialStats,
+};
+
+mod runner;
+pub use runner::{
+    run_scenario,
+    run_scenarios,
+};
+
+mod report;
+pub use report::{
+    markdown_table,
+    markdown_findings_table,
+    markdown_concurrency_table,
+    markdown_pipeline_table,
+    markdown_trials_table,
+    markdown_workload_table,
+    read_run_results,
+    parse_debug_duration,
+};
+
+mod pinning;
+pub use pinning::{
+    available_cpus,
+    pin_current_thread,
+};
+
+mod workload;
+pub use workload::{
+    Operation,
+    WorkloadCounts,
+    WorkloadGenerator,
+};
+
+mod watchdog;
+pub use watchdog::wait_merging_threads;
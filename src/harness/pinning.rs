@@ -0,0 +1,78 @@
+use std::{
+    io,
+    thread,
+};
+
+/// CPUs the current thread may be scheduled on
+#[cfg(target_os = "linux")]
+pub fn available_cpus() -> Vec<usize> {
+    // SAFETY: `cpu_set_t` is a plain bitmask, zeroed is an empty set
+    let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+
+    let result = unsafe { libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut cpu_set) };
+    if result != 0 {
+        return fallback_cpus();
+    }
+
+    (0..libc::CPU_SETSIZE as usize)
+        .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &cpu_set) })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn available_cpus() -> Vec<usize> {
+    fallback_cpus()
+}
+
+fn fallback_cpus() -> Vec<usize> {
+    match thread::available_parallelism() {
+        Ok(parallelism) => (0..parallelism.get()).collect(),
+        Err(_) => vec![0],
+    }
+}
+
+/// Pins the current thread to `cpus`, threads it spawns afterwards (e.g. the
+/// indexing and merging threads of a writer) inheriting the pinning
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(cpus: &[usize]) -> Result<(), io::Error> {
+    if cpus.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "No CPUs to pin to"));
+    }
+
+    // SAFETY: `cpu_set_t` is a plain bitmask, zeroed is an empty set
+    let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+
+    for cpu in cpus {
+        if *cpu >= libc::CPU_SETSIZE as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("CPU {} out of range", cpu)));
+        }
+
+        unsafe { libc::CPU_SET(*cpu, &mut cpu_set) };
+    }
+
+    let result = unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &cpu_set) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current_thread(_cpus: &[usize]) -> Result<(), io::Error> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "CPU pinning is only supported on Linux"))
+}
+
+/// CPUs of `slot` out of `slots`, the available CPUs being split into even chunks,
+/// slots beyond the number of CPUs wrapping around
+pub fn slot_cpus(available_cpus: &[usize], slot: usize, slots: usize) -> Vec<usize> {
+    if available_cpus.is_empty() {
+        return vec![];
+    }
+
+    let chunk = (available_cpus.len() / slots.max(1)).max(1);
+    let chunks = available_cpus.len() / chunk;
+    let start = (slot % chunks) * chunk;
+
+    available_cpus[start..start + chunk].to_vec()
+}
@@ -0,0 +1,432 @@
+use std::{
+    panic,
+    sync::mpsc::{
+        self,
+        Receiver,
+        SyncSender,
+    },
+    thread::{
+        self,
+        Scope,
+        ScopedJoinHandle,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use tantivy::TantivyDocument;
+
+use super::super::store::people::PersonFields;
+use super::workload::Operation;
+
+// StageMetrics
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StageMetrics {
+    pub threads: usize,
+    /// Operations which went through the stage
+    pub items: u64,
+    /// Time spent on the operations, summed over the threads of the stage
+    pub busy: Duration,
+    /// Time spent waiting for the previous stage or for room in the next one
+    pub blocked: Duration,
+}
+
+impl StageMetrics {
+    /// Operations the stage could handle per second if it never had to wait
+    pub fn items_per_second(&self) -> f64 {
+        match self.busy.as_secs_f64() {
+            secs if secs > 0.0 => self.items as f64 * self.threads as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    fn merge(&mut self, other: &StageMetrics) {
+        self.threads += other.threads;
+        self.items += other.items;
+        self.busy += other.busy;
+        self.blocked += other.blocked;
+    }
+}
+
+/// Metrics of the stages between the workload and the writer
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineMetrics {
+    pub conversion_queue: usize,
+    /// Reading the people and picking the operations, blocked when the conversion
+    /// threads are behind
+    pub generate: StageMetrics,
+    /// Building the documents of the inserts and updates
+    pub convert: StageMetrics,
+    /// Deletes, `add_document` and commits, arrival pauses excluded
+    pub index: StageMetrics,
+    /// Time the writer waited for documents to arrive, modelling the arrival pattern,
+    /// left out of the index time of the run
+    pub arrival_pauses: Duration,
+}
+
+// ConversionPipeline
+
+/// Operation along with its document, `None` for deletes
+pub type ConvertedOperation = (Operation, Option<TantivyDocument>);
+
+/// Converts the people of the operations into documents on a pool of threads, handing
+/// them back in the order of the operations.
+///
+/// Operation `n` goes to thread `n % threads`, every thread having a channel bounded by
+/// `queue` on either side, so that the operations are taken round-robin from the
+/// threads again without reordering. A slow writer blocks the threads once their
+/// channels are full, and in turn the thread picking the operations.
+pub struct ConversionPipeline<'scope> {
+    outputs: Vec<Receiver<ConvertedOperation>>,
+    generate: ScopedJoinHandle<'scope, StageMetrics>,
+    convert: Vec<ScopedJoinHandle<'scope, StageMetrics>>,
+    queue: usize,
+    next_output: usize,
+    index: StageMetrics,
+    /// When the last operation was handed to the writer
+    returned_at: Option<Instant>,
+    paused: Duration,
+    arrival_pauses: Duration,
+}
+
+fn to_document(operation: &Operation, fields: &PersonFields) -> Option<TantivyDocument> {
+    match operation {
+        Operation::Insert(person) | Operation::Update(person) => Some(person.to_doc(fields)),
+        Operation::Delete(_) => None,
+    }
+}
+
+impl<'scope> ConversionPipeline<'scope> {
+    pub fn spawn<'env, I>(
+        scope: &'scope Scope<'scope, 'env>,
+        operations: I,
+        fields: PersonFields,
+        threads: usize,
+        queue: usize,
+    ) -> Self
+    where
+        I: Iterator<Item = Operation> + Send + 'scope,
+    {
+        let threads = threads.max(1);
+        let queue = queue.max(1);
+
+        let mut inputs: Vec<SyncSender<Operation>> = Vec::with_capacity(threads);
+        let mut outputs: Vec<Receiver<ConvertedOperation>> = Vec::with_capacity(threads);
+        let mut convert = Vec::with_capacity(threads);
+
+        for _ in 0..threads {
+            let (input_sender, input_receiver) = mpsc::sync_channel::<Operation>(queue);
+            let (output_sender, output_receiver) = mpsc::sync_channel::<ConvertedOperation>(queue);
+
+            convert.push(scope.spawn(move || {
+                let mut metrics = StageMetrics{
+                    threads: 1,
+                    ..StageMetrics::default()
+                };
+
+                loop {
+                    let waiting_at = Instant::now();
+                    let operation = match input_receiver.recv() {
+                        Ok(operation) => operation,
+                        Err(_) => break,
+                    };
+                    let converting_at = Instant::now();
+                    metrics.blocked += converting_at - waiting_at;
+
+                    let document = to_document(&operation, &fields);
+                    let sending_at = Instant::now();
+                    metrics.busy += sending_at - converting_at;
+                    metrics.items += 1;
+
+                    if output_sender.send((operation, document)).is_err() {
+                        break;
+                    }
+                    metrics.blocked += sending_at.elapsed();
+                }
+
+                metrics
+            }));
+
+            inputs.push(input_sender);
+            outputs.push(output_receiver);
+        }
+
+        let generate = scope.spawn(move || {
+            let mut metrics = StageMetrics{
+                threads: 1,
+                ..StageMetrics::default()
+            };
+            let mut operations = operations;
+
+            for index in 0.. {
+                let generating_at = Instant::now();
+                let operation = match operations.next() {
+                    Some(operation) => operation,
+                    None => break,
+                };
+                let sending_at = Instant::now();
+                metrics.busy += sending_at - generating_at;
+                metrics.items += 1;
+
+                if inputs[index % inputs.len()].send(operation).is_err() {
+                    break;
+                }
+                metrics.blocked += sending_at.elapsed();
+            }
+
+            metrics
+        });
+
+        ConversionPipeline{
+            outputs,
+            generate,
+            convert,
+            queue,
+            next_output: 0,
+            index: StageMetrics{
+                threads: 1,
+                ..StageMetrics::default()
+            },
+            returned_at: None,
+            paused: Duration::ZERO,
+            arrival_pauses: Duration::ZERO,
+        }
+    }
+
+    /// Sleeps before handing the next document to the writer, not counting towards
+    /// the time of the index stage
+    pub fn pause(&mut self, pause: Duration) {
+        thread::sleep(pause);
+        self.paused += pause;
+        self.arrival_pauses += pause;
+    }
+
+    /// Counts the time since the last operation was handed out towards the writer
+    fn record_index_time(&mut self, now: Instant) {
+        if let Some(returned_at) = self.returned_at.take() {
+            self.index.busy += (now - returned_at).saturating_sub(self.paused);
+            self.index.items += 1;
+        }
+        self.paused = Duration::ZERO;
+    }
+
+    /// Waits for the threads once every operation went through, or drops the ones left
+    pub fn finish(mut self) -> PipelineMetrics {
+        self.record_index_time(Instant::now());
+        self.outputs.clear();
+
+        let join = |handle: ScopedJoinHandle<'scope, StageMetrics>| match handle.join() {
+            Ok(metrics) => metrics,
+            Err(payload) => panic::resume_unwind(payload),
+        };
+
+        let generate = join(self.generate);
+
+        let mut convert = StageMetrics::default();
+        for handle in self.convert {
+            convert.merge(&join(handle));
+        }
+
+        PipelineMetrics{
+            conversion_queue: self.queue,
+            generate,
+            convert,
+            index: self.index,
+            arrival_pauses: self.arrival_pauses,
+        }
+    }
+}
+
+impl<'scope> Iterator for ConversionPipeline<'scope> {
+    type Item = ConvertedOperation;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let waiting_at = Instant::now();
+        self.record_index_time(waiting_at);
+
+        if self.outputs.is_empty() {
+            return None;
+        }
+
+        // The thread of the next operation only hangs up once it converted all of its own
+        let converted = match self.outputs[self.next_output % self.outputs.len()].recv() {
+            Ok(converted) => converted,
+            Err(_) => {
+                self.outputs.clear();
+                return None;
+            },
+        };
+
+        let received_at = Instant::now();
+        self.index.blocked += received_at - waiting_at;
+        self.returned_at = Some(received_at);
+        self.next_output += 1;
+
+        Some(converted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    };
+
+    use super::*;
+    use super::super::super::generator::PersonGenerator;
+    use super::super::super::store::people::build_schema;
+
+    fn fields() -> PersonFields {
+        PersonFields::new(&build_schema()).unwrap()
+    }
+
+    /// Inserts with every third operation a delete
+    fn operations(count: usize) -> Vec<Operation> {
+        PersonGenerator::new(1)
+            .generate(count)
+            .into_iter()
+            .enumerate()
+            .map(|(index, person)| match index % 3 {
+                2 => Operation::Delete(person),
+                _ => Operation::Insert(person),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hands_the_operations_back_in_order() {
+        let operations = operations(200);
+
+        let (converted, metrics) = thread::scope(|scope| {
+            let mut pipeline = ConversionPipeline::spawn(scope, operations.clone().into_iter(), fields(), 4, 2);
+            let converted = pipeline.by_ref().collect::<Vec<ConvertedOperation>>();
+
+            (converted, pipeline.finish())
+        });
+
+        let ids = |operations: &mut dyn Iterator<Item = &Operation>| operations
+            .map(|operation| operation.person().id.clone())
+            .collect::<Vec<String>>();
+        assert_eq!(ids(&mut converted.iter().map(|(operation, _)| operation)), ids(&mut operations.iter()));
+
+        for (operation, document) in &converted {
+            assert_eq!(document.is_some(), !matches!(operation, Operation::Delete(_)));
+        }
+
+        assert_eq!(metrics.conversion_queue, 2);
+        assert_eq!((metrics.generate.threads, metrics.generate.items), (1, 200));
+        assert_eq!((metrics.convert.threads, metrics.convert.items), (4, 200));
+        assert_eq!((metrics.index.threads, metrics.index.items), (1, 200));
+        assert_eq!(metrics.arrival_pauses, Duration::ZERO);
+    }
+
+    #[test]
+    fn bounds_the_operations_ahead_of_the_writer() {
+        let pulled = AtomicUsize::new(0);
+        let (threads, queue) = (2, 3);
+
+        let metrics = thread::scope(|scope| {
+            let pulled = &pulled;
+            let endless = PersonGenerator::new(1)
+                .inspect(move |_| {
+                    pulled.fetch_add(1, Ordering::SeqCst);
+                })
+                .map(Operation::Insert);
+
+            let mut pipeline = ConversionPipeline::spawn(scope, endless, fields(), threads, queue);
+            assert!(pipeline.next().is_some());
+            thread::sleep(Duration::from_millis(200));
+
+            // Per thread a full channel on either side and the one being converted, and
+            // the one the generator is blocked on
+            let bound = 1 + threads * (2 * queue + 1) + 1;
+            assert!(pulled.load(Ordering::SeqCst) <= bound, "{} pulled", pulled.load(Ordering::SeqCst));
+
+            // Stopping early hangs up on the threads rather than draining the operations
+            pipeline.finish()
+        });
+
+        assert_eq!(metrics.index.items, 1);
+        assert!(metrics.generate.items <= pulled.load(Ordering::SeqCst) as u64);
+        assert!(metrics.generate.blocked > Duration::ZERO);
+    }
+
+    #[test]
+    fn finishes_once_the_operations_run_out() {
+        let metrics = thread::scope(|scope| {
+            let mut pipeline = ConversionPipeline::spawn(scope, operations(5).into_iter(), fields(), 3, 1);
+
+            assert_eq!(pipeline.by_ref().count(), 5);
+            assert!(pipeline.next().is_none());
+
+            pipeline.finish()
+        });
+
+        assert_eq!(metrics.generate.items, 5);
+        assert_eq!(metrics.convert.items, 5);
+        assert_eq!(metrics.index.items, 5);
+
+        let metrics = thread::scope(|scope| {
+            ConversionPipeline::spawn(scope, std::iter::empty(), fields(), 2, 1).finish()
+        });
+
+        assert_eq!((metrics.generate.items, metrics.convert.items, metrics.index.items), (0, 0, 0));
+    }
+
+    #[test]
+    fn leaves_the_arrival_pauses_out_of_the_index_time() {
+        let pause = Duration::from_millis(20);
+        let work = Duration::from_millis(5);
+
+        let metrics = thread::scope(|scope| {
+            let mut pipeline = ConversionPipeline::spawn(scope, operations(4).into_iter(), fields(), 2, 1);
+
+            while pipeline.next().is_some() {
+                thread::sleep(work);
+                pipeline.pause(pause);
+            }
+
+            pipeline.finish()
+        });
+
+        assert_eq!(metrics.index.items, 4);
+        assert_eq!(metrics.arrival_pauses, pause * 4);
+        assert!(metrics.index.busy >= work * 4);
+        assert!(metrics.index.busy < pause * 4, "{:?}", metrics.index.busy);
+    }
+
+    #[test]
+    fn stage_metrics_add_up_across_threads() {
+        let mut metrics = StageMetrics{
+            threads: 1,
+            items: 10,
+            busy: Duration::from_secs(2),
+            blocked: Duration::from_secs(1),
+        };
+        metrics.merge(&StageMetrics{
+            threads: 1,
+            items: 30,
+            busy: Duration::from_secs(3),
+            blocked: Duration::from_secs(2),
+        });
+
+        assert_eq!(metrics, StageMetrics{
+            threads: 2,
+            items: 40,
+            busy: Duration::from_secs(5),
+            blocked: Duration::from_secs(3),
+        });
+        assert_eq!(metrics.items_per_second(), 16.0);
+        assert_eq!(StageMetrics::default().items_per_second(), 0.0);
+    }
+}
@@ -0,0 +1,569 @@
+use std::{
+    fs,
+    io,
+    path::Path,
+    time::Duration,
+};
+
+use super::{
+    result::{
+        RunResult,
+        RunOutcome,
+    },
+    scenario::{
+        CommitStrategy,
+        MergePolicyConfig,
+        WaitMode,
+    },
+    stats::Distribution,
+};
+
+/// Parses durations formatted via `format!("{:?}", duration)`, e.g. `244.575708ms`
+pub fn parse_debug_duration(value: &str) -> Option<Duration> {
+    let units = [
+        ("ns", 1e-9),
+        ("µs", 1e-6),
+        ("us", 1e-6),
+        ("ms", 1e-3),
+        ("s", 1.0),
+    ];
+
+    for (suffix, multiplier) in units {
+        if let Some(number) = value.strip_suffix(suffix) {
+            return match number.parse::<f64>() {
+                Ok(number) => Some(Duration::from_secs_f64(number * multiplier)),
+                Err(_) => None,
+            };
+        }
+    }
+
+    None
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", (duration.as_secs_f64() * 1000.0).round() as u64)
+    } else {
+        format!("{}s", duration.as_secs_f64().round() as u64)
+    }
+}
+
+fn format_time(result: &RunResult) -> String {
+    match result.outcome {
+        RunOutcome::Completed if result.trials.len() > 1 => format!(
+            "`{}` (median of {})",
+            format_duration(result.total_index_time),
+            result.trials.len(),
+        ),
+        RunOutcome::Completed => format!("`{}`", format_duration(result.total_index_time)),
+        RunOutcome::TimedOut => format!("**Timed out** (`>{}`)", format_duration(result.total_index_time)),
+    }
+}
+
+fn format_segment_counts(result: &RunResult) -> String {
+    let counts = &result.final_segment_file_counts;
+
+    [
+        ("fast", counts.fast),
+        ("fieldnorm", counts.fieldnorm),
+        ("idx", counts.idx),
+        ("pos", counts.pos),
+        ("store", counts.store),
+        ("term", counts.term),
+    ]
+        .into_iter()
+        .map(|(extension, count)| format!("`.{}: {}x`", extension, count))
+        .chain(match result.segment_inventory.orphan_files.len() {
+            0 => None,
+            orphans => Some(format!("**Orphans: {}x**", orphans)),
+        })
+        .collect::<Vec<String>>()
+        .join("<br>")
+}
+
+fn format_merge_policy_calls(result: &RunResult) -> String {
+    // Calls of a timed out run were cut short once its merge policy was stopped
+    let prefix = match result.outcome {
+        RunOutcome::Completed => "",
+        RunOutcome::TimedOut => ">",
+    };
+
+    let mut lines = vec![format!("**Calls: {}{}x**", prefix, result.merge_policy_stats.calls)];
+
+    for (input_size, calls) in result.merge_policy_stats.input_sizes.iter() {
+        let args = match input_size {
+            1 => "arg",
+            _ => "args",
+        };
+
+        lines.push(format!("`{} {}: {}{}x`", input_size, args, prefix, calls));
+    }
+
+    lines.join("<br>")
+}
+
+fn format_commit_strategy(commit_strategy: &CommitStrategy) -> String {
+    match commit_strategy {
+        CommitStrategy::Single => "Single".to_string(),
+        CommitStrategy::EveryDocument => "After every change".to_string(),
+        CommitStrategy::EveryDocs{ docs } => format!("Every {} docs", docs),
+        CommitStrategy::Interval{ millis } => format!("Every {}ms", millis),
+        CommitStrategy::Bytes{ bytes } => format!("Every {} bytes", bytes),
+        CommitStrategy::Poisson{ docs_per_second, commit_millis, .. } => {
+            format!("Every {}ms, {} docs/s at random", commit_millis, docs_per_second)
+        },
+        CommitStrategy::Bursty{ min_burst_docs, max_burst_docs, min_idle_millis, max_idle_millis, .. } => format!(
+            "Bursts of {}-{} docs, {}-{}ms apart",
+            min_burst_docs,
+            max_burst_docs,
+            min_idle_millis,
+            max_idle_millis,
+        ),
+    }
+}
+
+fn format_merge_policy(merge_policy: &MergePolicyConfig) -> String {
+    match merge_policy {
+        MergePolicyConfig::Whenever => "MergeWhenever".to_string(),
+        MergePolicyConfig::NoMerge => "NoMerge".to_string(),
+        MergePolicyConfig::TargetDocs{ .. } => "TargetDocs".to_string(),
+        MergePolicyConfig::TargetBytes{ .. } => "TargetBytes".to_string(),
+        MergePolicyConfig::Tiered{ .. } => "Tiered".to_string(),
+        MergePolicyConfig::Log{ .. } => "Log".to_string(),
+        MergePolicyConfig::Named(name) => format!("`{}`", name),
+        // Combinators are only told apart by their full config
+        merge_policy => format!("`{}`", merge_policy),
+    }
+}
+
+fn format_wait_mode(wait_mode: WaitMode) -> &'static str {
+    match wait_mode {
+        WaitMode::NoWait => "No",
+        WaitMode::WaitMergingThreads => "Yes",
+    }
+}
+
+/// Renders the results in the format of the README tables
+pub fn markdown_table(results: &[RunResult]) -> String {
+    let mut lines = vec![
+        "|Run|Commit|Merge policy|Wait for merge threads|Time|Segment counts|`compute_merge_candidates`|".to_string(),
+        "|-|-|-|-|-|-|-|".to_string(),
+    ];
+
+    for result in results {
+        lines.push(format!(
+            "|{}|{}|{}|{}|{}|{}|{}|",
+            result.scenario.name.to_uppercase(),
+            format_commit_strategy(&result.scenario.commit_strategy),
+            format_merge_policy(&result.scenario.merge_policy),
+            format_wait_mode(result.scenario.wait_mode),
+            format_time(result),
+            format_segment_counts(result),
+            format_merge_policy_calls(result),
+        ));
+    }
+
+    lines.join("\n")
+}
+
+/// Lists the runs whose merge policy returned degenerate or repeated candidates
+pub fn markdown_findings_table(results: &[RunResult]) -> Option<String> {
+    let mut lines = vec![
+        "|Run|Empty|Single segment|Duplicate ids|Unknown ids|Filtered|Repeated outputs|Max repeats|".to_string(),
+        "|-|-|-|-|-|-|-|-|".to_string(),
+    ];
+
+    for result in results {
+        let findings = &result.merge_policy_findings;
+        if !findings.has_degenerate_candidates() && findings.repeated_outputs == 0 {
+            continue;
+        }
+
+        lines.push(format!(
+            "|{}|{}|{}|{}|{}|{}|{}|{}|",
+            result.scenario.name.to_uppercase(),
+            findings.empty_candidates,
+            findings.single_segment_candidates,
+            findings.duplicate_segment_ids,
+            findings.unknown_segment_ids,
+            findings.filtered_candidates,
+            findings.repeated_outputs,
+            findings.max_repeated_output,
+        ));
+    }
+
+    match lines.len() {
+        2 => None,
+        _ => Some(lines.join("\n")),
+    }
+}
+
+fn format_micros(micros: f64) -> String {
+    format_duration(Duration::from_secs_f64(micros.max(0.0) / 1e6))
+}
+
+/// `min / median / p95 / max ± std dev` of a distribution
+fn format_spread(distribution: &Distribution, format_value: fn(f64) -> String) -> String {
+    format!(
+        "`{} / {} / {} / {} ± {}`",
+        format_value(distribution.min as f64),
+        format_value(distribution.median as f64),
+        format_value(distribution.p95 as f64),
+        format_value(distribution.max as f64),
+        format_value(distribution.std_dev),
+    )
+}
+
+fn format_count(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Lists the runs which were repeated, with the spread of their trials
+pub fn markdown_trials_table(results: &[RunResult]) -> Option<String> {
+    let mut lines = vec![
+        "|Run|Trials|Warmup|Time (min / median / p95 / max ± std dev)|Merges|Final segments|".to_string(),
+        "|-|-|-|-|-|-|".to_string(),
+    ];
+
+    for result in results {
+        let trial_stats = match result.trial_stats.as_ref() {
+            Some(trial_stats) if result.trials.len() > 1 => trial_stats,
+            _ => {
+                continue;
+            },
+        };
+
+        lines.push(format!(
+            "|{}|{}|{}|{}|{}|{}|",
+            result.scenario.name.to_uppercase(),
+            result.trials.len(),
+            result.scenario.warmup_runs,
+            format_spread(&trial_stats.index_time_micros, format_micros),
+            format_spread(&trial_stats.merges, format_count),
+            format_spread(&trial_stats.final_segments, format_count),
+        ));
+    }
+
+    match lines.len() {
+        2 => None,
+        _ => Some(lines.join("\n")),
+    }
+}
+
+/// Lists the runs which updated or deleted people, with the deletes left in their segments
+pub fn markdown_workload_table(results: &[RunResult]) -> Option<String> {
+    let mut lines = vec![
+        "|Run|Inserts|Updates|Deletes|Live docs|Deleted docs|Deleted ratio|Segments with deletes|".to_string(),
+        "|-|-|-|-|-|-|-|-|".to_string(),
+    ];
+
+    for result in results {
+        if result.scenario.workload.is_insert_only() {
+            continue;
+        }
+
+        let counts = &result.workload_counts;
+        let inventory = &result.segment_inventory;
+        let num_docs = inventory.num_docs();
+        let num_deleted_docs = inventory.num_deleted_docs();
+
+        let deleted_ratio = match num_docs + num_deleted_docs {
+            0 => 0.0,
+            max_docs => num_deleted_docs as f64 / max_docs as f64,
+        };
+
+        lines.push(format!(
+            "|{}|{}|{}|{}|{}|{}|{:.1}%|{}/{}|",
+            result.scenario.name.to_uppercase(),
+            counts.inserts,
+            counts.updates,
+            counts.deletes,
+            num_docs,
+            num_deleted_docs,
+            deleted_ratio * 100.0,
+            inventory.segments.iter().filter(|segment| segment.num_deleted_docs > 0).count(),
+            inventory.segments.len(),
+        ));
+    }
+
+    match lines.len() {
+        2 => None,
+        _ => Some(lines.join("\n")),
+    }
+}
+
+fn format_cpus(cpus: &Option<Vec<usize>>) -> String {
+    match cpus {
+        Some(cpus) => cpus
+            .iter()
+            .map(|cpu| cpu.to_string())
+            .collect::<Vec<String>>()
+            .join(","),
+        None => "any".to_string(),
+    }
+}
+
+/// Lists the runs which overlapped with other runs of their batch, flagging the
+/// ones whose timing was likely skewed by the concurrency
+pub fn markdown_concurrency_table(results: &[RunResult]) -> Option<String> {
+    let mut lines = vec![
+        "|Run|Slot|Start|End|Writer threads|CPUs|Concurrent runs|Concurrent writer threads|Timing skewed|".to_string(),
+        "|-|-|-|-|-|-|-|-|-|".to_string(),
+    ];
+
+    for result in results {
+        let concurrency = match result.concurrency.as_ref() {
+            Some(concurrency) if concurrency.max_concurrent_runs > 1 => concurrency,
+            _ => {
+                continue;
+            },
+        };
+
+        let timing_skewed = match (concurrency.timing_skewed, concurrency.shared_cpus) {
+            (true, true) => "**Yes** (shared CPUs)",
+            (true, false) => "**Yes** (oversubscribed)",
+            (false, _) => "No",
+        };
+
+        lines.push(format!(
+            "|{}|{}|`{}`|`{}`|{}|{}|{}|{}/{}|{}|",
+            result.scenario.name.to_uppercase(),
+            concurrency.slot,
+            format_duration(concurrency.started_at),
+            format_duration(concurrency.finished_at),
+            result.writer_threads,
+            format_cpus(&concurrency.pinned_cpus),
+            concurrency.max_concurrent_runs,
+            concurrency.max_concurrent_writer_threads,
+            concurrency.available_parallelism,
+            timing_skewed,
+        ));
+    }
+
+    match lines.len() {
+        2 => None,
+        _ => Some(lines.join("\n")),
+    }
+}
+
+/// Lists the stages of every run from the operations to the writer, the throughput
+/// being what a stage could sustain without waiting on the others
+pub fn markdown_pipeline_table(results: &[RunResult]) -> Option<String> {
+    let mut lines = vec![
+        "|Run|Stage|Threads|Operations|Busy|Blocked|Throughput|".to_string(),
+        "|-|-|-|-|-|-|-|".to_string(),
+    ];
+
+    for result in results {
+        let pipeline = match result.pipeline.as_ref() {
+            Some(pipeline) => pipeline,
+            None => {
+                continue;
+            },
+        };
+
+        let stages = [
+            ("Generate", &pipeline.generate),
+            ("Convert", &pipeline.convert),
+            ("Index", &pipeline.index),
+        ];
+
+        for (stage, metrics) in stages {
+            lines.push(format!(
+                "|{}|{}|{}|{}|`{}`|`{}`|`{:.0}/s`|",
+                result.scenario.name.to_uppercase(),
+                stage,
+                metrics.threads,
+                metrics.items,
+                format_duration(metrics.busy),
+                format_duration(metrics.blocked),
+                metrics.items_per_second(),
+            ));
+        }
+
+        if pipeline.arrival_pauses > Duration::ZERO {
+            lines.push(format!(
+                "|{}|Arrival pauses|||`{}`|||",
+                result.scenario.name.to_uppercase(),
+                format_duration(pipeline.arrival_pauses),
+            ));
+        }
+    }
+
+    match lines.len() {
+        2 => None,
+        _ => Some(lines.join("\n")),
+    }
+}
+
+/// Reads run results from a JSON file (single result or an array of them) or from
+/// the indexer output, where every line starting with `{` is a single result
+pub fn read_run_results<T: AsRef<Path>>(path: T) -> Result<Vec<RunResult>, io::Error> {
+    let content = fs::read_to_string(path)?;
+
+    if let Ok(results) = serde_json::from_str::<Vec<RunResult>>(&content) {
+        return Ok(results);
+    }
+
+    if let Ok(result) = serde_json::from_str::<RunResult>(&content) {
+        return Ok(vec![result]);
+    }
+
+    let mut results: Vec<RunResult> = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        if !line.trim_start().starts_with('{') {
+            continue;
+        }
+
+        match serde_json::from_str::<RunResult>(line) {
+            Ok(result) => results.push(result),
+            Err(err) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid run result on line {}: {}", index + 1, err),
+                ));
+            },
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use super::super::super::store::utils::{
+        MergePolicyFindings,
+        OrphanFile,
+        SegmentInventory,
+    };
+    use super::super::{
+        result::{
+            SegmentFileCounts,
+            TrialResult,
+        },
+        scenario::Scenario,
+        stats::MergePolicyStats,
+        workload::WorkloadCounts,
+    };
+
+    fn result(scenario: Scenario, outcome: RunOutcome, total_index_time: Duration, input_sizes: &[(usize, u64)]) -> RunResult {
+        let merge_policy_stats = MergePolicyStats{
+            calls: input_sizes.iter().map(|(_, calls)| calls).sum(),
+            input_sizes: input_sizes.iter().copied().collect(),
+            ..MergePolicyStats::default()
+        };
+
+        RunResult{
+            scenario,
+            outcome,
+            total_index_time,
+            commits: 1,
+            workload_counts: WorkloadCounts::default(),
+            final_segment_file_counts: SegmentFileCounts{
+                fast: 1,
+                fieldnorm: 1,
+                idx: 1,
+                pos: 1,
+                store: 1,
+                term: 1,
+            },
+            final_segment_count: 1,
+            segment_inventory: SegmentInventory::default(),
+            merge_policy_stats,
+            merge_policy_findings: MergePolicyFindings::default(),
+            last_segments: Vec::new(),
+            writer_lock_released: true,
+            index_path: None,
+            writer_threads: 1,
+            concurrency: None,
+            trials: Vec::new(),
+            trial_stats: None,
+            pipeline: None,
+        }
+    }
+
+    fn results() -> Vec<RunResult> {
+        let single = result(
+            Scenario::new(CommitStrategy::Single, MergePolicyConfig::Whenever, WaitMode::WaitMergingThreads, 1000, 50_000_000)
+                .with_name("a"),
+            RunOutcome::Completed,
+            Duration::from_micros(244_575),
+            &[(1, 1)],
+        );
+
+        let mut timed_out = result(
+            Scenario::new(CommitStrategy::EveryDocument, MergePolicyConfig::Whenever, WaitMode::WaitMergingThreads, 1000, 50_000_000)
+                .with_name("b"),
+            RunOutcome::TimedOut,
+            Duration::from_millis(120_400),
+            &[(1, 1), (2, 998), (3, 1024)],
+        );
+        timed_out.segment_inventory.orphan_files = vec![
+            OrphanFile{
+                path: PathBuf::from("1.store"),
+                bytes: 10,
+                managed: true,
+            },
+            OrphanFile{
+                path: PathBuf::from("2.store"),
+                bytes: 10,
+                managed: false,
+            },
+        ];
+        timed_out.merge_policy_findings = MergePolicyFindings{
+            calls: 2023,
+            single_segment_candidates: 3,
+            repeated_outputs: 1020,
+            max_repeated_output: 1019,
+            ..MergePolicyFindings::default()
+        };
+
+        let mut repeated = result(
+            Scenario::new(CommitStrategy::EveryDocs{ docs: 100 }, MergePolicyConfig::NoMerge, WaitMode::NoWait, 1000, 50_000_000)
+                .with_name("c"),
+            RunOutcome::Completed,
+            Duration::from_millis(1_600),
+            &[],
+        );
+        repeated.trials = vec![TrialResult::from_run(&repeated); 3];
+
+        vec![single, timed_out, repeated]
+    }
+
+    #[test]
+    fn renders_the_readme_table() {
+        assert_eq!(markdown_table(&results()), [
+            "|Run|Commit|Merge policy|Wait for merge threads|Time|Segment counts|`compute_merge_candidates`|",
+            "|-|-|-|-|-|-|-|",
+            "|A|Single|MergeWhenever|Yes|`245ms`|`.fast: 1x`<br>`.fieldnorm: 1x`<br>`.idx: 1x`<br>`.pos: 1x`<br>`.store: 1x`<br>`.term: 1x`|**Calls: 1x**<br>`1 arg: 1x`|",
+            "|B|After every change|MergeWhenever|Yes|**Timed out** (`>120s`)|`.fast: 1x`<br>`.fieldnorm: 1x`<br>`.idx: 1x`<br>`.pos: 1x`<br>`.store: 1x`<br>`.term: 1x`<br>**Orphans: 2x**|**Calls: >2023x**<br>`1 arg: >1x`<br>`2 args: >998x`<br>`3 args: >1024x`|",
+            "|C|Every 100 docs|NoMerge|No|`2s` (median of 3)|`.fast: 1x`<br>`.fieldnorm: 1x`<br>`.idx: 1x`<br>`.pos: 1x`<br>`.store: 1x`<br>`.term: 1x`|**Calls: 0x**|",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn renders_the_findings_of_degenerate_runs_only() {
+        assert_eq!(markdown_findings_table(&results()).unwrap(), [
+            "|Run|Empty|Single segment|Duplicate ids|Unknown ids|Filtered|Repeated outputs|Max repeats|",
+            "|-|-|-|-|-|-|-|-|",
+            "|B|0|3|0|0|0|1020|1019|",
+        ].join("\n"));
+        assert_eq!(markdown_findings_table(&results()[..1]), None);
+    }
+
+    #[test]
+    fn parses_debug_durations() {
+        assert_eq!(parse_debug_duration("244.575708ms"), Some(Duration::from_nanos(244_575_708)));
+        assert_eq!(parse_debug_duration("1.5s"), Some(Duration::from_millis(1_500)));
+        assert_eq!(parse_debug_duration("12µs"), Some(Duration::from_micros(12)));
+        assert_eq!(parse_debug_duration("12 parsecs"), None);
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    path::PathBuf,
+    time::Duration,
+};
+
+use serde::{
+    de,
+    Serialize,
+    Deserialize,
+    Deserializer,
+};
+
+use super::super::store::utils::{
+    RecordedSegment,
+    MergePolicyFindings,
+    SegmentInventory,
+};
+use super::{
+    pipeline::PipelineMetrics,
+    report::parse_debug_duration,
+    scenario::Scenario,
+    stats::{
+        MergePolicyStats,
+        TrialStats,
+    },
+    workload::WorkloadCounts,
+};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Completed,
+    /// `wait_merging_threads` did not terminate before the deadline
+    TimedOut,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunResult {
+    pub scenario: Scenario,
+    pub outcome: RunOutcome,
+    /// Time of the trial the details below come from, the median one when repeated,
+    /// arrival pauses excluded
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub total_index_time: Duration,
+    /// Commits made by the run, the final one included
+    #[serde(default)]
+    pub commits: u64,
+    #[serde(default)]
+    pub workload_counts: WorkloadCounts,
+    /// Component files of the live segments, orphans excluded
+    pub final_segment_file_counts: SegmentFileCounts,
+    /// Searchable segments once the run was over
+    #[serde(default)]
+    pub final_segment_count: u64,
+    /// Live segments of the last commit and the orphan files around them
+    #[serde(default)]
+    pub segment_inventory: SegmentInventory,
+    pub merge_policy_stats: MergePolicyStats,
+    pub merge_policy_findings: MergePolicyFindings,
+    /// Segments passed to the last `compute_merge_candidates` call
+    pub last_segments: Vec<RecordedSegment>,
+    /// Whether the index writer lock could be acquired once the run was over
+    #[serde(default)]
+    pub writer_lock_released: bool,
+    /// Directory of a timed out run, left in place for its still running writer
+    #[serde(default)]
+    pub index_path: Option<PathBuf>,
+    /// Indexing threads the writer was created with
+    #[serde(default)]
+    pub writer_threads: usize,
+    /// Overlap with the other runs of the batch, `None` for a run on its own
+    #[serde(default)]
+    pub concurrency: Option<RunConcurrency>,
+    /// Measured trials of the scenario in the order they ran, warmup runs excluded
+    #[serde(default)]
+    pub trials: Vec<TrialResult>,
+    #[serde(default)]
+    pub trial_stats: Option<TrialStats>,
+    /// Conversion of the people into documents ahead of the writer
+    #[serde(default)]
+    pub pipeline: Option<PipelineMetrics>,
+}
+
+/// Results written before durations were numeric hold their `Debug` format, e.g. `"244.575708ms"`
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DurationValue {
+        Duration(Duration),
+        Debug(String),
+    }
+
+    match DurationValue::deserialize(deserializer)? {
+        DurationValue::Duration(duration) => Ok(duration),
+        DurationValue::Debug(value) => match parse_debug_duration(&value) {
+            Some(duration) => Ok(duration),
+            None => Err(de::Error::custom(format!("Invalid duration {:?}", value))),
+        },
+    }
+}
+
+/// Single measured repetition of a scenario
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrialResult {
+    pub outcome: RunOutcome,
+    pub total_index_time: Duration,
+    /// Merge candidates handed to the writer
+    pub merges: u64,
+    pub final_segments: u64,
+}
+
+impl TrialResult {
+    pub fn from_run(result: &RunResult) -> Self {
+        TrialResult{
+            outcome: result.outcome,
+            total_index_time: result.total_index_time,
+            merges: result.merge_policy_stats.merges(),
+            final_segments: result.final_segment_count,
+        }
+    }
+}
+
+/// When a run took place relative to the other runs of its batch.
+///
+/// Concurrent runs compete for CPUs, memory bandwidth and disk, `timing_skewed`
+/// flags the runs whose timings are therefore not comparable to sequential ones.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunConcurrency {
+    /// Concurrency slot, i.e. worker thread, the run was executed by
+    pub slot: usize,
+    /// Offsets from the start of the batch
+    pub started_at: Duration,
+    pub finished_at: Duration,
+    /// CPUs the run was pinned to, `None` when unpinned
+    pub pinned_cpus: Option<Vec<usize>>,
+    /// CPUs available to the process when the batch started
+    pub available_parallelism: usize,
+    /// Peak number of runs in progress during the run, the run included
+    pub max_concurrent_runs: usize,
+    /// Peak number of writer indexing threads in progress during the run, merge threads excluded
+    pub max_concurrent_writer_threads: usize,
+    /// Whether an overlapping run could be scheduled on the CPUs of the run
+    pub shared_cpus: bool,
+    /// Overlapping runs shared CPUs or had more indexing threads than CPUs available
+    pub timing_skewed: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentFileCounts {
+    pub fast: u32,
+    pub fieldnorm: u32,
+    pub idx: u32,
+    pub pos: u32,
+    pub store: u32,
+    pub term: u32,
+}
+
+impl SegmentFileCounts {
+    /// Counts the component files of the live segments
+    pub fn from_inventory(segment_inventory: &SegmentInventory) -> Self {
+        let mut segment_file_counts = SegmentFileCounts::default();
+
+        let extensions = segment_inventory.segments
+            .iter()
+            .flat_map(|segment| segment.files.iter())
+            .filter_map(|path| path.extension().and_then(|extension| extension.to_str()));
+
+        for extension in extensions {
+            match extension {
+                "fast" => segment_file_counts.fast += 1,
+                "fieldnorm" => segment_file_counts.fieldnorm += 1,
+                "idx" => segment_file_counts.idx += 1,
+                "pos" => segment_file_counts.pos += 1,
+                "store" => segment_file_counts.store += 1,
+                "term" => segment_file_counts.term += 1,
+                _ => {},
+            }
+        }
+
+        segment_file_counts
+    }
+}
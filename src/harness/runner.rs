@@ -0,0 +1,709 @@
+use std::{
+    fs,
+    io,
+    panic,
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{
+            AtomicBool,
+            AtomicUsize,
+            Ordering,
+        },
+        Mutex,
+    },
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use tantivy::{
+    Index,
+    IndexWriter,
+    Term,
+    directory::{
+        Directory,
+        INDEX_WRITER_LOCK,
+        MmapDirectory,
+        RamDirectory,
+        error::LockError,
+    },
+};
+
+use super::super::{
+    config::Config,
+    models::{
+        loader::PeopleLoader,
+        person::Person,
+    },
+    store::{
+        self,
+        people::PersonFields,
+        utils::{
+            MergePolicyRecorder,
+            MergePolicyValidation,
+            RecordingMergePolicy,
+            SegmentInventory,
+            ValidatingMergePolicy,
+        },
+    },
+};
+use super::{
+    pinning,
+    pipeline::ConversionPipeline,
+    result::{
+        RunResult,
+        RunOutcome,
+        RunConcurrency,
+        SegmentFileCounts,
+        TrialResult,
+    },
+    scenario::{
+        Scenario,
+        CpuPinning,
+        IndexStorage,
+        WaitMode,
+    },
+    stats::{
+        self,
+        MergePolicyStats,
+        TrialStats,
+    },
+    watchdog,
+    workload::{
+        Operation,
+        WorkloadCounts,
+        WorkloadGenerator,
+    },
+};
+
+static RUN_INDEX_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// Index of a single run, isolated from the indexes of the other runs
+struct RunIndex {
+    index: Index,
+    /// Directory of the index, `None` when in RAM
+    path: Option<PathBuf>,
+}
+
+/// Creates `<index_people_path>/<scenario>-<pid>-<seq>`, unique even across
+/// concurrent runs and processes
+fn create_run_directory(config: &Config, scenario: &Scenario) -> PathBuf {
+    let name = scenario.name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect::<String>();
+
+    match fs::create_dir_all(&config.index_people_path) {
+        Ok(_) => {},
+        Err(err) => {
+            panic!("Failed to prepare index directory: {}", err);
+        },
+    }
+
+    loop {
+        let seq = RUN_INDEX_SEQ.fetch_add(1, Ordering::Relaxed);
+        let path = config.index_people_path.join(format!("{}-{}-{}", name, process::id(), seq));
+
+        match fs::create_dir(&path) {
+            Ok(_) => {
+                return path;
+            },
+            // Left over by an earlier process with the same pid
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {},
+            Err(err) => {
+                panic!("Failed to create run index directory {:?}: {}", path, err);
+            },
+        }
+    }
+}
+
+async fn get_index(config: &Config, scenario: &Scenario) -> RunIndex {
+    let schema = store::PERSON_SCHEMA.clone();
+
+    let (index, path) = match scenario.index_storage {
+        IndexStorage::Directory => {
+            let path = create_run_directory(config, scenario);
+
+            let directory = match MmapDirectory::open(&path) {
+                Ok(directory) => directory,
+                Err(err) => {
+                    panic!("Failed to open run index directory {:?}: {}", path, err);
+                },
+            };
+
+            (store::people::open_index_in(directory, schema).await, Some(path))
+        },
+        IndexStorage::Ram => (store::people::open_index_in(RamDirectory::create(), schema).await, None),
+    };
+
+    match index {
+        Ok(index) => RunIndex{
+            index,
+            path,
+        },
+        Err(err) => {
+            panic!("Failed to open people index: {:?}", err);
+        },
+    }
+}
+
+/// One conversion thread per CPU the current thread may use
+fn default_conversion_threads() -> usize {
+    match thread::available_parallelism() {
+        Ok(parallelism) => parallelism.get(),
+        Err(_) => 1,
+    }
+}
+
+/// Tries to take the writer lock, which succeeds only once the writer of the run is gone
+fn writer_lock_released(index: &Index) -> bool {
+    match index.directory().acquire_lock(&INDEX_WRITER_LOCK) {
+        Ok(_lock) => true,
+        Err(LockError::LockBusy) => false,
+        Err(err) => {
+            panic!("Failed to check the writer lock: {}", err);
+        },
+    }
+}
+
+fn commit(writer: &mut IndexWriter, recorder: &MergePolicyRecorder) {
+    match writer.commit() {
+        Ok(_) => {},
+        Err(err) => {
+            panic!("Failed to commit the writer: {}", err);
+        },
+    }
+
+    recorder.mark_commit();
+}
+
+fn write_merge_policy_log(config: &Config, scenario: &Scenario, recorder: &MergePolicyRecorder) {
+    let log_path = match config.merge_policy_log_path.as_ref() {
+        Some(log_path) => log_path.join(format!("{}.jsonl", scenario.name)),
+        None => {
+            return;
+        },
+    };
+
+    match recorder.write_jsonl(&log_path) {
+        Ok(_) => {},
+        Err(err) => {
+            panic!("Failed to write merge policy log {:?}: {}", log_path, err);
+        },
+    }
+}
+
+/// Result of a trial along with the merge policy calls it recorded
+struct TrialRun {
+    result: RunResult,
+    recorder: MergePolicyRecorder,
+}
+
+/// Streams the valid people of the data file, read anew by every trial so that none
+/// of them are held in memory past their operations. Invalid records are skipped,
+/// the indexer reports them before running.
+///
+/// The file is opened and its first person read right away, so that a trial starts
+/// timing once the people are flowing. The following ones are read by the generate
+/// stage of the pipeline, ahead of the writer.
+fn open_people(config: &Config) -> impl Iterator<Item = Person> + Send {
+    let loader = match PeopleLoader::open(&config.data_people_path) {
+        Ok(loader) => loader,
+        Err(err) => {
+            panic!("Failed to open the people of {:?}: {}", config.data_people_path, err);
+        },
+    };
+
+    let mut people = loader
+        .filter_map(|person| match person {
+            Ok(person) => Some(person),
+            Err(err) if err.is_fatal() => {
+                panic!("Failed to read the people: {}", err);
+            },
+            Err(_) => None,
+        })
+        .peekable();
+
+    people.peek();
+    people
+}
+
+/// Indexes the first `scenario.doc_count` people using the commit cadence,
+/// merge policy and wait mode of the scenario.
+///
+/// Waiting for merging threads is bounded by `scenario.wait_timeout`, a run
+/// exceeding it is reported as `RunOutcome::TimedOut` instead of hanging.
+///
+/// A timed out run stops its merge policy from handing out more merges and gives the
+/// writer a bounded time to shut down, its index time being the one at the deadline.
+///
+/// Every trial gets a fresh index of its own, which is removed once the writer is
+/// shut down and its lock released. A run whose writer is still merging in the
+/// background keeps its index directory.
+async fn run_trial(config: &Config, scenario: &Scenario) -> TrialRun {
+    // Before the index directory gets created, which an invalid scenario would leave behind
+    if let Err(err) = scenario.validate() {
+        panic!("Invalid scenario {}: {}", scenario.name, err);
+    }
+
+    let RunIndex{ index, path } = get_index(config, scenario).await;
+    let people = open_people(config);
+
+    let start_instant = Instant::now();
+
+    let writer_threads = scenario.effective_writer_threads();
+
+    let mut writer: IndexWriter = match index.writer_with_num_threads(writer_threads, scenario.writer_memory_budget) {
+        Ok(writer) => writer,
+        Err(err) => {
+            panic!("Failed to init writer: {}", err);
+        },
+    };
+
+    // The recorder sees the candidates the writer actually receives
+    let validation = MergePolicyValidation::new();
+    let recorder = MergePolicyRecorder::new();
+    let inner_merge_policy = match scenario.merge_policy.build(&index) {
+        Ok(merge_policy) => merge_policy,
+        Err(err) => {
+            panic!("Failed to build merge policy {}: {}", scenario.merge_policy, err);
+        },
+    };
+    let merge_policy = RecordingMergePolicy::new(
+        ValidatingMergePolicy::new(inner_merge_policy, validation.clone())
+            .filter_degenerate_candidates(scenario.filter_degenerate_candidates)
+            .as_box(),
+        recorder.clone(),
+    );
+    writer.set_merge_policy(merge_policy.as_box());
+
+    let mut commit_trigger = match scenario.commit_strategy.trigger() {
+        Ok(commit_trigger) => commit_trigger,
+        Err(err) => {
+            panic!("Invalid commit strategy {}: {}", scenario.commit_strategy, err);
+        },
+    };
+
+    let mut pending_docs: usize = 0;
+    let mut commits: u64 = 0;
+
+    let fields = match PersonFields::new(&store::PERSON_SCHEMA) {
+        Ok(fields) => fields,
+        Err(err) => {
+            panic!("Failed to get the person fields: {}", err);
+        },
+    };
+
+    let conversion_threads = match scenario.conversion_threads {
+        Some(0) => {
+            panic!("Conversion of {} needs at least one thread", scenario.name);
+        },
+        Some(conversion_threads) => conversion_threads,
+        None => default_conversion_threads(),
+    };
+
+    let mut workload_counts = WorkloadCounts::default();
+
+    let pipeline_metrics = thread::scope(|scope| {
+        let mut pipeline = ConversionPipeline::spawn(
+            scope,
+            WorkloadGenerator::new(people, scenario.workload, scenario.doc_count),
+            fields,
+            conversion_threads,
+            scenario.conversion_queue,
+        );
+
+        while let Some((operation, document)) = pipeline.next() {
+            if let Some(pause) = commit_trigger.before_document() {
+                pipeline.pause(pause);
+            }
+
+            let person = operation.person();
+
+            if let Operation::Update(_) | Operation::Delete(_) = operation {
+                writer.delete_term(Term::from_field_text(fields.id, &person.id));
+            }
+
+            if let Some(document) = document {
+                match writer.add_document(document) {
+                    Ok(_) => {},
+                    Err(err) => {
+                        panic!("Failed to add document to writer: {}", err);
+                    },
+                }
+            }
+
+            workload_counts.record(&operation);
+            pending_docs += 1;
+
+            if commit_trigger.after_document(person) {
+                commit(&mut writer, &recorder);
+                commit_trigger.after_commit();
+                pending_docs = 0;
+                commits += 1;
+            }
+        }
+
+        pipeline.finish()
+    });
+
+    // Whatever the strategy, the last documents get committed
+    if pending_docs > 0 || commits == 0 {
+        commit(&mut writer, &recorder);
+        commits += 1;
+    }
+
+    let waiting_at = Instant::now();
+
+    let outcome = match scenario.wait_mode {
+        WaitMode::NoWait => {
+            drop(writer);
+            RunOutcome::Completed
+        },
+        WaitMode::WaitMergingThreads => match watchdog::wait_merging_threads(writer, scenario.wait_timeout, &recorder) {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                panic!("Failed to wait for merging threads: {}", err);
+            },
+        },
+    };
+
+    let run_time = match (outcome, scenario.wait_timeout) {
+        (RunOutcome::TimedOut, Some(wait_timeout)) => (waiting_at - start_instant) + wait_timeout,
+        _ => start_instant.elapsed(),
+    };
+    // Waiting for documents to arrive isn't indexing, the pauses are reported on their own
+    let total_index_time = run_time.saturating_sub(pipeline_metrics.arrival_pauses);
+
+    let writer_lock_released = writer_lock_released(&index);
+    if outcome == RunOutcome::Completed && !writer_lock_released {
+        panic!("Writer lock of {} still held after the writer shut down", scenario.name);
+    }
+
+    // The recorder of a timed out run is stopped, no more calls get recorded
+    let merge_policy_calls = recorder.calls();
+
+    let segment_inventory = match SegmentInventory::read(&index, path.as_deref()) {
+        Ok(segment_inventory) => segment_inventory,
+        Err(err) => {
+            panic!("Failed to read segments of {}: {}", scenario.name, err);
+        },
+    };
+    drop(index);
+
+    let index_path = match path {
+        Some(path) if writer_lock_released => {
+            match fs::remove_dir_all(&path) {
+                Ok(_) => {},
+                Err(err) => {
+                    panic!("Failed to cleanup run index directory {:?}: {}", path, err);
+                },
+            }
+
+            None
+        },
+        path => path,
+    };
+
+    let result = RunResult{
+        scenario: scenario.clone(),
+        outcome,
+        total_index_time,
+        commits,
+        workload_counts,
+        final_segment_file_counts: SegmentFileCounts::from_inventory(&segment_inventory),
+        final_segment_count: segment_inventory.segments.len() as u64,
+        segment_inventory,
+        merge_policy_stats: MergePolicyStats::from_calls(&merge_policy_calls),
+        merge_policy_findings: validation.findings(),
+        last_segments: match merge_policy_calls.last() {
+            Some(call) => call.segments.clone(),
+            None => vec![],
+        },
+        writer_lock_released,
+        index_path,
+        writer_threads,
+        concurrency: None,
+        trials: vec![],
+        trial_stats: None,
+        pipeline: Some(pipeline_metrics),
+    };
+
+    TrialRun{
+        result,
+        recorder,
+    }
+}
+
+/// Runs the warmup runs then the repetitions of the scenario, each on a fresh index.
+///
+/// The details of the result come from the median trial by index time, or from the
+/// timed out trial, after which no more trials are run. Only the merge policy calls
+/// of that trial are written to the log of the scenario.
+pub async fn run_scenario(config: &Config, scenario: &Scenario) -> RunResult {
+    for _ in 0..scenario.warmup_runs {
+        let TrialRun{ result, recorder } = run_trial(config, scenario).await;
+
+        if result.outcome == RunOutcome::TimedOut {
+            write_merge_policy_log(config, scenario, &recorder);
+            return result;
+        }
+    }
+
+    let mut results: Vec<RunResult> = Vec::new();
+    let mut recorders: Vec<MergePolicyRecorder> = Vec::new();
+
+    for _ in 0..scenario.repetitions.max(1) {
+        let TrialRun{ result, recorder } = run_trial(config, scenario).await;
+        let outcome = result.outcome;
+
+        results.push(result);
+        recorders.push(recorder);
+
+        if outcome == RunOutcome::TimedOut {
+            break;
+        }
+    }
+
+    let trials = results
+        .iter()
+        .map(TrialResult::from_run)
+        .collect::<Vec<TrialResult>>();
+
+    let index = match results.iter().position(|result| result.outcome == RunOutcome::TimedOut) {
+        Some(index) => index,
+        None => {
+            let mut order = (0..results.len()).collect::<Vec<usize>>();
+            order.sort_by_key(|index| results[*index].total_index_time);
+            order[stats::percentile_index(order.len(), 50)]
+        },
+    };
+
+    write_merge_policy_log(config, scenario, &recorders[index]);
+
+    let mut result = results.swap_remove(index);
+    result.trial_stats = Some(TrialStats::from_trials(&trials));
+    result.trials = trials;
+
+    result
+}
+
+/// Run of a batch along with when and where it ran
+struct BatchRun {
+    result: RunResult,
+    slot: usize,
+    started_at: Duration,
+    finished_at: Duration,
+    pinned_cpus: Option<Vec<usize>>,
+}
+
+/// Runs the scenario on a thread of its own, pinned as configured, so that the
+/// pinning is inherited by the writer threads and doesn't leak into other runs
+fn run_batch_scenario(
+    config: &Config,
+    scenario: &Scenario,
+    slot: usize,
+    slots: usize,
+    available_cpus: &[usize],
+    batch_instant: Instant,
+) -> BatchRun {
+    let pinned_cpus = match &scenario.cpu_pinning {
+        CpuPinning::Unpinned => None,
+        CpuPinning::Cpus(cpus) => Some(cpus.clone()),
+        CpuPinning::Slot => Some(pinning::slot_cpus(available_cpus, slot, slots)),
+    };
+
+    let (result, started_at, finished_at) = thread::scope(|scope| {
+        let run_thread = thread::Builder::new()
+            .name(format!("run-{}", scenario.name))
+            .spawn_scoped(scope, || {
+                if let Some(cpus) = pinned_cpus.as_ref() {
+                    match pinning::pin_current_thread(cpus) {
+                        Ok(_) => {},
+                        Err(err) => {
+                            panic!("Failed to pin {} to CPUs {:?}: {}", scenario.name, cpus, err);
+                        },
+                    }
+                }
+
+                let runtime = match tokio::runtime::Builder::new_current_thread().build() {
+                    Ok(runtime) => runtime,
+                    Err(err) => {
+                        panic!("Failed to build the runtime of {}: {}", scenario.name, err);
+                    },
+                };
+
+                let started_at = batch_instant.elapsed();
+                let result = runtime.block_on(run_scenario(config, scenario));
+
+                (result, started_at, batch_instant.elapsed())
+            });
+
+        let run_thread = match run_thread {
+            Ok(run_thread) => run_thread,
+            Err(err) => {
+                panic!("Failed to spawn the thread of {}: {}", scenario.name, err);
+            },
+        };
+
+        match run_thread.join() {
+            Ok(run) => run,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    });
+
+    BatchRun{
+        result,
+        slot,
+        started_at,
+        finished_at,
+        pinned_cpus,
+    }
+}
+
+/// Overlap of the run `index` with the other runs of the batch
+fn run_concurrency(runs: &[BatchRun], index: usize, available_cpus: &[usize]) -> RunConcurrency {
+    let run = &runs[index];
+
+    let overlapping = runs
+        .iter()
+        .filter(|other| other.started_at < run.finished_at && run.started_at < other.finished_at)
+        .collect::<Vec<&BatchRun>>();
+
+    // Concurrency only rises when a run starts, the peaks are at the start instants
+    let mut max_concurrent_runs = 0;
+    let mut max_concurrent_writer_threads = 0;
+
+    for instant in overlapping.iter().map(|other| other.started_at.max(run.started_at)) {
+        let in_progress = overlapping
+            .iter()
+            .filter(|other| other.started_at <= instant && instant < other.finished_at)
+            .collect::<Vec<&&BatchRun>>();
+
+        max_concurrent_runs = max_concurrent_runs.max(in_progress.len());
+        max_concurrent_writer_threads = max_concurrent_writer_threads.max(
+            in_progress
+                .iter()
+                .map(|other| other.result.writer_threads)
+                .sum::<usize>(),
+        );
+    }
+
+    let cpus_of = |other: &BatchRun| match other.pinned_cpus.as_ref() {
+        Some(cpus) => cpus.clone(),
+        None => available_cpus.to_vec(),
+    };
+
+    let run_cpus = cpus_of(run);
+    let shared_cpus = overlapping
+        .iter()
+        .filter(|other| !std::ptr::eq(**other, run))
+        .any(|other| cpus_of(other).iter().any(|cpu| run_cpus.contains(cpu)));
+
+    RunConcurrency{
+        slot: run.slot,
+        started_at: run.started_at,
+        finished_at: run.finished_at,
+        pinned_cpus: run.pinned_cpus.clone(),
+        available_parallelism: available_cpus.len(),
+        max_concurrent_runs,
+        max_concurrent_writer_threads,
+        shared_cpus,
+        timing_skewed: max_concurrent_runs > 1
+            && (shared_cpus || max_concurrent_writer_threads > available_cpus.len()),
+    }
+}
+
+fn run_batch(config: &Config, scenarios: &[Scenario], concurrency: usize) -> Vec<RunResult> {
+    let slots = concurrency.clamp(1, scenarios.len().max(1));
+    let available_cpus = pinning::available_cpus();
+    let batch_instant = Instant::now();
+
+    let next_scenario = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let runs: Mutex<Vec<Option<BatchRun>>> = Mutex::new(scenarios.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        let workers = (0..slots)
+            .map(|slot| {
+                let (next_scenario, failed, runs, available_cpus) = (&next_scenario, &failed, &runs, &available_cpus);
+
+                scope.spawn(move || {
+                    while !failed.load(Ordering::Relaxed) {
+                        let index = next_scenario.fetch_add(1, Ordering::Relaxed);
+                        let scenario = match scenarios.get(index) {
+                            Some(scenario) => scenario,
+                            None => {
+                                break;
+                            },
+                        };
+
+                        let run = match panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                            run_batch_scenario(config, scenario, slot, slots, available_cpus, batch_instant)
+                        })) {
+                            Ok(run) => run,
+                            Err(payload) => {
+                                // The other slots stop picking up scenarios
+                                failed.store(true, Ordering::Relaxed);
+                                panic::resume_unwind(payload);
+                            },
+                        };
+
+                        match runs.lock() {
+                            Ok(mut runs) => runs[index] = Some(run),
+                            Err(poisoned) => poisoned.into_inner()[index] = Some(run),
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<thread::ScopedJoinHandle<()>>>();
+
+        for worker in workers {
+            if let Err(payload) = worker.join() {
+                panic::resume_unwind(payload);
+            }
+        }
+    });
+
+    let runs = match runs.into_inner() {
+        Ok(runs) => runs,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let runs = runs
+        .into_iter()
+        .map(|run| match run {
+            Some(run) => run,
+            None => {
+                panic!("Scenario of the batch was never run");
+            },
+        })
+        .collect::<Vec<BatchRun>>();
+
+    (0..runs.len())
+        .map(|index| {
+            let mut result = runs[index].result.clone();
+            result.concurrency = Some(run_concurrency(&runs, index, &available_cpus));
+            result
+        })
+        .collect()
+}
+
+/// Runs the scenarios on up to `concurrency` threads at once, returning the results
+/// in the order of the scenarios. Every run gets a thread of its own, pinned per
+/// `Scenario::cpu_pinning`, and reports its overlap with the other runs.
+pub async fn run_scenarios(config: &Config, scenarios: &[Scenario], concurrency: usize) -> Vec<RunResult> {
+    let (config, scenarios) = (config.clone(), scenarios.to_vec());
+
+    match tokio::task::spawn_blocking(move || run_batch(&config, &scenarios, concurrency)).await {
+        Ok(results) => results,
+        Err(err) => match err.try_into_panic() {
+            Ok(payload) => panic::resume_unwind(payload),
+            Err(err) => {
+                panic!("Failed to run the scenarios: {}", err);
+            },
+        },
+    }
+}
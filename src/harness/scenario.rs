@@ -0,0 +1,851 @@
+use std::{
+    fmt,
+    fs,
+    io,
+    path::Path,
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use serde::{
+    Serialize,
+    Deserialize,
+};
+
+use super::super::store::utils::PackingStrategy;
+
+use super::super::config::{
+    MIN_WRITER_MEMORY_BUDGET,
+    MAX_WRITER_MEMORY_BUDGET,
+};
+
+pub use super::super::config::DEFAULT_WRITER_MEMORY_BUDGET;
+pub use super::super::store::utils::{
+    MergePolicyConfig,
+    DEFAULT_TARGET_DOCS_PER_SEGMENT,
+};
+
+// CommitStrategy
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitStrategy {
+    /// Single final commit after all the documents were added
+    Single,
+    /// Commit after every `add_document`
+    EveryDocument,
+    /// Commit after every `docs` documents
+    EveryDocs {
+        docs: usize,
+    },
+    /// Commit once `millis` elapsed since the last commit
+    Interval {
+        millis: u64,
+    },
+    /// Commit once the added people reach `bytes` of JSON since the last commit
+    Bytes {
+        bytes: u64,
+    },
+    /// Documents arrive at random, exponentially distributed intervals averaging
+    /// `docs_per_second`, commits happen every `commit_millis`
+    Poisson {
+        docs_per_second: u64,
+        commit_millis: u64,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// Bursts of a random number of documents, each followed by a commit and by a
+    /// random idle time
+    Bursty {
+        min_burst_docs: usize,
+        max_burst_docs: usize,
+        min_idle_millis: u64,
+        max_idle_millis: u64,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+impl CommitStrategy {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            CommitStrategy::EveryDocs{ docs: 0 } => Err("every_docs needs at least 1 doc".to_string()),
+            CommitStrategy::Bytes{ bytes: 0 } => Err("bytes needs at least 1 byte".to_string()),
+            CommitStrategy::Poisson{ docs_per_second: 0, .. } => Err("poisson needs at least 1 doc per second".to_string()),
+            CommitStrategy::Bursty{ min_burst_docs, max_burst_docs, min_idle_millis, max_idle_millis, .. } => {
+                if *min_burst_docs == 0 || min_burst_docs > max_burst_docs {
+                    Err(format!("Invalid burst size {}-{}", min_burst_docs, max_burst_docs))
+                } else if min_idle_millis > max_idle_millis {
+                    Err(format!("Invalid idle time {}-{}", min_idle_millis, max_idle_millis))
+                } else {
+                    Ok(())
+                }
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for CommitStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommitStrategy::Single => write!(f, "single"),
+            CommitStrategy::EveryDocument => write!(f, "every_document"),
+            CommitStrategy::EveryDocs{ docs } => write!(f, "every_docs:{}", docs),
+            CommitStrategy::Interval{ millis } => write!(f, "interval:{}", millis),
+            CommitStrategy::Bytes{ bytes } => write!(f, "bytes:{}", bytes),
+            CommitStrategy::Poisson{ docs_per_second, commit_millis, seed } => {
+                write!(f, "poisson:{}:{}:{}", docs_per_second, commit_millis, seed)
+            },
+            CommitStrategy::Bursty{ min_burst_docs, max_burst_docs, min_idle_millis, max_idle_millis, seed } => write!(
+                f,
+                "bursty:{}-{}:{}-{}:{}",
+                min_burst_docs,
+                max_burst_docs,
+                min_idle_millis,
+                max_idle_millis,
+                seed,
+            ),
+        }
+    }
+}
+
+fn parse_number<T: FromStr>(value: &str, spec: &str) -> Result<T, String> {
+    value
+        .trim()
+        .replace('_', "")
+        .parse::<T>()
+        .map_err(|_| format!("Invalid number {:?} in commit strategy {:?}", value, spec))
+}
+
+fn parse_range<T: FromStr>(value: &str, spec: &str) -> Result<(T, T), String> {
+    match value.split_once('-') {
+        Some((min, max)) => Ok((parse_number(min, spec)?, parse_number(max, spec)?)),
+        None => Err(format!("Invalid range {:?} in commit strategy {:?}, expected <min>-<max>", value, spec)),
+    }
+}
+
+impl FromStr for CommitStrategy {
+    type Err = String;
+
+    /// Parses the `Display` format, the seeds being optional
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parts = spec.trim().split(':').collect::<Vec<&str>>();
+
+        let commit_strategy = match parts.as_slice() {
+            ["single"] => CommitStrategy::Single,
+            ["every_document"] => CommitStrategy::EveryDocument,
+            ["every_docs", docs] => CommitStrategy::EveryDocs{
+                docs: parse_number(docs, spec)?,
+            },
+            ["interval", millis] => CommitStrategy::Interval{
+                millis: parse_number(millis, spec)?,
+            },
+            ["bytes", bytes] => CommitStrategy::Bytes{
+                bytes: parse_number(bytes, spec)?,
+            },
+            ["poisson", docs_per_second, commit_millis, seed @ ..] if seed.len() <= 1 => CommitStrategy::Poisson{
+                docs_per_second: parse_number(docs_per_second, spec)?,
+                commit_millis: parse_number(commit_millis, spec)?,
+                seed: match seed.first() {
+                    Some(seed) => parse_number(seed, spec)?,
+                    None => 0,
+                },
+            },
+            ["bursty", burst_docs, idle_millis, seed @ ..] if seed.len() <= 1 => {
+                let (min_burst_docs, max_burst_docs) = parse_range(burst_docs, spec)?;
+                let (min_idle_millis, max_idle_millis) = parse_range(idle_millis, spec)?;
+
+                CommitStrategy::Bursty{
+                    min_burst_docs,
+                    max_burst_docs,
+                    min_idle_millis,
+                    max_idle_millis,
+                    seed: match seed.first() {
+                        Some(seed) => parse_number(seed, spec)?,
+                        None => 0,
+                    },
+                }
+            },
+            _ => {
+                return Err(format!("Unknown commit strategy {:?}", spec));
+            },
+        };
+
+        commit_strategy.validate()?;
+
+        Ok(commit_strategy)
+    }
+}
+
+// WorkloadMix
+
+/// Relative weights of the operations a run applies, every step picking one of them.
+///
+/// Updates and deletes hit a random person indexed earlier in the run, and fall back
+/// to an insert while there's none. Once every person of the data was inserted, inserts
+/// fall back to an update, or end the run when the mix is insert only.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkloadMix {
+    pub inserts: u32,
+    /// `delete_term` on the `id` field followed by re-adding the person
+    pub updates: u32,
+    /// `delete_term` on the `id` field alone
+    pub deletes: u32,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+impl Default for WorkloadMix {
+    fn default() -> Self {
+        WorkloadMix{
+            inserts: 1,
+            updates: 0,
+            deletes: 0,
+            seed: 0,
+        }
+    }
+}
+
+impl WorkloadMix {
+    /// Only inserts, as in the README runs
+    pub fn is_insert_only(&self) -> bool {
+        self.updates == 0 && self.deletes == 0
+    }
+}
+
+impl fmt::Display for WorkloadMix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}:{}", self.inserts, self.updates, self.deletes, self.seed)
+    }
+}
+
+impl FromStr for WorkloadMix {
+    type Err = String;
+
+    /// Parses `<inserts>:<updates>:<deletes>[:<seed>]`, e.g. `70:20:10`
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parts = spec.trim().split(':').collect::<Vec<&str>>();
+
+        let (weights, seed) = match parts.as_slice() {
+            [inserts, updates, deletes] => ([inserts, updates, deletes], "0"),
+            [inserts, updates, deletes, seed] => ([inserts, updates, deletes], *seed),
+            _ => {
+                return Err(format!("Invalid workload {:?}, expected <inserts>:<updates>:<deletes>[:<seed>]", spec));
+            },
+        };
+
+        let parse_weight = |weight: &str| weight
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid weight {:?} in workload {:?}", weight, spec));
+
+        let workload_mix = WorkloadMix{
+            inserts: parse_weight(weights[0])?,
+            updates: parse_weight(weights[1])?,
+            deletes: parse_weight(weights[2])?,
+            seed: seed
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid seed {:?} in workload {:?}", seed, spec))?,
+        };
+
+        if workload_mix.inserts == 0 {
+            return Err(format!("Workload {:?} needs inserts to have anything to update or delete", spec));
+        }
+
+        Ok(workload_mix)
+    }
+}
+
+// WaitMode
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitMode {
+    /// Drop the writer right after the last commit
+    NoWait,
+    /// Call `IndexWriter::wait_merging_threads` after the last commit
+    WaitMergingThreads,
+}
+
+impl fmt::Display for WaitMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitMode::NoWait => write!(f, "no_wait"),
+            WaitMode::WaitMergingThreads => write!(f, "wait"),
+        }
+    }
+}
+
+// IndexStorage
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexStorage {
+    /// Own directory in `Config::index_people_path`, removed after the run
+    #[default]
+    Directory,
+    /// In-process `RamDirectory`, leaving disk IO out of the timings
+    Ram,
+}
+
+impl fmt::Display for IndexStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexStorage::Directory => write!(f, "directory"),
+            IndexStorage::Ram => write!(f, "ram"),
+        }
+    }
+}
+
+// CpuPinning
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CpuPinning {
+    /// Threads of the run are scheduled by the OS on any CPU
+    #[default]
+    Unpinned,
+    /// The run and the writer threads it spawns are pinned to the given CPUs
+    Cpus(Vec<usize>),
+    /// Pinned to the CPUs of the concurrency slot running it, the available CPUs
+    /// being split evenly between the slots
+    Slot,
+}
+
+impl fmt::Display for CpuPinning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CpuPinning::Unpinned => write!(f, "unpinned"),
+            CpuPinning::Cpus(cpus) => {
+                let cpus = cpus
+                    .iter()
+                    .map(|cpu| cpu.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",");
+
+                write!(f, "cpus:{}", cpus)
+            },
+            CpuPinning::Slot => write!(f, "slot"),
+        }
+    }
+}
+
+impl FromStr for CpuPinning {
+    type Err = String;
+
+    /// Parses `unpinned`, `slot` or a CPU list such as `0-3,6`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+
+        match value {
+            "unpinned" | "none" => {
+                return Ok(CpuPinning::Unpinned);
+            },
+            "slot" => {
+                return Ok(CpuPinning::Slot);
+            },
+            _ => {},
+        }
+
+        let mut cpus: Vec<usize> = Vec::new();
+        let parse_cpu = |cpu: &str| cpu
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid CPU {:?} in {:?}", cpu, value));
+
+        for part in value.strip_prefix("cpus:").unwrap_or(value).split(',') {
+            match part.split_once('-') {
+                Some((first, last)) => {
+                    let (first, last) = (parse_cpu(first)?, parse_cpu(last)?);
+                    if first > last {
+                        return Err(format!("Invalid CPU range {:?}", part));
+                    }
+
+                    cpus.extend(first..=last);
+                },
+                None => cpus.push(parse_cpu(part)?),
+            }
+        }
+
+        cpus.sort_unstable();
+        cpus.dedup();
+
+        Ok(CpuPinning::Cpus(cpus))
+    }
+}
+
+// Scenario
+
+pub const DEFAULT_DOC_COUNT: usize = 1000;
+pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(120);
+/// Documents every conversion thread may hold ahead of the writer
+pub const DEFAULT_CONVERSION_QUEUE: usize = 256;
+
+/// Mirrors `tantivy::indexer::index_writer::MAX_NUM_THREAD`
+const MAX_DEFAULT_WRITER_THREADS: usize = 8;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Scenario {
+    pub name: String,
+    pub commit_strategy: CommitStrategy,
+    pub merge_policy: MergePolicyConfig,
+    pub wait_mode: WaitMode,
+    pub doc_count: usize,
+    pub writer_memory_budget: usize,
+    /// Deadline for `wait_merging_threads`, `None` waits forever
+    #[serde(default)]
+    pub wait_timeout: Option<Duration>,
+    /// Drops degenerate merge candidates before they reach the writer
+    #[serde(default)]
+    pub filter_degenerate_candidates: bool,
+    #[serde(default)]
+    pub index_storage: IndexStorage,
+    /// Indexing threads of the writer, `None` leaving the choice to tantivy
+    #[serde(default)]
+    pub writer_threads: Option<usize>,
+    #[serde(default)]
+    pub cpu_pinning: CpuPinning,
+    /// Measured trials, each on a fresh index
+    #[serde(default = "default_repetitions")]
+    pub repetitions: usize,
+    /// Trials run beforehand and left out of the results
+    #[serde(default)]
+    pub warmup_runs: usize,
+    /// Every step of the run applies one operation, `doc_count` being the number of steps
+    #[serde(default)]
+    pub workload: WorkloadMix,
+    /// Threads converting people into documents ahead of the writer, `None` for one per CPU
+    #[serde(default)]
+    pub conversion_threads: Option<usize>,
+    /// Bound of the channels into and out of every conversion thread
+    #[serde(default = "default_conversion_queue")]
+    pub conversion_queue: usize,
+}
+
+fn default_repetitions() -> usize {
+    1
+}
+
+fn default_conversion_queue() -> usize {
+    DEFAULT_CONVERSION_QUEUE
+}
+
+impl Scenario {
+    pub fn new(
+        commit_strategy: CommitStrategy,
+        merge_policy: MergePolicyConfig,
+        wait_mode: WaitMode,
+        doc_count: usize,
+        writer_memory_budget: usize,
+    ) -> Self {
+        let name = format!(
+            "{}-{}-{}-{}-{}",
+            commit_strategy,
+            merge_policy,
+            wait_mode,
+            doc_count,
+            writer_memory_budget,
+        );
+
+        Scenario{
+            name,
+            commit_strategy,
+            merge_policy,
+            wait_mode,
+            doc_count,
+            writer_memory_budget,
+            wait_timeout: Some(DEFAULT_WAIT_TIMEOUT),
+            filter_degenerate_candidates: false,
+            index_storage: IndexStorage::default(),
+            writer_threads: None,
+            cpu_pinning: CpuPinning::default(),
+            repetitions: default_repetitions(),
+            warmup_runs: 0,
+            workload: WorkloadMix::default(),
+            conversion_threads: None,
+            conversion_queue: DEFAULT_CONVERSION_QUEUE,
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn with_writer_memory_budget(mut self, writer_memory_budget: usize) -> Self {
+        self.writer_memory_budget = writer_memory_budget;
+        self
+    }
+
+    pub fn with_wait_timeout(mut self, wait_timeout: Option<Duration>) -> Self {
+        self.wait_timeout = wait_timeout;
+        self
+    }
+
+    pub fn with_filter_degenerate_candidates(mut self, filter_degenerate_candidates: bool) -> Self {
+        self.filter_degenerate_candidates = filter_degenerate_candidates;
+        self
+    }
+
+    pub fn with_index_storage(mut self, index_storage: IndexStorage) -> Self {
+        self.index_storage = index_storage;
+        self
+    }
+
+    pub fn with_writer_threads(mut self, writer_threads: Option<usize>) -> Self {
+        self.writer_threads = writer_threads;
+        self
+    }
+
+    pub fn with_cpu_pinning(mut self, cpu_pinning: CpuPinning) -> Self {
+        self.cpu_pinning = cpu_pinning;
+        self
+    }
+
+    /// Scenarios with updates or deletes get the mix appended to their name
+    pub fn with_workload(mut self, workload: WorkloadMix) -> Self {
+        if !workload.is_insert_only() {
+            self.name = format!("{}-{}", self.name, workload);
+        }
+
+        self.workload = workload;
+        self
+    }
+
+    pub fn with_repetitions(mut self, repetitions: usize, warmup_runs: usize) -> Self {
+        self.repetitions = repetitions;
+        self.warmup_runs = warmup_runs;
+        self
+    }
+
+    pub fn with_conversion(mut self, conversion_threads: Option<usize>, conversion_queue: usize) -> Self {
+        self.conversion_threads = conversion_threads;
+        self.conversion_queue = conversion_queue;
+        self
+    }
+
+    /// Indexing threads of the writer, when not set the ones `Index::writer` would
+    /// pick from the CPUs the current thread may use
+    pub fn effective_writer_threads(&self) -> usize {
+        if let Some(writer_threads) = self.writer_threads {
+            return writer_threads;
+        }
+
+        let writer_threads = match thread::available_parallelism() {
+            Ok(parallelism) => parallelism.get().min(MAX_DEFAULT_WRITER_THREADS),
+            Err(_) => 1,
+        };
+
+        if self.writer_memory_budget / writer_threads < MIN_WRITER_MEMORY_BUDGET {
+            (self.writer_memory_budget / MIN_WRITER_MEMORY_BUDGET).max(1)
+        } else {
+            writer_threads
+        }
+    }
+
+    /// Checks the settings tantivy or the runner would otherwise only reject once
+    /// the scenario runs, e.g. a memory budget too small for the writer threads
+    pub fn validate(&self) -> Result<(), String> {
+        self.commit_strategy.validate()?;
+
+        if self.writer_threads == Some(0) {
+            return Err("The writer needs at least one thread".to_string());
+        }
+
+        let writer_threads = self.effective_writer_threads();
+        let thread_memory_budget = self.writer_memory_budget / writer_threads;
+
+        if !(MIN_WRITER_MEMORY_BUDGET..MAX_WRITER_MEMORY_BUDGET).contains(&thread_memory_budget) {
+            return Err(format!(
+                "Writer memory budget of {} bytes leaves {} to each of the {} writer threads, \
+                tantivy accepts from {} up to {} excluded",
+                self.writer_memory_budget,
+                thread_memory_budget,
+                writer_threads,
+                MIN_WRITER_MEMORY_BUDGET,
+                MAX_WRITER_MEMORY_BUDGET,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Runs `A` to `H` as described in the README
+    pub fn presets() -> Vec<Scenario> {
+        let target_docs = MergePolicyConfig::TargetDocs{
+            target_docs_per_segment: DEFAULT_TARGET_DOCS_PER_SEGMENT,
+            packing: PackingStrategy::default(),
+        };
+
+        let presets = [
+            ("a", CommitStrategy::Single, MergePolicyConfig::Whenever, WaitMode::NoWait),
+            ("b", CommitStrategy::Single, MergePolicyConfig::Whenever, WaitMode::WaitMergingThreads),
+            ("c", CommitStrategy::Single, target_docs.clone(), WaitMode::NoWait),
+            ("d", CommitStrategy::Single, target_docs.clone(), WaitMode::WaitMergingThreads),
+            ("e", CommitStrategy::EveryDocument, MergePolicyConfig::Whenever, WaitMode::NoWait),
+            ("f", CommitStrategy::EveryDocument, MergePolicyConfig::Whenever, WaitMode::WaitMergingThreads),
+            ("g", CommitStrategy::EveryDocument, target_docs.clone(), WaitMode::NoWait),
+            ("h", CommitStrategy::EveryDocument, target_docs, WaitMode::WaitMergingThreads),
+        ];
+
+        presets
+            .into_iter()
+            .map(|(name, commit_strategy, merge_policy, wait_mode)| {
+                Scenario::new(
+                    commit_strategy,
+                    merge_policy,
+                    wait_mode,
+                    DEFAULT_DOC_COUNT,
+                    DEFAULT_WRITER_MEMORY_BUDGET,
+                ).with_name(name)
+            })
+            .collect::<Vec<Scenario>>()
+    }
+
+    pub fn preset(name: &str) -> Option<Scenario> {
+        Scenario::presets()
+            .into_iter()
+            .find(|scenario| scenario.name == name)
+    }
+}
+
+// ScenarioMatrix
+
+/// Cartesian product of all the scenario dimensions, omitted dimensions taking the
+/// values of the default matrix
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScenarioMatrix {
+    pub commit_strategies: Vec<CommitStrategy>,
+    pub merge_policies: Vec<MergePolicyConfig>,
+    pub wait_modes: Vec<WaitMode>,
+    pub doc_counts: Vec<usize>,
+    pub writer_memory_budgets: Vec<usize>,
+    pub wait_timeout: Option<Duration>,
+    pub index_storage: IndexStorage,
+    pub writer_threads: Option<usize>,
+    pub cpu_pinning: CpuPinning,
+    pub repetitions: usize,
+    pub warmup_runs: usize,
+    pub workloads: Vec<WorkloadMix>,
+    pub conversion_threads: Option<usize>,
+    pub conversion_queue: usize,
+}
+
+impl Default for ScenarioMatrix {
+    fn default() -> Self {
+        ScenarioMatrix{
+            commit_strategies: vec![CommitStrategy::Single, CommitStrategy::EveryDocument],
+            merge_policies: vec![
+                MergePolicyConfig::Whenever,
+                MergePolicyConfig::TargetDocs{
+                    target_docs_per_segment: DEFAULT_TARGET_DOCS_PER_SEGMENT,
+                    packing: PackingStrategy::default(),
+                },
+            ],
+            wait_modes: vec![WaitMode::NoWait, WaitMode::WaitMergingThreads],
+            doc_counts: vec![DEFAULT_DOC_COUNT],
+            writer_memory_budgets: vec![DEFAULT_WRITER_MEMORY_BUDGET],
+            wait_timeout: Some(DEFAULT_WAIT_TIMEOUT),
+            index_storage: IndexStorage::default(),
+            writer_threads: None,
+            cpu_pinning: CpuPinning::default(),
+            repetitions: default_repetitions(),
+            warmup_runs: 0,
+            workloads: vec![WorkloadMix::default()],
+            conversion_threads: None,
+            conversion_queue: DEFAULT_CONVERSION_QUEUE,
+        }
+    }
+}
+
+impl ScenarioMatrix {
+    /// Reads a `.toml` file, or JSON for any other extension, e.g.
+    ///
+    /// ```toml
+    /// commitStrategies = ["single"]
+    /// mergePolicies = ["whenever", { tiered = { merge_factor = 4 } }]
+    /// waitModes = ["wait_merging_threads"]
+    /// waitTimeout = { secs = 60, nanos = 0 }
+    /// ```
+    pub fn read<T: AsRef<Path>>(path: T) -> Result<ScenarioMatrix, io::Error> {
+        let content = fs::read_to_string(path.as_ref())?;
+
+        let is_toml = match path.as_ref().extension() {
+            Some(extension) => extension == "toml",
+            None => false,
+        };
+
+        if is_toml {
+            toml::from_str(&content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        } else {
+            serde_json::from_str(&content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        }
+    }
+
+    pub fn scenarios(&self) -> Vec<Scenario> {
+        let mut scenarios: Vec<Scenario> = Vec::new();
+
+        for commit_strategy in self.commit_strategies.iter() {
+            for merge_policy in self.merge_policies.iter() {
+                for wait_mode in self.wait_modes.iter() {
+                    for doc_count in self.doc_counts.iter() {
+                        for writer_memory_budget in self.writer_memory_budgets.iter() {
+                            for workload in self.workloads.iter() {
+                                scenarios.push(Scenario::new(
+                                    commit_strategy.clone(),
+                                    merge_policy.clone(),
+                                    *wait_mode,
+                                    *doc_count,
+                                    *writer_memory_budget,
+                                )
+                                    .with_wait_timeout(self.wait_timeout)
+                                    .with_index_storage(self.index_storage)
+                                    .with_writer_threads(self.writer_threads)
+                                    .with_cpu_pinning(self.cpu_pinning.clone())
+                                    .with_repetitions(self.repetitions, self.warmup_runs)
+                                    .with_conversion(self.conversion_threads, self.conversion_queue)
+                                    .with_workload(*workload));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        scenarios
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(writer_memory_budget: usize, writer_threads: Option<usize>) -> Scenario {
+        Scenario::new(
+            CommitStrategy::Single,
+            MergePolicyConfig::Whenever,
+            WaitMode::NoWait,
+            100,
+            writer_memory_budget,
+        ).with_writer_threads(writer_threads)
+    }
+
+    #[test]
+    fn commit_strategies_round_trip() {
+        let commit_strategies = [
+            CommitStrategy::Single,
+            CommitStrategy::EveryDocument,
+            CommitStrategy::EveryDocs{ docs: 100 },
+            CommitStrategy::Interval{ millis: 0 },
+            CommitStrategy::Bytes{ bytes: 1 << 20 },
+            CommitStrategy::Poisson{ docs_per_second: 500, commit_millis: 1000, seed: 7 },
+            CommitStrategy::Bursty{
+                min_burst_docs: 1,
+                max_burst_docs: 50,
+                min_idle_millis: 0,
+                max_idle_millis: 200,
+                seed: 3,
+            },
+        ];
+
+        for commit_strategy in commit_strategies {
+            let spec = commit_strategy.to_string();
+
+            assert_eq!(spec.parse::<CommitStrategy>(), Ok(commit_strategy.clone()), "{}", spec);
+            assert_eq!(commit_strategy.validate(), Ok(()));
+
+            let json = serde_json::to_string(&commit_strategy).unwrap();
+            assert_eq!(serde_json::from_str::<CommitStrategy>(&json).unwrap(), commit_strategy, "{}", json);
+        }
+    }
+
+    #[test]
+    fn commit_strategy_seeds_are_optional() {
+        assert_eq!("poisson:10:1_000".parse::<CommitStrategy>(), Ok(CommitStrategy::Poisson{
+            docs_per_second: 10,
+            commit_millis: 1000,
+            seed: 0,
+        }));
+        assert_eq!(" bursty:2-5:10-20 ".parse::<CommitStrategy>(), Ok(CommitStrategy::Bursty{
+            min_burst_docs: 2,
+            max_burst_docs: 5,
+            min_idle_millis: 10,
+            max_idle_millis: 20,
+            seed: 0,
+        }));
+    }
+
+    #[test]
+    fn rejects_invalid_commit_strategies() {
+        let specs = [
+            "",
+            "never",
+            "single:1",
+            "every_docs",
+            "every_docs:0",
+            "every_docs:-1",
+            "interval:soon",
+            "bytes:0",
+            "poisson:0:1000",
+            "poisson:10:1000:1:2",
+            "bursty:5-2:10-20",
+            "bursty:0-2:10-20",
+            "bursty:2-5:20-10",
+            "bursty:2:10-20",
+        ];
+
+        for spec in specs {
+            assert!(spec.parse::<CommitStrategy>().is_err(), "{:?}", spec);
+        }
+
+        // Deserialized strategies are only checked by `validate`
+        let commit_strategy = serde_json::from_str::<CommitStrategy>(r#"{"every_docs":{"docs":0}}"#).unwrap();
+        assert!(commit_strategy.validate().is_err());
+        assert!(commit_strategy.trigger().is_err());
+    }
+
+    #[test]
+    fn validates_the_budget_of_every_writer_thread() {
+        assert!(scenario(DEFAULT_WRITER_MEMORY_BUDGET, Some(3)).validate().is_ok());
+        assert!(scenario(DEFAULT_WRITER_MEMORY_BUDGET, Some(4)).validate().is_err());
+        assert!(scenario(DEFAULT_WRITER_MEMORY_BUDGET, Some(0)).validate().is_err());
+        assert!(scenario(MIN_WRITER_MEMORY_BUDGET - 1, Some(1)).validate().is_err());
+
+        assert!(scenario(MAX_WRITER_MEMORY_BUDGET - 1, Some(1)).validate().is_ok());
+        assert!(scenario(MAX_WRITER_MEMORY_BUDGET, Some(1)).validate().is_err());
+        assert!(scenario(MAX_WRITER_MEMORY_BUDGET * 2, Some(3)).validate().is_ok());
+    }
+
+    #[test]
+    fn default_writer_threads_fit_the_budget() {
+        for writer_memory_budget in [MIN_WRITER_MEMORY_BUDGET, DEFAULT_WRITER_MEMORY_BUDGET, 1_000_000_000] {
+            let scenario = scenario(writer_memory_budget, None);
+            let writer_threads = scenario.effective_writer_threads();
+
+            assert!((1..=MAX_DEFAULT_WRITER_THREADS).contains(&writer_threads));
+            assert!(scenario.validate().is_ok(), "{}", writer_memory_budget);
+        }
+
+        assert_eq!(scenario(MIN_WRITER_MEMORY_BUDGET, None).effective_writer_threads(), 1);
+        assert!(scenario(MIN_WRITER_MEMORY_BUDGET - 1, None).validate().is_err());
+    }
+
+    #[test]
+    fn validates_the_commit_strategy() {
+        let scenario = Scenario::new(
+            CommitStrategy::EveryDocs{ docs: 0 },
+            MergePolicyConfig::Whenever,
+            WaitMode::NoWait,
+            100,
+            DEFAULT_WRITER_MEMORY_BUDGET,
+        );
+
+        assert!(scenario.validate().is_err());
+    }
+}
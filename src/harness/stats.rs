@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use serde::{
+    Serialize,
+    Deserialize,
+};
+
+use super::super::store::utils::MergePolicyCall;
+use super::result::TrialResult;
+
+// Distribution
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Distribution {
+    pub count: u64,
+    pub min: u64,
+    pub median: u64,
+    pub p95: u64,
+    pub max: u64,
+    pub mean: f64,
+    /// Sample standard deviation, `0` for less than two values
+    #[serde(default)]
+    pub std_dev: f64,
+}
+
+impl Distribution {
+    pub fn from_values(mut values: Vec<u64>) -> Self {
+        if values.is_empty() {
+            return Distribution::default();
+        }
+
+        values.sort_unstable();
+
+        let count = values.len();
+        let sum: u64 = values.iter().sum();
+        let mean = sum as f64 / count as f64;
+
+        let std_dev = match count {
+            1 => 0.0,
+            _ => {
+                let squares = values
+                    .iter()
+                    .map(|value| (*value as f64 - mean).powi(2))
+                    .sum::<f64>();
+
+                (squares / (count - 1) as f64).sqrt()
+            },
+        };
+
+        Distribution{
+            count: count as u64,
+            min: values[0],
+            median: values[percentile_index(count, 50)],
+            p95: values[percentile_index(count, 95)],
+            max: values[count - 1],
+            mean,
+            std_dev,
+        }
+    }
+}
+
+/// Nearest-rank percentile of a sorted, non-empty list
+pub(super) fn percentile_index(count: usize, percentile: usize) -> usize {
+    let rank = (percentile * count).div_ceil(100);
+    rank.clamp(1, count) - 1
+}
+
+// TrialStats
+
+/// Spread of the measured trials of a scenario, warmup runs excluded
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrialStats {
+    pub index_time_micros: Distribution,
+    /// Merge candidates handed to the writer
+    pub merges: Distribution,
+    pub final_segments: Distribution,
+}
+
+impl TrialStats {
+    pub fn from_trials(trials: &[TrialResult]) -> Self {
+        TrialStats{
+            index_time_micros: Distribution::from_values(
+                trials.iter().map(|trial| trial.total_index_time.as_micros() as u64).collect(),
+            ),
+            merges: Distribution::from_values(trials.iter().map(|trial| trial.merges).collect()),
+            final_segments: Distribution::from_values(trials.iter().map(|trial| trial.final_segments).collect()),
+        }
+    }
+}
+
+// MergePolicyStats
+
+/// Aggregated `compute_merge_candidates` calls of a single run
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MergePolicyStats {
+    pub calls: u64,
+    /// Number of calls per number of segments passed to the policy
+    pub input_sizes: BTreeMap<usize, u64>,
+    /// Number of calls per number of returned merge candidates
+    pub output_candidate_counts: BTreeMap<usize, u64>,
+    /// Number of returned candidates per number of segments in the candidate
+    pub candidate_sizes: BTreeMap<usize, u64>,
+    /// Docs of the segments (deletes excluded) grouped into a single candidate
+    pub candidate_docs: Distribution,
+    /// Calls observed between two consecutive commits
+    pub calls_per_commit: Distribution,
+    pub policy_time_micros: Distribution,
+    pub total_policy_time_micros: u64,
+}
+
+impl MergePolicyStats {
+    /// Merge candidates returned over all the calls
+    pub fn merges(&self) -> u64 {
+        self.output_candidate_counts
+            .iter()
+            .map(|(candidates, calls)| *candidates as u64 * calls)
+            .sum()
+    }
+
+    pub fn from_calls(calls: &[MergePolicyCall]) -> Self {
+        let mut stats = MergePolicyStats::default();
+
+        let mut candidate_docs: Vec<u64> = Vec::new();
+        let mut calls_per_commit: BTreeMap<u64, u64> = BTreeMap::new();
+        let mut policy_time_micros: Vec<u64> = Vec::new();
+
+        for call in calls {
+            stats.calls += 1;
+            *stats.input_sizes.entry(call.segments.len()).or_insert(0) += 1;
+            *stats.output_candidate_counts.entry(call.candidates.len()).or_insert(0) += 1;
+
+            for candidate in call.candidates.iter() {
+                *stats.candidate_sizes.entry(candidate.len()).or_insert(0) += 1;
+
+                let docs = call.segments
+                    .iter()
+                    .filter(|segment| candidate.contains(&segment.id))
+                    .map(|segment| segment.num_docs as u64)
+                    .sum::<u64>();
+                candidate_docs.push(docs);
+            }
+
+            *calls_per_commit.entry(call.commit).or_insert(0) += 1;
+
+            policy_time_micros.push(call.elapsed_micros);
+            stats.total_policy_time_micros += call.elapsed_micros;
+        }
+
+        // Commits without any call count as well, up to the last observed one
+        if let Some(last_commit) = calls_per_commit.keys().next_back().copied() {
+            for commit in 0..=last_commit {
+                calls_per_commit.entry(commit).or_insert(0);
+            }
+        }
+
+        stats.candidate_docs = Distribution::from_values(candidate_docs);
+        stats.calls_per_commit = Distribution::from_values(calls_per_commit.into_values().collect());
+        stats.policy_time_micros = Distribution::from_values(policy_time_micros);
+
+        stats
+    }
+}
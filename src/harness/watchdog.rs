@@ -0,0 +1,69 @@
+use std::{
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+use tantivy::{
+    IndexWriter,
+    TantivyError,
+};
+
+use super::super::store::utils::MergePolicyRecorder;
+use super::result::RunOutcome;
+
+/// Calls `IndexWriter::wait_merging_threads` and gives up once `timeout` elapses.
+///
+/// On timeout the recorder is stopped, so the merge policy of the writer returns no
+/// more candidates, and the merges in flight, which can't be cancelled, get up to
+/// `timeout` again to finish. Only a writer still busy past that is left running
+/// in the background.
+pub fn wait_merging_threads(
+    writer: IndexWriter,
+    timeout: Option<Duration>,
+    recorder: &MergePolicyRecorder,
+) -> Result<RunOutcome, TantivyError> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => {
+            writer.wait_merging_threads()?;
+            return Ok(RunOutcome::Completed);
+        },
+    };
+
+    let (sender, receiver) = mpsc::channel::<Result<(), TantivyError>>();
+
+    let spawn_result = thread::Builder::new()
+        .name("wait-merging-threads".to_string())
+        .spawn(move || {
+            // The receiver is gone when the watchdog already gave up
+            let _ = sender.send(writer.wait_merging_threads());
+        });
+
+    let handle = match spawn_result {
+        Ok(handle) => handle,
+        Err(err) => {
+            return Err(TantivyError::SystemError(format!("Failed to spawn watchdog thread: {}", err)));
+        },
+    };
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => {
+            let _ = handle.join();
+            result?;
+            Ok(RunOutcome::Completed)
+        },
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            recorder.stop();
+
+            // Whether the writer shut down cleanly or not, the run timed out
+            if receiver.recv_timeout(timeout).is_ok() {
+                let _ = handle.join();
+            }
+
+            Ok(RunOutcome::TimedOut)
+        },
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            Err(TantivyError::SystemError("Watchdog thread exited without a result".to_string()))
+        },
+    }
+}
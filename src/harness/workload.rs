@@ -0,0 +1,238 @@
+use rand::{
+    Rng,
+    SeedableRng,
+    rngs::StdRng,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+
+use super::super::models::person::Person;
+use super::scenario::WorkloadMix;
+
+// Operation
+
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Insert(Person),
+    /// Delete of the person by id and re-add
+    Update(Person),
+    Delete(Person),
+}
+
+impl Operation {
+    pub fn person(&self) -> &Person {
+        match self {
+            Operation::Insert(person) => person,
+            Operation::Update(person) => person,
+            Operation::Delete(person) => person,
+        }
+    }
+}
+
+// WorkloadCounts
+
+/// Operations applied by a run
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkloadCounts {
+    pub inserts: u64,
+    pub updates: u64,
+    pub deletes: u64,
+}
+
+impl WorkloadCounts {
+    pub fn record(&mut self, operation: &Operation) {
+        match operation {
+            Operation::Insert(_) => self.inserts += 1,
+            Operation::Update(_) => self.updates += 1,
+            Operation::Delete(_) => self.deletes += 1,
+        }
+    }
+
+    /// People indexed once the operations were applied
+    pub fn live_docs(&self) -> u64 {
+        self.inserts.saturating_sub(self.deletes)
+    }
+}
+
+// WorkloadGenerator
+
+/// Seeded sequence of `steps` operations over the people read from `people`, following
+/// the weights of the mix.
+///
+/// People are pulled from `people` as they get inserted, only the ones still in the
+/// index are kept around for the updates and deletes, and none for insert only mixes.
+pub struct WorkloadGenerator<I: Iterator<Item = Person>> {
+    people: I,
+    mix: WorkloadMix,
+    rng: StdRng,
+    steps: usize,
+    /// People currently in the index
+    live: Vec<Person>,
+}
+
+impl<I: Iterator<Item = Person>> WorkloadGenerator<I> {
+    pub fn new(people: I, mix: WorkloadMix, steps: usize) -> Self {
+        WorkloadGenerator{
+            people,
+            mix,
+            rng: StdRng::seed_from_u64(mix.seed),
+            steps,
+            live: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self) -> Option<Operation> {
+        let person = self.people.next()?;
+
+        if !self.mix.is_insert_only() {
+            self.live.push(person.clone());
+        }
+
+        Some(Operation::Insert(person))
+    }
+
+    fn update(&mut self) -> Option<Operation> {
+        if self.live.is_empty() {
+            return None;
+        }
+
+        let index = self.rng.gen_range(0..self.live.len());
+        Some(Operation::Update(self.live[index].clone()))
+    }
+
+    fn delete(&mut self) -> Option<Operation> {
+        if self.live.is_empty() {
+            return None;
+        }
+
+        let index = self.rng.gen_range(0..self.live.len());
+        Some(Operation::Delete(self.live.swap_remove(index)))
+    }
+}
+
+impl<I: Iterator<Item = Person>> Iterator for WorkloadGenerator<I> {
+    type Item = Operation;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.steps == 0 {
+            return None;
+        }
+        self.steps -= 1;
+
+        let total = self.mix.inserts + self.mix.updates + self.mix.deletes;
+        let pick = self.rng.gen_range(0..total.max(1));
+
+        let operation = if pick < self.mix.inserts {
+            match self.insert() {
+                Some(operation) => Some(operation),
+                // Insert only runs are bounded by the data, as they always were
+                None if self.mix.is_insert_only() => None,
+                None => self.update(),
+            }
+        } else if pick < self.mix.inserts + self.mix.updates {
+            self.update().or_else(|| self.insert())
+        } else {
+            self.delete().or_else(|| self.insert())
+        };
+
+        // Nothing left to insert and nothing to update either
+        if operation.is_none() {
+            self.steps = 0;
+        }
+
+        operation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use super::super::super::generator::PersonGenerator;
+
+    fn mix(inserts: u32, updates: u32, deletes: u32) -> WorkloadMix {
+        WorkloadMix{
+            inserts,
+            updates,
+            deletes,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn pulls_people_as_they_get_inserted() {
+        let pulled = Cell::new(0);
+        let people = PersonGenerator::new(1).inspect(|_| pulled.set(pulled.get() + 1));
+        let mut generator = WorkloadGenerator::new(people, mix(60, 25, 15), 1000);
+
+        let mut counts = WorkloadCounts::default();
+        for _ in 0..500 {
+            let operation = generator.next().unwrap();
+            counts.record(&operation);
+
+            assert_eq!(pulled.get() as u64, counts.inserts);
+            assert_eq!(generator.live.len() as u64, counts.live_docs());
+        }
+    }
+
+    #[test]
+    fn insert_only_mixes_keep_no_people() {
+        let people = PersonGenerator::new(1).generate(100);
+        let mut generator = WorkloadGenerator::new(people.clone().into_iter(), WorkloadMix::default(), 1000);
+
+        let inserted = generator
+            .by_ref()
+            .map(|operation| match operation {
+                Operation::Insert(person) => person.id,
+                other => panic!("Unexpected {:?}", other),
+            })
+            .collect::<Vec<String>>();
+
+        // Bounded by the people rather than the steps
+        assert_eq!(inserted, people.iter().map(|person| person.id.clone()).collect::<Vec<String>>());
+        assert!(generator.live.is_empty());
+    }
+
+    #[test]
+    fn updates_and_deletes_target_live_people() {
+        let people = PersonGenerator::new(1).generate(300);
+        let mut live: Vec<String> = Vec::new();
+
+        for operation in WorkloadGenerator::new(people.into_iter(), mix(40, 30, 30), 2000) {
+            let id = operation.person().id.clone();
+
+            match operation {
+                Operation::Insert(_) => {
+                    assert!(!live.contains(&id));
+                    live.push(id);
+                },
+                Operation::Update(_) => assert!(live.contains(&id)),
+                Operation::Delete(_) => {
+                    let index = live.iter().position(|live_id| *live_id == id).unwrap();
+                    live.swap_remove(index);
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_yields_the_same_operations() {
+        let operations = |seed: u64| {
+            let mix = WorkloadMix{
+                seed,
+                ..mix(60, 25, 15)
+            };
+
+            WorkloadGenerator::new(PersonGenerator::new(1), mix, 600)
+                .map(|operation| format!("{:?}", operation))
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(operations(7), operations(7));
+        assert_ne!(operations(7), operations(8));
+    }
+}
@@ -1,3 +1,6 @@
 pub mod config;
+pub mod generator;
+pub mod harness;
 pub mod models;
+pub mod simulator;
 pub mod store;
@@ -3,6 +3,8 @@ use serde::{
     Deserialize,
 };
 
+use super::json_len;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Address {
@@ -12,3 +14,15 @@ pub struct Address {
     pub line_1: Option<String>,
     pub line_2: Option<String>,
 }
+
+impl Address {
+    pub fn json_len(&self) -> usize {
+        json_len::object(&[
+            ("country", json_len::optional_string(&self.country)),
+            ("zipCode", json_len::optional_string(&self.zip_code)),
+            ("city", json_len::optional_string(&self.city)),
+            ("line1", json_len::optional_string(&self.line_1)),
+            ("line2", json_len::optional_string(&self.line_2)),
+        ])
+    }
+}
@@ -0,0 +1,32 @@
+//! Length of the compact JSON of the models, computed from their fields without
+//! serializing them. Characters JSON escapes are counted as a single byte.
+
+const NULL_LEN: usize = "null".len();
+
+pub fn string(value: &str) -> usize {
+    value.len() + 2
+}
+
+pub fn optional_string(value: &Option<String>) -> usize {
+    match value {
+        Some(value) => string(value),
+        None => NULL_LEN,
+    }
+}
+
+pub fn optional<T>(value: &Option<T>, len: impl Fn(&T) -> usize) -> usize {
+    match value {
+        Some(value) => len(value),
+        None => NULL_LEN,
+    }
+}
+
+/// Object of the given keys along with the length of their values
+pub fn object(fields: &[(&str, usize)]) -> usize {
+    let fields_len = fields
+        .iter()
+        .map(|(key, value_len)| string(key) + 1 + value_len)
+        .sum::<usize>();
+
+    2 + fields_len + fields.len().saturating_sub(1)
+}
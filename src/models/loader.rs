@@ -0,0 +1,710 @@
+use std::{
+    error,
+    fmt,
+    fs::File,
+    io::{
+        self,
+        BufRead,
+        BufReader,
+    },
+    path::Path,
+};
+
+use flate2::bufread::MultiGzDecoder;
+
+use super::person::Person;
+
+/// First bytes of a gzip member
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// PeopleFormat
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeopleFormat {
+    /// A single JSON array of people, as written by `generate-people`
+    JsonArray,
+    /// One person per line
+    Ndjson,
+}
+
+// LoadError
+
+#[derive(Debug)]
+pub enum LoadErrorKind {
+    Io(io::Error),
+    /// The input isn't a JSON array or NDJSON anymore, nothing after it can be read
+    Syntax(String),
+    /// The record isn't a valid person, the following ones are still read
+    Record(serde_json::Error),
+}
+
+/// Location of a failure in the input, decompressed if gzipped
+#[derive(Debug)]
+pub struct LoadError {
+    /// Index of the record in the input, starting at 0
+    pub record: u64,
+    /// Line, starting at 1
+    pub line: u64,
+    /// Column in bytes, starting at 1
+    pub column: u64,
+    /// Byte offset of the record
+    pub offset: u64,
+    /// Path of the offending field, e.g. `address.zipCode`, for invalid records
+    pub field: Option<String>,
+    pub kind: LoadErrorKind,
+}
+
+impl LoadError {
+    /// Whether the loader stopped, as opposed to skipping an invalid record
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self.kind, LoadErrorKind::Record(_))
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "record {} at line {}, column {} (byte {})", self.record, self.line, self.column, self.offset)?;
+
+        if let Some(field) = &self.field {
+            write!(f, ", field {}", field)?;
+        }
+
+        match &self.kind {
+            LoadErrorKind::Io(err) => write!(f, ": {}", err),
+            LoadErrorKind::Syntax(message) => write!(f, ": {}", message),
+            LoadErrorKind::Record(err) => {
+                // The location serde_json appends is relative to the record
+                let message = err.to_string();
+                let suffix = format!(" at line {} column {}", err.line(), err.column());
+
+                write!(f, ": {}", message.strip_suffix(&suffix).unwrap_or(&message))
+            },
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            LoadErrorKind::Io(err) => Some(err),
+            LoadErrorKind::Syntax(_) => None,
+            LoadErrorKind::Record(err) => Some(err),
+        }
+    }
+}
+
+// Position
+
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    line: u64,
+    column: u64,
+    offset: u64,
+}
+
+impl Position {
+    fn advance(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            if *byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
+        self.offset += bytes.len() as u64;
+    }
+}
+
+/// State of the scan of a JSON value of the array
+#[derive(Debug, Clone, Copy, Default)]
+struct ValueScan {
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    started: bool,
+}
+
+impl ValueScan {
+    /// Length of `bytes` belonging to the value, `None` when the value goes on past them
+    fn scan(&mut self, bytes: &[u8]) -> Option<usize> {
+        for (index, byte) in bytes.iter().enumerate() {
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if *byte == b'\\' {
+                    self.escaped = true;
+                } else if *byte == b'"' {
+                    self.in_string = false;
+
+                    if self.depth == 0 {
+                        return Some(index + 1);
+                    }
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' if self.depth > 0 => {
+                    self.depth -= 1;
+
+                    if self.depth == 0 {
+                        return Some(index + 1);
+                    }
+                },
+                // End of a number, literal or of a stray closing bracket at the top level
+                b',' | b']' | b'}' | b' ' | b'\t' | b'\r' | b'\n' if self.depth == 0 && self.started => {
+                    return Some(index);
+                },
+                _ => {},
+            }
+
+            self.started = true;
+        }
+
+        None
+    }
+}
+
+// PeopleLoader
+
+/// Lazily reads people from a JSON array or from NDJSON, one record at a time.
+///
+/// Invalid records are yielded as errors with their location and the loader moves on
+/// to the next one, I/O errors and a broken array structure end the iteration.
+pub struct PeopleLoader<R: BufRead> {
+    reader: R,
+    format: Option<PeopleFormat>,
+    position: Position,
+    record: u64,
+    done: bool,
+    buffer: Vec<u8>,
+}
+
+impl PeopleLoader<Box<dyn BufRead + Send>> {
+    /// Opens a file of people, gunzipping it when it starts with the gzip magic bytes
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let reader: Box<dyn BufRead + Send> = if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+        } else {
+            Box::new(reader)
+        };
+
+        Ok(PeopleLoader::new(reader))
+    }
+}
+
+impl<R: BufRead> PeopleLoader<R> {
+    pub fn new(reader: R) -> Self {
+        PeopleLoader{
+            reader,
+            format: None,
+            position: Position{
+                line: 1,
+                column: 1,
+                offset: 0,
+            },
+            record: 0,
+            done: false,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Format of the input, known once the first record was read
+    pub fn format(&self) -> Option<PeopleFormat> {
+        self.format
+    }
+
+    fn error(&mut self, position: Position, field: Option<String>, kind: LoadErrorKind) -> LoadError {
+        if !matches!(kind, LoadErrorKind::Record(_)) {
+            self.done = true;
+        }
+
+        LoadError{
+            record: self.record,
+            line: position.line,
+            column: position.column,
+            offset: position.offset,
+            field,
+            kind,
+        }
+    }
+
+    fn peek_byte(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn consume_byte(&mut self, byte: u8) {
+        self.reader.consume(1);
+        self.position.advance(&[byte]);
+    }
+
+    /// Skips whitespace, returning the next byte without consuming it
+    fn skip_whitespace(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            match self.peek_byte()? {
+                Some(byte) if byte.is_ascii_whitespace() => self.consume_byte(byte),
+                next => return Ok(next),
+            }
+        }
+    }
+
+    fn detect_format(&mut self) -> Result<Option<PeopleFormat>, LoadError> {
+        match self.skip_whitespace() {
+            Ok(Some(b'[')) => {
+                self.consume_byte(b'[');
+                Ok(Some(PeopleFormat::JsonArray))
+            },
+            Ok(Some(_)) => Ok(Some(PeopleFormat::Ndjson)),
+            Ok(None) => Ok(None),
+            Err(err) => Err(self.error(self.position, None, LoadErrorKind::Io(err))),
+        }
+    }
+
+    /// Deserializes the record in the buffer, which started at `start`
+    fn parse_record(&mut self, start: Position) -> Result<Person, LoadError> {
+        let mut deserializer = serde_json::Deserializer::from_slice(&self.buffer);
+
+        let result = match serde_path_to_error::deserialize::<_, Person>(&mut deserializer) {
+            Ok(person) => deserializer.end().map(|_| person).map_err(|err| (None, err)),
+            Err(err) => {
+                let field = err.path().iter().next().map(|_| err.path().to_string());
+
+                Err((field, err.into_inner()))
+            },
+        };
+
+        match result {
+            Ok(person) => {
+                self.record += 1;
+                Ok(person)
+            },
+            Err((field, err)) => {
+                // serde_json counts from the start of the record
+                let mut position = start;
+                if err.line() > 1 {
+                    position.line += err.line() as u64 - 1;
+                    position.column = err.column() as u64;
+                } else if err.column() > 0 {
+                    position.column += err.column() as u64 - 1;
+                }
+
+                let error = self.error(position, field, LoadErrorKind::Record(err));
+                self.record += 1;
+
+                Err(error)
+            },
+        }
+    }
+
+    fn next_ndjson(&mut self) -> Option<Result<Person, LoadError>> {
+        loop {
+            let start = self.position;
+            self.buffer.clear();
+
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => {},
+                Err(err) => return Some(Err(self.error(start, None, LoadErrorKind::Io(err)))),
+            }
+
+            self.position.advance(&self.buffer);
+
+            if self.buffer.iter().all(|byte| byte.is_ascii_whitespace()) {
+                continue;
+            }
+
+            while let Some(b'\n' | b'\r') = self.buffer.last() {
+                self.buffer.pop();
+            }
+
+            return Some(self.parse_record(start));
+        }
+    }
+
+    fn next_array_element(&mut self) -> Option<Result<Person, LoadError>> {
+        // Separator of the previous element, or the end of the array
+        let next = match self.skip_whitespace() {
+            Ok(next) => next,
+            Err(err) => return Some(Err(self.error(self.position, None, LoadErrorKind::Io(err)))),
+        };
+
+        match next {
+            Some(b']') => {
+                self.consume_byte(b']');
+                self.done = true;
+
+                return match self.skip_whitespace() {
+                    Ok(None) => None,
+                    Ok(Some(_)) => Some(Err(self.error(
+                        self.position,
+                        None,
+                        LoadErrorKind::Syntax("trailing characters after the array".to_string()),
+                    ))),
+                    Err(err) => Some(Err(self.error(self.position, None, LoadErrorKind::Io(err)))),
+                };
+            },
+            Some(b',') if self.record > 0 => {
+                self.consume_byte(b',');
+            },
+            Some(_) if self.record == 0 => {},
+            Some(byte) => {
+                return Some(Err(self.error(
+                    self.position,
+                    None,
+                    LoadErrorKind::Syntax(format!("expected `,` or `]`, found {:?}", byte as char)),
+                )));
+            },
+            None => {
+                return Some(Err(self.error(
+                    self.position,
+                    None,
+                    LoadErrorKind::Syntax("unterminated array".to_string()),
+                )));
+            },
+        }
+
+        let start = match self.skip_whitespace() {
+            Ok(Some(b']')) if self.record > 0 => {
+                return Some(Err(self.error(
+                    self.position,
+                    None,
+                    LoadErrorKind::Syntax("trailing comma in the array".to_string()),
+                )));
+            },
+            Ok(Some(_)) => self.position,
+            Ok(None) => {
+                return Some(Err(self.error(
+                    self.position,
+                    None,
+                    LoadErrorKind::Syntax("unterminated array".to_string()),
+                )));
+            },
+            Err(err) => return Some(Err(self.error(self.position, None, LoadErrorKind::Io(err)))),
+        };
+
+        // Copies the bytes of the value, whatever it is, so a record of the wrong
+        // shape is reported on its own rather than breaking the array
+        self.buffer.clear();
+        let mut scan = ValueScan::default();
+
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(err) => return Some(Err(self.error(self.position, None, LoadErrorKind::Io(err)))),
+            };
+
+            if available.is_empty() {
+                return Some(Err(self.error(
+                    start,
+                    None,
+                    LoadErrorKind::Syntax("unterminated record".to_string()),
+                )));
+            }
+
+            let (length, complete) = match scan.scan(available) {
+                Some(length) => (length, true),
+                None => (available.len(), false),
+            };
+
+            self.buffer.extend_from_slice(&available[..length]);
+            self.reader.consume(length);
+            self.position.advance(&self.buffer[self.buffer.len() - length..]);
+
+            if complete {
+                break;
+            }
+        }
+
+        Some(self.parse_record(start))
+    }
+}
+
+impl<R: BufRead> Iterator for PeopleLoader<R> {
+    type Item = Result<Person, LoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if self.format.is_none() {
+            match self.detect_format() {
+                Ok(Some(format)) => self.format = Some(format),
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                },
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        match self.format {
+            Some(PeopleFormat::JsonArray) => self.next_array_element(),
+            Some(PeopleFormat::Ndjson) => self.next_ndjson(),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs,
+        io::Write,
+        process,
+    };
+
+    use flate2::{
+        Compression,
+        write::GzEncoder,
+    };
+
+    use super::*;
+    use super::super::super::generator::PersonGenerator;
+
+    fn people(count: usize) -> Vec<Person> {
+        PersonGenerator::new(3).generate(count)
+    }
+
+    fn ids(people: &[Person]) -> Vec<String> {
+        people.iter().map(|person| person.id.clone()).collect()
+    }
+
+    fn load(input: &str) -> (Vec<Result<Person, LoadError>>, Option<PeopleFormat>) {
+        let mut loader = PeopleLoader::new(input.as_bytes());
+        let results = loader.by_ref().collect::<Vec<Result<Person, LoadError>>>();
+
+        (results, loader.format())
+    }
+
+    fn loaded_ids(results: &[Result<Person, LoadError>]) -> Vec<String> {
+        results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|person| person.id.clone())
+            .collect()
+    }
+
+    fn ndjson(people: &[Person]) -> String {
+        people
+            .iter()
+            .map(|person| serde_json::to_string(person).unwrap() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn reads_a_json_array() {
+        let people = people(5);
+
+        for input in [
+            serde_json::to_string(&people).unwrap(),
+            serde_json::to_string_pretty(&people).unwrap(),
+            format!("  \n{}\n\n", serde_json::to_string(&people).unwrap()),
+        ] {
+            let (results, format) = load(&input);
+
+            assert_eq!(format, Some(PeopleFormat::JsonArray));
+            assert!(results.iter().all(|result| result.is_ok()));
+            assert_eq!(loaded_ids(&results), ids(&people));
+        }
+
+        let (results, format) = load("[]");
+        assert_eq!(format, Some(PeopleFormat::JsonArray));
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn reads_ndjson() {
+        let people = people(5);
+        let lines = people
+            .iter()
+            .map(|person| serde_json::to_string(person).unwrap())
+            .collect::<Vec<String>>();
+
+        for input in [
+            ndjson(&people),
+            lines.join("\n"),
+            lines.join("\r\n"),
+            format!("\n\n{}\n  \n\n{}", lines[..2].join("\n"), lines[2..].join("\n\n")),
+        ] {
+            let (results, format) = load(&input);
+
+            assert_eq!(format, Some(PeopleFormat::Ndjson));
+            assert!(results.iter().all(|result| result.is_ok()));
+            assert_eq!(loaded_ids(&results), ids(&people));
+        }
+
+        let (results, format) = load(" \n\n");
+        assert_eq!(format, None);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn scans_strings_and_nested_values_of_the_array() {
+        let mut people = people(3);
+        people[0].last_name = "Brackets ] } [ { and a quote \\\"".to_string();
+        people[1].first_name = "Comma, \\ and \"]".to_string();
+
+        let (results, _) = load(&serde_json::to_string_pretty(&people).unwrap());
+
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(loaded_ids(&results), ids(&people));
+
+        let names = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|person| (person.first_name.clone(), person.last_name.clone()))
+            .collect::<Vec<(String, String)>>();
+        assert_eq!(names[0].1, people[0].last_name);
+        assert_eq!(names[1].0, people[1].first_name);
+    }
+
+    #[test]
+    fn value_scan_finds_the_end_of_the_value() {
+        let cases: [(&[u8], usize); 6] = [
+            (br#"{"a": {"b": [1, 2]}, "c": "}"}, {"#, 30),
+            (br#""a \" ] string", 1"#, 15),
+            (b"42, 43", 2),
+            (b"true]", 4),
+            (b"[[], [[]]] ,", 10),
+            (br#"{"escaped": "\\"}]"#, 17),
+        ];
+
+        for (bytes, length) in cases {
+            assert_eq!(ValueScan::default().scan(bytes), Some(length), "{}", String::from_utf8_lossy(bytes));
+        }
+
+        // A value split over reads carries its state over
+        let mut scan = ValueScan::default();
+        assert_eq!(scan.scan(br#"{"a": "}\"#), None);
+        assert_eq!(scan.scan(br#""" }, 1"#), Some(4));
+    }
+
+    #[test]
+    fn reports_the_location_of_an_invalid_ndjson_record() {
+        let people = people(2);
+        let first = serde_json::to_string(&people[0]).unwrap();
+        let invalid = r#"{"id": "b", "firstName": "B", "lastName": "B", "sex": "male", "email": "b@b", "address": {"zipCode": 5}}"#;
+        let input = format!("{}\n{}\n{}\n", first, invalid, serde_json::to_string(&people[1]).unwrap());
+
+        let (results, _) = load(&input);
+        assert_eq!(results.len(), 3);
+
+        let err = match &results[1] {
+            Err(err) => err,
+            Ok(person) => panic!("Loaded invalid person {:?}", person),
+        };
+
+        assert!(!err.is_fatal());
+        assert!(matches!(err.kind, LoadErrorKind::Record(_)));
+        assert_eq!(err.record, 1);
+        assert_eq!(err.line, 2);
+        assert_eq!(err.offset, first.len() as u64 + 1);
+        // serde_json points at the last byte of the offending value
+        assert_eq!(err.column, invalid.find('5').unwrap() as u64 + 1);
+        assert_eq!(err.field.as_deref(), Some("address.zipCode"));
+
+        // The following record is still read
+        assert_eq!(loaded_ids(&results), ids(&people));
+    }
+
+    #[test]
+    fn reports_the_location_of_an_invalid_array_record() {
+        let people = people(2);
+        let first = serde_json::to_string(&people[0]).unwrap();
+        let input = format!(
+            "[\n  {},\n  {{\"id\": 7}},\n  42,\n  {}\n]\n",
+            first,
+            serde_json::to_string(&people[1]).unwrap(),
+        );
+
+        let (results, format) = load(&input);
+        assert_eq!(format, Some(PeopleFormat::JsonArray));
+        assert_eq!(results.len(), 4);
+
+        let err = match &results[1] {
+            Err(err) => err,
+            Ok(person) => panic!("Loaded invalid person {:?}", person),
+        };
+
+        assert!(!err.is_fatal());
+        assert_eq!(err.record, 1);
+        assert_eq!(err.line, 3);
+        assert_eq!(err.offset, input.find("{\"id\": 7}").unwrap() as u64);
+        assert_eq!(err.column, "  {\"id\": 7".len() as u64);
+        assert_eq!(err.field.as_deref(), Some("id"));
+
+        // A value which isn't an object is a record of its own
+        let err = match &results[2] {
+            Err(err) => err,
+            Ok(person) => panic!("Loaded invalid person {:?}", person),
+        };
+
+        assert!(!err.is_fatal());
+        assert_eq!(err.record, 2);
+        assert_eq!(err.line, 4);
+        assert_eq!(err.field, None);
+
+        assert_eq!(loaded_ids(&results), ids(&people));
+    }
+
+    #[test]
+    fn stops_at_a_broken_array() {
+        let person = serde_json::to_string(&people(1)[0]).unwrap();
+
+        for (input, message) in [
+            (format!("[{},]", person), "trailing comma in the array"),
+            (format!("[{}", person), "unterminated array"),
+            (format!("[{}, ", person), "unterminated array"),
+            (format!("[{}] {}", person, person), "trailing characters after the array"),
+            (format!("[{} {}]", person, person), "expected `,` or `]`, found '{'"),
+            (format!("[{}, {}", person, &person[..person.len() - 1]), "unterminated record"),
+        ] {
+            let (results, _) = load(&input);
+
+            assert_eq!(results.len(), 2, "{}", message);
+            assert!(results[0].is_ok());
+
+            match &results[1] {
+                Err(LoadError{ kind: LoadErrorKind::Syntax(syntax), record, .. }) => {
+                    assert_eq!(syntax, message);
+                    assert_eq!(*record, 1);
+                },
+                other => panic!("Expected {:?}, got {:?}", message, other),
+            }
+            assert!(results[1].as_ref().is_err_and(|err| err.is_fatal()));
+        }
+    }
+
+    #[test]
+    fn opens_gzipped_and_plain_files() {
+        let people = people(20);
+        let directory = env::temp_dir();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(serde_json::to_string(&people).unwrap().as_bytes()).unwrap();
+
+        for (name, bytes, format) in [
+            ("gzip-array.json.gz", encoder.finish().unwrap(), PeopleFormat::JsonArray),
+            ("plain-ndjson.json", ndjson(&people).into_bytes(), PeopleFormat::Ndjson),
+        ] {
+            let path = directory.join(format!("people-loader-{}-{}", process::id(), name));
+            fs::write(&path, &bytes).unwrap();
+
+            let mut loader = PeopleLoader::open(&path).unwrap();
+            let results = loader.by_ref().collect::<Vec<Result<Person, LoadError>>>();
+            let _ = fs::remove_file(&path);
+
+            assert_eq!(loader.format(), Some(format), "{}", name);
+            assert!(results.iter().all(|result| result.is_ok()), "{}", name);
+            assert_eq!(loaded_ids(&results), ids(&people), "{}", name);
+        }
+    }
+}
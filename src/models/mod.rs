@@ -1,2 +1,4 @@
 pub mod address;
+mod json_len;
+pub mod loader;
 pub mod person;
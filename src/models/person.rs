@@ -4,6 +4,7 @@ use serde::{
 };
 
 use super::address::Address;
+use super::json_len;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -17,8 +18,65 @@ pub struct Person {
     pub settings: Option<PersonSettings>,
 }
 
+impl Person {
+    /// Bytes of the compact JSON of the person, without serializing it
+    pub fn json_len(&self) -> usize {
+        json_len::object(&[
+            ("id", json_len::string(&self.id)),
+            ("firstName", json_len::string(&self.first_name)),
+            ("lastName", json_len::string(&self.last_name)),
+            ("sex", json_len::string(&self.sex)),
+            ("email", json_len::string(&self.email)),
+            ("address", json_len::optional(&self.address, Address::json_len)),
+            ("settings", json_len::optional(&self.settings, PersonSettings::json_len)),
+        ])
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonSettings {
     pub locale: Option<String>,
 }
+
+impl PersonSettings {
+    pub fn json_len(&self) -> usize {
+        json_len::object(&[
+            ("locale", json_len::optional_string(&self.locale)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::generator::PersonGenerator;
+
+    #[test]
+    fn json_len_matches_the_serialized_person() {
+        let mut people = PersonGenerator::new(11).generate(200);
+
+        people[0].address = None;
+        people[1].settings = None;
+        people[2].settings = Some(PersonSettings{ locale: None });
+        if let Some(address) = people[3].address.as_mut() {
+            address.line_2 = Some("Flat 2".to_string());
+        }
+
+        for person in people.iter() {
+            let json = serde_json::to_vec(person).unwrap();
+            assert_eq!(person.json_len(), json.len(), "{}", String::from_utf8_lossy(&json));
+        }
+    }
+
+    #[test]
+    fn json_len_counts_escapes_once() {
+        let mut person = PersonGenerator::new(11).next_person();
+        let json_len = serde_json::to_vec(&person).unwrap().len();
+
+        person.last_name = format!("{}\"", person.last_name);
+
+        assert_eq!(person.json_len(), json_len + 1);
+        assert_eq!(serde_json::to_vec(&person).unwrap().len(), json_len + 2);
+    }
+}
@@ -0,0 +1,580 @@
+use std::collections::HashSet;
+
+use rand::{
+    Rng,
+    SeedableRng,
+    rngs::StdRng,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use tantivy::{
+    Index,
+    Opstamp,
+    index::{
+        SegmentId,
+        SegmentMeta,
+    },
+    merge_policy::MergePolicy,
+    schema::Schema,
+};
+
+// SimulatorOptions
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatorOptions {
+    pub seed: u64,
+    /// Fixed cost of every merge
+    pub merge_base_millis: u64,
+    pub merge_micros_per_doc: u64,
+    /// Upper bound of the random delay added to every merge
+    pub merge_jitter_millis: u64,
+    /// Models the tantivy fix ignoring single-segment candidates without deletes
+    pub skip_single_segment_merges: bool,
+}
+
+impl Default for SimulatorOptions {
+    fn default() -> Self {
+        SimulatorOptions{
+            seed: 0,
+            merge_base_millis: 5,
+            merge_micros_per_doc: 100,
+            merge_jitter_millis: 5,
+            skip_single_segment_merges: false,
+        }
+    }
+}
+
+// SimulatedSegment
+
+#[derive(Debug, Clone)]
+pub struct SimulatedSegment {
+    pub id: SegmentId,
+    pub max_doc: u32,
+    pub num_deleted_docs: u32,
+    pub committed: bool,
+}
+
+impl SimulatedSegment {
+    pub fn num_docs(&self) -> u32 {
+        self.max_doc - self.num_deleted_docs
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InFlightMerge {
+    pub segment_ids: Vec<SegmentId>,
+    /// Deleted docs of every merged segment when the merge started
+    deleted_at_start: Vec<u32>,
+    pub started_at_millis: u64,
+    pub completes_at_millis: u64,
+}
+
+// SimulationStats
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationStats {
+    pub commits: u64,
+    pub policy_calls: u64,
+    pub merges_started: u64,
+    pub merges_completed: u64,
+    /// Candidates tantivy would refuse to start (unknown, already merging or mixed segments)
+    pub merges_rejected: u64,
+    pub merges_skipped: u64,
+    pub docs_indexed: u64,
+    pub docs_deleted: u64,
+    /// Docs rewritten by merges
+    pub docs_merged: u64,
+}
+
+impl SimulationStats {
+    /// Docs written to disk per indexed doc
+    pub fn write_amplification(&self) -> f64 {
+        if self.docs_indexed == 0 {
+            return 0.0;
+        }
+
+        (self.docs_indexed + self.docs_merged) as f64 / self.docs_indexed as f64
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulationOutcome {
+    /// No merge is in flight anymore
+    Converged,
+    /// Merges kept being scheduled for all the allowed steps, e.g. runs D and H
+    StepLimitExceeded,
+}
+
+// Simulator
+
+/// Deterministic model of tantivy's segment updater driving a merge policy.
+///
+/// Segments are only metadata, merges take simulated time drawn from a seeded
+/// generator and segments of in-flight merges are hidden from the policy, the same
+/// way `SegmentUpdater::consider_merge_options` does.
+pub struct Simulator {
+    policy: Box<dyn MergePolicy>,
+    options: SimulatorOptions,
+    index: Index,
+    rng: StdRng,
+    clock_millis: u64,
+    opstamp: Opstamp,
+    next_segment_seq: u128,
+    pending_docs: u32,
+    segments: Vec<SimulatedSegment>,
+    merges: Vec<InFlightMerge>,
+    stats: SimulationStats,
+}
+
+impl Simulator {
+    pub fn new(policy: Box<dyn MergePolicy>, options: SimulatorOptions) -> Self {
+        let rng = StdRng::seed_from_u64(options.seed);
+
+        Simulator{
+            policy,
+            options,
+            index: Index::create_in_ram(Schema::builder().build()),
+            rng,
+            clock_millis: 0,
+            opstamp: 0,
+            next_segment_seq: 1,
+            pending_docs: 0,
+            segments: Vec::new(),
+            merges: Vec::new(),
+            stats: SimulationStats::default(),
+        }
+    }
+
+    pub fn now_millis(&self) -> u64 {
+        self.clock_millis
+    }
+
+    pub fn segments(&self) -> &[SimulatedSegment] {
+        &self.segments
+    }
+
+    pub fn in_flight_merges(&self) -> &[InFlightMerge] {
+        &self.merges
+    }
+
+    pub fn stats(&self) -> &SimulationStats {
+        &self.stats
+    }
+
+    /// Advances the clock without completing merges scheduled later
+    pub fn advance_clock(&mut self, millis: u64) {
+        let until_millis = self.clock_millis + millis;
+
+        while let Some(index) = self.next_merge_index() {
+            if self.merges[index].completes_at_millis > until_millis {
+                break;
+            }
+
+            self.clock_millis = self.clock_millis.max(self.merges[index].completes_at_millis);
+            self.complete_merge(index);
+        }
+
+        self.clock_millis = until_millis;
+    }
+
+    pub fn add_documents(&mut self, count: u32) {
+        self.pending_docs += count;
+        self.stats.docs_indexed += count as u64;
+        self.opstamp += count as u64;
+    }
+
+    /// Turns the pending docs into a new uncommitted segment, like a writer thread
+    /// reaching its memory budget
+    pub fn flush(&mut self) {
+        if self.pending_docs == 0 {
+            return;
+        }
+
+        let segment = SimulatedSegment{
+            id: self.next_segment_id(),
+            max_doc: self.pending_docs,
+            num_deleted_docs: 0,
+            committed: false,
+        };
+
+        self.pending_docs = 0;
+        self.segments.push(segment);
+
+        self.consider_merge_options();
+    }
+
+    pub fn commit(&mut self) {
+        self.flush();
+
+        for segment in self.segments.iter_mut() {
+            segment.committed = true;
+        }
+
+        self.stats.commits += 1;
+        self.opstamp += 1;
+
+        self.consider_merge_options();
+    }
+
+    /// Deletes random live docs across committed segments
+    pub fn delete_documents(&mut self, count: u32) {
+        for _ in 0..count {
+            let live_docs = self.segments
+                .iter()
+                .filter(|segment| segment.committed)
+                .map(|segment| segment.num_docs() as u64)
+                .sum::<u64>();
+
+            if live_docs == 0 {
+                break;
+            }
+
+            let mut doc = self.rng.gen_range(0..live_docs);
+
+            for segment in self.segments.iter_mut().filter(|segment| segment.committed) {
+                if doc < segment.num_docs() as u64 {
+                    segment.num_deleted_docs += 1;
+                    break;
+                }
+
+                doc -= segment.num_docs() as u64;
+            }
+
+            self.stats.docs_deleted += 1;
+        }
+
+        self.opstamp += 1;
+    }
+
+    /// Completes the next in-flight merge, returns `false` when there's none
+    pub fn step(&mut self) -> bool {
+        match self.next_merge_index() {
+            Some(index) => {
+                self.clock_millis = self.clock_millis.max(self.merges[index].completes_at_millis);
+                self.complete_merge(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Completes merges until none is in flight, giving up after `max_steps`
+    pub fn wait_merging_threads(&mut self, max_steps: usize) -> SimulationOutcome {
+        for _ in 0..max_steps {
+            if !self.step() {
+                return SimulationOutcome::Converged;
+            }
+        }
+
+        if self.merges.is_empty() {
+            SimulationOutcome::Converged
+        } else {
+            SimulationOutcome::StepLimitExceeded
+        }
+    }
+
+    fn next_segment_id(&mut self) -> SegmentId {
+        let uuid_string = format!("{:032x}", self.next_segment_seq);
+        self.next_segment_seq += 1;
+
+        match SegmentId::from_uuid_string(&uuid_string) {
+            Ok(segment_id) => segment_id,
+            Err(err) => {
+                panic!("Failed to build segment id {}: {}", uuid_string, err);
+            },
+        }
+    }
+
+    fn next_merge_index(&self) -> Option<usize> {
+        self.merges
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, merge)| merge.completes_at_millis)
+            .map(|(index, _)| index)
+    }
+
+    fn segment_meta(&self, segment: &SimulatedSegment) -> SegmentMeta {
+        let segment_meta = self.index.new_segment_meta(segment.id, segment.max_doc);
+
+        match segment.num_deleted_docs {
+            0 => segment_meta,
+            num_deleted_docs => segment_meta.with_delete_meta(num_deleted_docs, self.opstamp),
+        }
+    }
+
+    fn is_merging(&self, segment_id: &SegmentId) -> bool {
+        self.merges
+            .iter()
+            .any(|merge| merge.segment_ids.contains(segment_id))
+    }
+
+    /// Asks the policy for uncommitted and committed segments separately, skipping
+    /// the ones already being merged
+    fn consider_merge_options(&mut self) {
+        for committed in [false, true] {
+            let segment_metas = self.segments
+                .iter()
+                .filter(|segment| segment.committed == committed && !self.is_merging(&segment.id))
+                .map(|segment| self.segment_meta(segment))
+                .collect::<Vec<SegmentMeta>>();
+
+            self.stats.policy_calls += 1;
+
+            for merge_candidate in self.policy.compute_merge_candidates(&segment_metas) {
+                self.start_merge(merge_candidate.0, committed);
+            }
+        }
+    }
+
+    fn start_merge(&mut self, segment_ids: Vec<SegmentId>, committed: bool) {
+        if segment_ids.is_empty() {
+            return;
+        }
+
+        let unique_ids = segment_ids.iter().collect::<HashSet<&SegmentId>>();
+        let mergeable = unique_ids.len() == segment_ids.len() && segment_ids
+            .iter()
+            .all(|segment_id| {
+                !self.is_merging(segment_id) && self.segments
+                    .iter()
+                    .any(|segment| segment.id == *segment_id && segment.committed == committed)
+            });
+
+        if !mergeable {
+            self.stats.merges_rejected += 1;
+            return;
+        }
+
+        let segments = segment_ids
+            .iter()
+            .filter_map(|segment_id| self.segments.iter().find(|segment| segment.id == *segment_id))
+            .collect::<Vec<&SimulatedSegment>>();
+
+        if self.options.skip_single_segment_merges && segments.len() == 1 && segments[0].num_deleted_docs == 0 {
+            self.stats.merges_skipped += 1;
+            return;
+        }
+
+        let docs = segments
+            .iter()
+            .map(|segment| segment.num_docs() as u64)
+            .sum::<u64>();
+        let deleted_at_start = segments
+            .iter()
+            .map(|segment| segment.num_deleted_docs)
+            .collect::<Vec<u32>>();
+
+        let jitter_millis = match self.options.merge_jitter_millis {
+            0 => 0,
+            merge_jitter_millis => self.rng.gen_range(0..=merge_jitter_millis),
+        };
+        let duration_millis = self.options.merge_base_millis
+            + docs * self.options.merge_micros_per_doc / 1000
+            + jitter_millis;
+
+        self.merges.push(InFlightMerge{
+            segment_ids,
+            deleted_at_start,
+            started_at_millis: self.clock_millis,
+            completes_at_millis: self.clock_millis + duration_millis,
+        });
+
+        self.stats.merges_started += 1;
+    }
+
+    fn complete_merge(&mut self, index: usize) {
+        let merge = self.merges.remove(index);
+
+        let mut max_doc: u32 = 0;
+        let mut num_deleted_docs: u32 = 0;
+        let mut committed = false;
+
+        for (segment_id, deleted_at_start) in merge.segment_ids.iter().zip(merge.deleted_at_start.iter()) {
+            if let Some(segment) = self.segments.iter().find(|segment| segment.id == *segment_id) {
+                max_doc += segment.max_doc - deleted_at_start;
+                // Deletes applied while merging are carried over to the merged segment
+                num_deleted_docs += segment.num_deleted_docs - deleted_at_start;
+                committed = segment.committed;
+            }
+        }
+
+        self.segments.retain(|segment| !merge.segment_ids.contains(&segment.id));
+
+        // Like tantivy, a merge of fully deleted segments doesn't produce any segment
+        if max_doc > 0 {
+            let segment = SimulatedSegment{
+                id: self.next_segment_id(),
+                max_doc,
+                num_deleted_docs,
+                committed,
+            };
+
+            self.segments.push(segment);
+        }
+
+        self.stats.merges_completed += 1;
+        self.stats.docs_merged += max_doc as u64;
+
+        self.consider_merge_options();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::merge_policy::MergeCandidate;
+
+    use super::*;
+    use super::super::store::utils::{
+        MergeWheneverPossiblePolicy,
+        TargetDocsPerSegmentPolicy,
+    };
+
+    /// `TargetDocsPerSegmentPolicy` as it was when runs D and H got stuck, proposing
+    /// the lone segment below the target as a merge of its own
+    #[derive(Debug)]
+    struct SingleSegmentPolicy {
+        target_docs_per_segment: u32,
+    }
+
+    impl MergePolicy for SingleSegmentPolicy {
+        fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+            let segment_ids = segment_metas
+                .iter()
+                .filter(|segment| segment.num_docs() < self.target_docs_per_segment)
+                .map(|segment| segment.id())
+                .collect::<Vec<SegmentId>>();
+
+            match segment_ids.is_empty() {
+                true => vec![],
+                false => vec![MergeCandidate(segment_ids)],
+            }
+        }
+    }
+
+    fn options(seed: u64, skip_single_segment_merges: bool) -> SimulatorOptions {
+        SimulatorOptions{
+            seed,
+            skip_single_segment_merges,
+            ..SimulatorOptions::default()
+        }
+    }
+
+    /// Run D, every doc committed at once
+    fn run_d(simulator: &mut Simulator) {
+        simulator.add_documents(1000);
+        simulator.commit();
+    }
+
+    /// Run H, a commit after every doc
+    fn run_h(simulator: &mut Simulator) {
+        for _ in 0..100 {
+            simulator.add_documents(1);
+            simulator.commit();
+        }
+    }
+
+    #[test]
+    fn single_segment_candidates_loop_forever() {
+        for run in [run_d, run_h] {
+            let policy = Box::new(SingleSegmentPolicy{ target_docs_per_segment: 10000 });
+            let mut simulator = Simulator::new(policy, options(7, false));
+
+            run(&mut simulator);
+
+            assert_eq!(simulator.wait_merging_threads(10000), SimulationOutcome::StepLimitExceeded);
+            // Every lone segment keeps being rewritten without ever being merged with another
+            assert!(simulator.stats().merges_completed >= 10000);
+            assert_eq!(simulator.in_flight_merges().len(), simulator.segments().len());
+            assert!(simulator.in_flight_merges().iter().all(|merge| merge.segment_ids.len() == 1));
+            assert_eq!(simulator.stats().merges_skipped, 0);
+        }
+    }
+
+    #[test]
+    fn skipping_single_segment_merges_breaks_the_loop() {
+        for run in [run_d, run_h] {
+            let policy = Box::new(SingleSegmentPolicy{ target_docs_per_segment: 10000 });
+            let mut simulator = Simulator::new(policy, options(7, true));
+
+            run(&mut simulator);
+
+            assert_eq!(simulator.wait_merging_threads(10000), SimulationOutcome::Converged);
+            assert!(simulator.in_flight_merges().is_empty());
+            assert_eq!(simulator.segments().len(), 1);
+            assert!(simulator.stats().merges_skipped > 0);
+        }
+    }
+
+    #[test]
+    fn policies_without_single_segment_candidates_converge() {
+        let policies: Vec<fn() -> Box<dyn MergePolicy>> = vec![
+            || MergeWheneverPossiblePolicy::new().as_box(),
+            || TargetDocsPerSegmentPolicy::new(10000).as_box(),
+        ];
+
+        for policy in policies {
+            for run in [run_d, run_h] {
+                let mut simulator = Simulator::new(policy(), options(7, false));
+
+                run(&mut simulator);
+
+                assert_eq!(simulator.wait_merging_threads(10000), SimulationOutcome::Converged);
+                assert_eq!(simulator.segments().len(), 1);
+                assert_eq!(simulator.stats().merges_skipped, 0);
+                assert_eq!(simulator.stats().merges_rejected, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_simulation() {
+        let simulate = |seed: u64| {
+            let mut simulator = Simulator::new(MergeWheneverPossiblePolicy::new().as_box(), options(seed, false));
+
+            for _ in 0..50 {
+                simulator.add_documents(20);
+                simulator.commit();
+                simulator.delete_documents(5);
+                simulator.advance_clock(3);
+            }
+            simulator.wait_merging_threads(10000);
+
+            (
+                simulator.now_millis(),
+                simulator.stats().merges_completed,
+                simulator.stats().docs_merged,
+                simulator.segments().iter().map(|segment| segment.num_docs()).collect::<Vec<u32>>(),
+            )
+        };
+
+        assert_eq!(simulate(42), simulate(42));
+        assert_eq!(simulate(42).3.iter().sum::<u32>(), 50 * 20 - 50 * 5);
+    }
+
+    #[test]
+    fn merging_segments_are_hidden_from_the_policy() {
+        let mut simulator = Simulator::new(MergeWheneverPossiblePolicy::new().as_box(), options(7, false));
+
+        simulator.add_documents(10);
+        simulator.commit();
+        simulator.add_documents(10);
+        simulator.commit();
+
+        assert_eq!(simulator.in_flight_merges().len(), 1);
+
+        // The third segment alone isn't worth merging while the first two are merged
+        simulator.add_documents(10);
+        simulator.commit();
+
+        assert_eq!(simulator.in_flight_merges().len(), 1);
+        assert_eq!(simulator.stats().merges_rejected, 0);
+        assert_eq!(simulator.wait_merging_threads(100), SimulationOutcome::Converged);
+        assert_eq!(simulator.segments().len(), 1);
+        assert_eq!(simulator.segments()[0].num_docs(), 30);
+    }
+}
@@ -10,72 +10,103 @@ use tantivy::{
 };
 
 use super::super::{
-    config::INDEX_PEOPLE_PATH,
+    config::Config,
     models::person::Person,
     store::utils,
 };
 
-impl Person {
-    pub async fn to_doc(self, schema: schema::Schema) -> Result<TantivyDocument, TantivyError> {
-        let id_field = schema.get_field("id")?;
-        let first_name_field = schema.get_field("first_name")?;
-        let first_name_ngram_field = schema.get_field("first_name_ngram")?;
-        let last_name_field = schema.get_field("last_name")?;
-        let last_name_ngram_field = schema.get_field("last_name_ngram")?;
-        let email_field = schema.get_field("email")?;
-        let email_ngram_field = schema.get_field("email_ngram")?;
-        let sex_field = schema.get_field("sex")?;
-        let address_country_field = schema.get_field("address_country")?;
-        let address_zip_code_field = schema.get_field("address_zip_code")?;
-        let address_city_field = schema.get_field("address_city")?;
-        let address_city_ngram_field = schema.get_field("address_city_ngram")?;
-        let address_line_1_field = schema.get_field("address_line_1")?;
-        let address_line_1_ngram_field = schema.get_field("address_line_1_ngram")?;
-        let address_line_2_field = schema.get_field("address_line_2")?;
-        let address_line_2_ngram_field = schema.get_field("address_line_2_ngram")?;
+// PersonFields
+
+/// Fields of the people schema, resolved once rather than for every document
+#[derive(Debug, Clone, Copy)]
+pub struct PersonFields {
+    pub id: schema::Field,
+    pub first_name: schema::Field,
+    pub first_name_ngram: schema::Field,
+    pub last_name: schema::Field,
+    pub last_name_ngram: schema::Field,
+    pub email: schema::Field,
+    pub email_ngram: schema::Field,
+    pub sex: schema::Field,
+    pub address_country: schema::Field,
+    pub address_zip_code: schema::Field,
+    pub address_city: schema::Field,
+    pub address_city_ngram: schema::Field,
+    pub address_line_1: schema::Field,
+    pub address_line_1_ngram: schema::Field,
+    pub address_line_2: schema::Field,
+    pub address_line_2_ngram: schema::Field,
+}
+
+impl PersonFields {
+    pub fn new(schema: &schema::Schema) -> Result<Self, TantivyError> {
+        Ok(PersonFields{
+            id: schema.get_field("id")?,
+            first_name: schema.get_field("first_name")?,
+            first_name_ngram: schema.get_field("first_name_ngram")?,
+            last_name: schema.get_field("last_name")?,
+            last_name_ngram: schema.get_field("last_name_ngram")?,
+            email: schema.get_field("email")?,
+            email_ngram: schema.get_field("email_ngram")?,
+            sex: schema.get_field("sex")?,
+            address_country: schema.get_field("address_country")?,
+            address_zip_code: schema.get_field("address_zip_code")?,
+            address_city: schema.get_field("address_city")?,
+            address_city_ngram: schema.get_field("address_city_ngram")?,
+            address_line_1: schema.get_field("address_line_1")?,
+            address_line_1_ngram: schema.get_field("address_line_1_ngram")?,
+            address_line_2: schema.get_field("address_line_2")?,
+            address_line_2_ngram: schema.get_field("address_line_2_ngram")?,
+        })
+    }
+}
 
+impl Person {
+    pub fn to_doc(&self, fields: &PersonFields) -> TantivyDocument {
         let mut document = doc!(
-            id_field => self.id,
-            first_name_field => self.first_name.clone(),
-            first_name_ngram_field => self.first_name,
-            last_name_field => self.last_name.clone(),
-            last_name_ngram_field => self.last_name,
-            email_field => self.email.clone(),
-            email_ngram_field => self.email,
+            fields.id => self.id.clone(),
+            fields.first_name => self.first_name.clone(),
+            fields.first_name_ngram => self.first_name.clone(),
+            fields.last_name => self.last_name.clone(),
+            fields.last_name_ngram => self.last_name.clone(),
+            fields.email => self.email.clone(),
+            fields.email_ngram => self.email.clone(),
         );
 
         let mut sex_facet = "/sex/".to_owned();
         sex_facet.push_str(self.sex.as_str());
-        document.add_facet(sex_field, sex_facet.as_str());
-
-        match self.address {
-            Some(address) => {
-                match address.country {
-                    Some(value) => {
-                        let mut country_facet = "/country/".to_owned();
-                        country_facet.push_str(value.as_str());
-                        document.add_facet(address_country_field, country_facet.as_str());
-                    },
-                    None => {},
-                }
-
-                document = utils::index_optional_text(document, address_zip_code_field, address.zip_code);
-                document = utils::index_optional_text(document, address_city_field, address.city.clone());
-                document = utils::index_optional_text(document, address_city_ngram_field, address.city);
-                document = utils::index_optional_text(document, address_line_1_field, address.line_1.clone());
-                document = utils::index_optional_text(document, address_line_1_ngram_field, address.line_1);
-                document = utils::index_optional_text(document, address_line_2_field, address.line_2.clone());
-                document = utils::index_optional_text(document, address_line_2_ngram_field, address.line_2);
-            },
-            None => {},
+        document.add_facet(fields.sex, sex_facet.as_str());
+
+        if let Some(address) = &self.address {
+            if let Some(value) = &address.country {
+                let mut country_facet = "/country/".to_owned();
+                country_facet.push_str(value.as_str());
+                document.add_facet(fields.address_country, country_facet.as_str());
+            }
+
+            document = utils::index_optional_text(document, fields.address_zip_code, address.zip_code.clone());
+            document = utils::index_optional_text(document, fields.address_city, address.city.clone());
+            document = utils::index_optional_text(document, fields.address_city_ngram, address.city.clone());
+            document = utils::index_optional_text(document, fields.address_line_1, address.line_1.clone());
+            document = utils::index_optional_text(document, fields.address_line_1_ngram, address.line_1.clone());
+            document = utils::index_optional_text(document, fields.address_line_2, address.line_2.clone());
+            document = utils::index_optional_text(document, fields.address_line_2_ngram, address.line_2.clone());
         }
 
-        Ok(document)
+        document
     }
 }
 
-pub async fn open_index(schema: schema::Schema) -> Result<index::Index, TantivyError> {
-    let directory = directory::MmapDirectory::open(INDEX_PEOPLE_PATH.clone())?;
+pub async fn open_index(config: &Config, schema: schema::Schema) -> Result<index::Index, TantivyError> {
+    let directory = directory::MmapDirectory::open(&config.index_people_path)?;
+    open_index_in(directory, schema).await
+}
+
+/// Opens or creates the people index in any directory, e.g. a `RamDirectory`
+pub async fn open_index_in<T: Into<Box<dyn directory::Directory>>>(
+    directory: T,
+    schema: schema::Schema,
+) -> Result<index::Index, TantivyError> {
     let index = index::Index::open_or_create(directory, schema)?;
 
     let ngram_2_4_tokenizer = tokenizer::NgramTokenizer::new(2, 4, false)?;
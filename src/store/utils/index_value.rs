@@ -24,22 +24,16 @@ pub fn index_optional_facet(document: TantivyDocument, field: schema::Field, val
 }
 
 pub fn index_optional_text(mut document: TantivyDocument, field: schema::Field, value: Option<String>) -> TantivyDocument {
-    match value {
-        Some(value) => {
-            document.add_text(field, value);
-        },
-        None => {},
+    if let Some(value) = value {
+        document.add_text(field, value);
     }
 
     document
 }
 
 pub fn index_optional_i64(mut document: TantivyDocument, field: schema::Field, value: Option<i64>) -> TantivyDocument {
-    match value {
-        Some(value) => {
-            document.add_i64(field, value);
-        },
-        None => {},
+    if let Some(value) = value {
+        document.add_i64(field, value);
     }
 
     document
@@ -1,4 +1,17 @@
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    sync::Mutex,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
 use tantivy::{
+    HasLen,
+    directory::Directory,
     index::{
         SegmentId,
         SegmentMeta,
@@ -11,75 +24,457 @@ use tantivy::{
 
 // TargetDocsPerSegmentPolicy
 
+pub const DEFAULT_TARGET_DOCS_PER_SEGMENT: u32 = 10000;
+
+/// How segments are assigned to the merge groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackingStrategy {
+    /// First group with enough room left
+    #[default]
+    FirstFit,
+    /// Group with the least room left that still fits the segment
+    BestFit,
+}
+
+/// Packs segments below `target_docs_per_segment` into as many groups as needed,
+/// each group holding at most `target_docs_per_segment` docs.
+///
+/// Segments are packed largest first, groups ending up with a single segment are
+/// dropped as merging them would only rewrite the very same docs.
 #[derive(Debug, Clone)]
 pub struct TargetDocsPerSegmentPolicy {
-    run_id: String,
     target_docs_per_segment: u32,
+    packing: PackingStrategy,
 }
 
 impl TargetDocsPerSegmentPolicy {
-    pub fn new(run_id: String, target_docs_per_segment: u32) -> Self {
+    pub fn new(target_docs_per_segment: u32) -> Self {
         TargetDocsPerSegmentPolicy{
-            run_id,
             target_docs_per_segment,
+            packing: PackingStrategy::default(),
         }
     }
 
+    pub fn with_packing(mut self, packing: PackingStrategy) -> Self {
+        self.packing = packing;
+        self
+    }
+
     pub fn as_box(self) -> Box<Self> {
-        return Box::new(self);
+        Box::new(self)
     }
 }
 
 impl MergePolicy for TargetDocsPerSegmentPolicy {
     fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
-        println!("{{\"run\":\"{}\",\"count\":\"{}\"}}", self.run_id, segment_metas.len());
+        let mut segments = segment_metas
+            .iter()
+            .filter(|segment| segment.num_docs() < self.target_docs_per_segment)
+            .collect::<Vec<&SegmentMeta>>();
 
-        let mut merge_candidates: Vec<(u32, Vec<SegmentId>)> = Vec::new();
+        // Stable, so equally sized segments keep their original order
+        segments.sort_by_key(|segment| std::cmp::Reverse(segment.num_docs()));
 
-        'merge_segment_loop: for segment in segment_metas {
-            let segment_id = segment.id();
+        let mut groups: Vec<(u32, Vec<SegmentId>)> = Vec::new();
+
+        for segment in segments {
             let num_docs = segment.num_docs();
+            let fits = |group: &&mut (u32, Vec<SegmentId>)| group.0 + num_docs <= self.target_docs_per_segment;
 
-            for group in merge_candidates.iter_mut() {
-                if group.0 + num_docs < self.target_docs_per_segment {
-                    group.1.push(segment_id);
-                    continue 'merge_segment_loop;
-                }
-            }
+            let group = match self.packing {
+                PackingStrategy::FirstFit => groups.iter_mut().find(fits),
+                PackingStrategy::BestFit => groups
+                    .iter_mut()
+                    .filter(fits)
+                    .max_by_key(|group| group.0),
+            };
 
-            if merge_candidates.len() < 1 {
-                merge_candidates.push((num_docs, vec![segment_id]));
+            match group {
+                Some(group) => {
+                    group.0 += num_docs;
+                    group.1.push(segment.id());
+                },
+                None => {
+                    groups.push((num_docs, vec![segment.id()]));
+                },
             }
         }
 
-        merge_candidates
+        groups
             .into_iter()
+            .filter(|group| group.1.len() > 1)
             .map(|group| MergeCandidate(group.1))
             .collect::<Vec<MergeCandidate>>()
     }
 }
 
-// MergeWheneverPossiblePolicy
+// TargetBytesPerSegmentPolicy
+
+/// Total size of the segment files, files which can't be opened (e.g. not yet
+/// flushed) count as empty
+pub fn segment_file_bytes(directory: &dyn Directory, segment_meta: &SegmentMeta) -> u64 {
+    segment_meta
+        .list_files()
+        .iter()
+        .filter_map(|path| directory.open_read(path).ok())
+        .map(|file_slice| file_slice.len() as u64)
+        .sum::<u64>()
+}
+
+pub const DEFAULT_MAX_SEGMENTS_PER_MERGE: usize = 10;
+
+/// Merges the smallest segments first until the estimated merged size would exceed
+/// `max_merged_segment_bytes` or the group holds `max_segments_per_merge` segments.
+///
+/// Sizes are the sum of the segment files in the index directory, scaled down by the
+/// ratio of deleted docs as those are dropped by the merge.
+#[derive(Debug)]
+pub struct TargetBytesPerSegmentPolicy {
+    directory: Box<dyn Directory>,
+    max_merged_segment_bytes: u64,
+    max_segments_per_merge: usize,
+    /// Segment files never change once written, deletes add a new file
+    segment_bytes: Mutex<HashMap<(SegmentId, u32), u64>>,
+}
+
+impl TargetBytesPerSegmentPolicy {
+    pub fn new(directory: Box<dyn Directory>, max_merged_segment_bytes: u64) -> Self {
+        TargetBytesPerSegmentPolicy{
+            directory,
+            max_merged_segment_bytes,
+            max_segments_per_merge: DEFAULT_MAX_SEGMENTS_PER_MERGE,
+            segment_bytes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_max_segments_per_merge(mut self, max_segments_per_merge: usize) -> Self {
+        self.max_segments_per_merge = max_segments_per_merge;
+        self
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+
+    /// Cached `segment_file_bytes` of the segment
+    pub fn segment_bytes(&self, segment_meta: &SegmentMeta) -> u64 {
+        let key = (segment_meta.id(), segment_meta.num_deleted_docs());
+
+        let mut segment_bytes = match self.segment_bytes.lock() {
+            Ok(segment_bytes) => segment_bytes,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if let Some(bytes) = segment_bytes.get(&key) {
+            return *bytes;
+        }
+
+        let bytes = segment_file_bytes(self.directory.as_ref(), segment_meta);
+
+        segment_bytes.insert(key, bytes);
+        bytes
+    }
+
+    /// Forgets the sizes of the segments not passed to the policy anymore, merged away
+    /// or carrying new deletes. Segments hidden from a single call, being merged or
+    /// committed while the call is about the uncommitted ones, are simply read again.
+    fn prune_segment_bytes(&self, segment_metas: &[SegmentMeta]) {
+        let keys = segment_metas
+            .iter()
+            .map(|segment_meta| (segment_meta.id(), segment_meta.num_deleted_docs()))
+            .collect::<HashSet<(SegmentId, u32)>>();
+
+        let mut segment_bytes = match self.segment_bytes.lock() {
+            Ok(segment_bytes) => segment_bytes,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        segment_bytes.retain(|key, _| keys.contains(key));
+    }
+
+    fn estimated_merged_bytes(&self, segment_meta: &SegmentMeta) -> u64 {
+        let bytes = self.segment_bytes(segment_meta);
+
+        match segment_meta.max_doc() {
+            0 => 0,
+            max_doc => bytes * segment_meta.num_docs() as u64 / max_doc as u64,
+        }
+    }
+}
+
+impl MergePolicy for TargetBytesPerSegmentPolicy {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        self.prune_segment_bytes(segment_metas);
+
+        let mut segments = segment_metas
+            .iter()
+            .map(|segment_meta| (self.estimated_merged_bytes(segment_meta), segment_meta.id()))
+            .filter(|(bytes, _)| *bytes < self.max_merged_segment_bytes)
+            .collect::<Vec<(u64, SegmentId)>>();
+
+        segments.sort_by_key(|(bytes, _)| *bytes);
+
+        let mut groups: Vec<Vec<SegmentId>> = Vec::new();
+        let mut group: Vec<SegmentId> = Vec::new();
+        let mut group_bytes: u64 = 0;
+
+        for (bytes, segment_id) in segments {
+            let full = group.len() >= self.max_segments_per_merge
+                || group_bytes + bytes > self.max_merged_segment_bytes;
+
+            if full {
+                groups.push(group);
+                group = Vec::new();
+                group_bytes = 0;
+            }
+
+            group.push(segment_id);
+            group_bytes += bytes;
+        }
 
+        groups.push(group);
+
+        groups
+            .into_iter()
+            .filter(|group| group.len() > 1)
+            .map(MergeCandidate)
+            .collect::<Vec<MergeCandidate>>()
+    }
+}
+
+// TieredMergePolicy
+
+pub const DEFAULT_MERGE_FACTOR: usize = 10;
+pub const DEFAULT_FLOOR_SEGMENT_DOCS: u32 = 1000;
+pub const DEFAULT_MAX_MERGE_AT_ONCE: usize = 10;
+pub const DEFAULT_MAX_MERGED_SEGMENT_DOCS: u32 = 5_000_000;
+
+/// Log-structured policy in the spirit of Lucene's `TieredMergePolicy`.
+///
+/// Segments are bucketed into tiers, each tier holding segments `merge_factor` times
+/// bigger than the previous one (segments below `floor_segment_docs` all land in the
+/// first tier). A tier gets merged once it holds `merge_factor` segments, so every
+/// doc gets rewritten about once per tier and write amplification grows only with
+/// the logarithm of the index size.
+///
+/// Below the floor, small flushes keep being merged into the biggest segment of the
+/// first tier, rewriting its docs up to `floor_segment_docs / (merge_factor - 1)`
+/// times, which bounds the cost of tiny commits regardless of their number.
 #[derive(Debug, Clone)]
-pub struct MergeWheneverPossiblePolicy {
-    run_id: String,
+pub struct TieredMergePolicy {
+    merge_factor: usize,
+    floor_segment_docs: u32,
+    max_merge_at_once: usize,
+    max_merged_segment_docs: u32,
+}
+
+impl Default for TieredMergePolicy {
+    fn default() -> Self {
+        TieredMergePolicy::new()
+    }
 }
 
+impl TieredMergePolicy {
+    pub fn new() -> Self {
+        TieredMergePolicy{
+            merge_factor: DEFAULT_MERGE_FACTOR,
+            floor_segment_docs: DEFAULT_FLOOR_SEGMENT_DOCS,
+            max_merge_at_once: DEFAULT_MAX_MERGE_AT_ONCE,
+            max_merged_segment_docs: DEFAULT_MAX_MERGED_SEGMENT_DOCS,
+        }
+    }
+
+    pub fn with_merge_factor(mut self, merge_factor: usize) -> Self {
+        self.merge_factor = merge_factor.max(2);
+        self
+    }
+
+    pub fn with_floor_segment_docs(mut self, floor_segment_docs: u32) -> Self {
+        self.floor_segment_docs = floor_segment_docs.max(1);
+        self
+    }
+
+    /// Caps the segments of a single merge, a tier holding more gets merged in several
+    /// groups. Groups cut at the cap are merged even when below `merge_factor`.
+    pub fn with_max_merge_at_once(mut self, max_merge_at_once: usize) -> Self {
+        self.max_merge_at_once = max_merge_at_once.max(2);
+        self
+    }
+
+    pub fn with_max_merged_segment_docs(mut self, max_merged_segment_docs: u32) -> Self {
+        self.max_merged_segment_docs = max_merged_segment_docs;
+        self
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+
+    fn tier(&self, num_docs: u32) -> u32 {
+        let mut tier = 0;
+        let mut tier_max_docs = self.floor_segment_docs as u64;
+
+        while (num_docs as u64) > tier_max_docs {
+            tier += 1;
+            tier_max_docs *= self.merge_factor as u64;
+        }
+
+        tier
+    }
+}
+
+impl MergePolicy for TieredMergePolicy {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let mut tiers: HashMap<u32, Vec<&SegmentMeta>> = HashMap::new();
+
+        for segment_meta in segment_metas {
+            // Like Lucene, segments above half the maximum merged size are left alone
+            if segment_meta.num_docs() >= self.max_merged_segment_docs / 2 {
+                continue;
+            }
+
+            tiers
+                .entry(self.tier(segment_meta.num_docs()))
+                .or_default()
+                .push(segment_meta);
+        }
+
+        let mut tiers = tiers.into_iter().collect::<Vec<(u32, Vec<&SegmentMeta>)>>();
+        tiers.sort_by_key(|(tier, _)| *tier);
+
+        let mut merge_candidates: Vec<MergeCandidate> = Vec::new();
+
+        for (_, mut segments) in tiers {
+            if segments.len() < self.merge_factor {
+                continue;
+            }
+
+            segments.sort_by_key(|segment_meta| segment_meta.num_docs());
+
+            // The tier is due, so full groups get merged even if `max_merge_at_once`
+            // is below `merge_factor`
+            let min_group_len = self.merge_factor.min(self.max_merge_at_once);
+
+            let mut group: Vec<SegmentId> = Vec::new();
+            let mut group_docs: u32 = 0;
+
+            for segment_meta in segments {
+                let full = group.len() >= self.max_merge_at_once
+                    || group_docs.saturating_add(segment_meta.num_docs()) > self.max_merged_segment_docs;
+
+                if full {
+                    if group.len() >= min_group_len {
+                        merge_candidates.push(MergeCandidate(group));
+                    }
+
+                    group = Vec::new();
+                    group_docs = 0;
+                }
+
+                group.push(segment_meta.id());
+                group_docs = group_docs.saturating_add(segment_meta.num_docs());
+            }
+
+            if group.len() >= min_group_len {
+                merge_candidates.push(MergeCandidate(group));
+            }
+        }
+
+        merge_candidates
+    }
+}
+
+// ExpungeDeletesMergePolicy
+
+pub const DEFAULT_EXPUNGE_DELETES_RATIO: f32 = 0.3;
+
+/// Rewrites segments whose ratio of deleted docs reached `expunge_deletes_ratio`,
+/// most deleted first and up to `max_segments_per_merge` per merge, a lone segment
+/// being rewritten on its own.
+///
+/// The remaining segments are left to the inner policy, e.g. a size-based one.
+#[derive(Debug)]
+pub struct ExpungeDeletesMergePolicy<P: MergePolicy + ?Sized> {
+    expunge_deletes_ratio: f32,
+    max_segments_per_merge: usize,
+    inner: Box<P>,
+}
+
+impl<P: MergePolicy + ?Sized> ExpungeDeletesMergePolicy<P> {
+    pub fn new(inner: Box<P>) -> Self {
+        ExpungeDeletesMergePolicy{
+            expunge_deletes_ratio: DEFAULT_EXPUNGE_DELETES_RATIO,
+            max_segments_per_merge: DEFAULT_MAX_SEGMENTS_PER_MERGE,
+            inner,
+        }
+    }
+
+    pub fn with_expunge_deletes_ratio(mut self, expunge_deletes_ratio: f32) -> Self {
+        self.expunge_deletes_ratio = expunge_deletes_ratio;
+        self
+    }
+
+    pub fn with_max_segments_per_merge(mut self, max_segments_per_merge: usize) -> Self {
+        self.max_segments_per_merge = max_segments_per_merge;
+        self
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+}
+
+pub fn deleted_docs_ratio(segment_meta: &SegmentMeta) -> f32 {
+    match segment_meta.max_doc() {
+        0 => 0.0,
+        max_doc => segment_meta.num_deleted_docs() as f32 / max_doc as f32,
+    }
+}
+
+impl<P: MergePolicy + ?Sized> MergePolicy for ExpungeDeletesMergePolicy<P> {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let (mut expunged, remaining): (Vec<SegmentMeta>, Vec<SegmentMeta>) = segment_metas
+            .iter()
+            .cloned()
+            .partition(|segment_meta| {
+                segment_meta.has_deletes() && deleted_docs_ratio(segment_meta) >= self.expunge_deletes_ratio
+            });
+
+        expunged.sort_by(|left, right| deleted_docs_ratio(right).total_cmp(&deleted_docs_ratio(left)));
+
+        let mut merge_candidates = expunged
+            .chunks(self.max_segments_per_merge.max(1))
+            .map(|chunk| {
+                MergeCandidate(chunk
+                    .iter()
+                    .map(|segment_meta| segment_meta.id())
+                    .collect::<Vec<SegmentId>>())
+            })
+            .collect::<Vec<MergeCandidate>>();
+
+        merge_candidates.extend(self.inner.compute_merge_candidates(&remaining));
+        merge_candidates
+    }
+}
+
+// MergeWheneverPossiblePolicy
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeWheneverPossiblePolicy;
+
 impl MergeWheneverPossiblePolicy {
-    pub fn new(run_id: String) -> Self {
-        MergeWheneverPossiblePolicy{ run_id }
+    pub fn new() -> Self {
+        MergeWheneverPossiblePolicy
     }
 
     pub fn as_box(self) -> Box<Self> {
-        return Box::new(self);
+        Box::new(self)
     }
 }
 
 impl MergePolicy for MergeWheneverPossiblePolicy {
     fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
-        println!("{{\"run\":\"{}\",\"count\":\"{}\"}}", self.run_id, segment_metas.len());
-
         let segment_ids = segment_metas
             .iter()
             .map(|segment_meta| segment_meta.id())
@@ -92,3 +487,267 @@ impl MergePolicy for MergeWheneverPossiblePolicy {
         vec![]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tantivy::directory::RamDirectory;
+
+    use crate::simulator::{
+        Simulator,
+        SimulatorOptions,
+        SimulationOutcome,
+    };
+    use super::*;
+    use super::super::test_policies::{
+        FixedPolicy,
+        segment_id,
+        segment_metas,
+        candidate_ids,
+    };
+
+    const PACKINGS: [PackingStrategy; 2] = [PackingStrategy::FirstFit, PackingStrategy::BestFit];
+
+    fn num_docs(segments: &[SegmentMeta], segment_ids: &[SegmentId]) -> u32 {
+        segment_ids
+            .iter()
+            .filter_map(|segment_id| segments.iter().find(|segment| segment.id() == *segment_id))
+            .map(|segment| segment.num_docs())
+            .sum()
+    }
+
+    #[test]
+    fn target_docs_skips_segments_at_target() {
+        let segments = segment_metas(&[(100, 0), (150, 0), (40, 0), (120, 20), (30, 0)]);
+
+        for packing in PACKINGS {
+            let policy = TargetDocsPerSegmentPolicy::new(100).with_packing(packing);
+            let merge_candidates = candidate_ids(&policy.compute_merge_candidates(&segments));
+
+            // 100 live docs in the fourth segment too, deleted ones don't count
+            assert_eq!(merge_candidates, vec![vec![segment_id(3), segment_id(5)]]);
+        }
+    }
+
+    #[test]
+    fn target_docs_drops_singleton_bins() {
+        let segments = segment_metas(&[(60, 0), (70, 0), (80, 0)]);
+
+        for packing in PACKINGS {
+            let policy = TargetDocsPerSegmentPolicy::new(100).with_packing(packing);
+
+            assert!(policy.compute_merge_candidates(&segments).is_empty());
+            assert!(policy.compute_merge_candidates(&segments[..1]).is_empty());
+        }
+    }
+
+    #[test]
+    fn target_docs_candidates_never_exceed_target() {
+        let docs = [37, 5, 61, 12, 88, 40, 3, 59, 20, 71, 9, 33, 50, 26, 94, 1]
+            .map(|max_doc| (max_doc, 0));
+        let segments = segment_metas(&docs);
+
+        for packing in PACKINGS {
+            let policy = TargetDocsPerSegmentPolicy::new(100).with_packing(packing);
+            let merge_candidates = candidate_ids(&policy.compute_merge_candidates(&segments));
+
+            assert!(!merge_candidates.is_empty());
+
+            for segment_ids in merge_candidates.iter() {
+                assert!(segment_ids.len() > 1);
+                assert!(num_docs(&segments, segment_ids) <= 100);
+            }
+
+            let mut merged = merge_candidates.concat();
+            let count = merged.len();
+            merged.sort();
+            merged.dedup();
+            assert_eq!(merged.len(), count);
+        }
+    }
+
+    #[test]
+    fn target_docs_best_fit_fills_the_fullest_bin() {
+        let segments = segment_metas(&[(60, 0), (45, 0), (44, 0), (42, 0), (10, 0)]);
+
+        let first_fit = TargetDocsPerSegmentPolicy::new(100);
+        let best_fit = TargetDocsPerSegmentPolicy::new(100).with_packing(PackingStrategy::BestFit);
+
+        // The last 10 docs fit the bins of 60, 89 and 42 docs
+        assert_eq!(candidate_ids(&first_fit.compute_merge_candidates(&segments)), vec![
+            vec![segment_id(1), segment_id(5)],
+            vec![segment_id(2), segment_id(3)],
+        ]);
+        assert_eq!(candidate_ids(&best_fit.compute_merge_candidates(&segments)), vec![
+            vec![segment_id(2), segment_id(3), segment_id(5)],
+        ]);
+    }
+
+    #[test]
+    fn target_bytes_forgets_segments_gone_from_the_input() {
+        let policy = TargetBytesPerSegmentPolicy::new(Box::new(RamDirectory::create()), 1000);
+        let cached = |policy: &TargetBytesPerSegmentPolicy| {
+            let mut keys = policy.segment_bytes
+                .lock()
+                .unwrap()
+                .keys()
+                .copied()
+                .collect::<Vec<(SegmentId, u32)>>();
+            keys.sort();
+            keys
+        };
+
+        let segments = segment_metas(&[(10, 0), (20, 0), (30, 0)]);
+        policy.compute_merge_candidates(&segments);
+        assert_eq!(cached(&policy), vec![(segment_id(1), 0), (segment_id(2), 0), (segment_id(3), 0)]);
+
+        policy.compute_merge_candidates(&segments[1..]);
+        assert_eq!(cached(&policy), vec![(segment_id(2), 0), (segment_id(3), 0)]);
+
+        // Deletes change the key, the entry of the old deletes goes
+        let segments = segment_metas(&[(10, 0), (20, 5)]);
+        policy.compute_merge_candidates(&segments[1..]);
+        assert_eq!(cached(&policy), vec![(segment_id(2), 5)]);
+
+        policy.compute_merge_candidates(&[]);
+        assert!(cached(&policy).is_empty());
+    }
+
+    fn simulate_tiered(policy: &TieredMergePolicy, commits: usize, docs_per_commit: u32) -> Simulator {
+        let mut simulator = Simulator::new(policy.clone().as_box(), SimulatorOptions{
+            seed: 3,
+            ..SimulatorOptions::default()
+        });
+
+        for _ in 0..commits {
+            simulator.add_documents(docs_per_commit);
+            simulator.commit();
+            simulator.advance_clock(10);
+        }
+
+        assert_eq!(simulator.wait_merging_threads(100_000), SimulationOutcome::Converged);
+        simulator
+    }
+
+    /// Once merges settle, no tier holds enough segments to be merged
+    fn assert_no_tier_due(policy: &TieredMergePolicy, simulator: &Simulator) {
+        let mut tiers: HashMap<u32, usize> = HashMap::new();
+
+        for segment in simulator.segments() {
+            *tiers.entry(policy.tier(segment.num_docs())).or_default() += 1;
+        }
+
+        assert!(tiers.values().all(|count| *count < policy.merge_factor), "{:?}", tiers);
+    }
+
+    #[test]
+    fn tiered_write_amplification_stays_logarithmic() {
+        let policy = TieredMergePolicy::new()
+            .with_merge_factor(10)
+            .with_floor_segment_docs(10);
+
+        let simulator = simulate_tiered(&policy, 2000, 10);
+        let stats = simulator.stats();
+
+        // Flushed segments of 10 docs are rewritten into segments of 100, 1000 and 10000
+        assert_eq!(stats.docs_indexed, 20_000);
+        assert_eq!(stats.merges_rejected, 0);
+        assert!(stats.write_amplification() <= 4.0, "{:?}", stats);
+        assert_no_tier_due(&policy, &simulator);
+    }
+
+    #[test]
+    fn tiered_write_amplification_below_the_floor_is_bounded() {
+        let policy = TieredMergePolicy::new();
+
+        let short = simulate_tiered(&policy, 1000, 1);
+        let long = simulate_tiered(&policy, 4000, 1);
+
+        // Segments below the floor share a tier, merges keep adding the few newest docs
+        // to the biggest of them until it leaves the tier
+        let bound = 1.0 + (DEFAULT_FLOOR_SEGMENT_DOCS as f64) / (DEFAULT_MERGE_FACTOR as f64 - 1.0);
+
+        for simulator in [&short, &long] {
+            assert!(simulator.stats().write_amplification() <= bound, "{:?}", simulator.stats());
+            assert_no_tier_due(&policy, simulator);
+        }
+
+        // It doesn't grow with the number of commits
+        let growth = long.stats().write_amplification() - short.stats().write_amplification();
+        assert!(growth.abs() < 1.0, "{:?} {:?}", short.stats(), long.stats());
+    }
+
+    #[test]
+    fn tiered_merges_with_max_merge_at_once_below_merge_factor() {
+        let policy = TieredMergePolicy::new()
+            .with_merge_factor(10)
+            .with_max_merge_at_once(4);
+
+        let segments = segment_metas(&[(5, 0); 12]);
+        let merge_candidates = candidate_ids(&policy.compute_merge_candidates(&segments));
+
+        // Three full groups of 4, the tier being due
+        assert_eq!(merge_candidates.len(), 3);
+        assert!(merge_candidates.iter().all(|segment_ids| segment_ids.len() == 4));
+        assert!(policy.compute_merge_candidates(&segments[..9]).is_empty());
+
+        let simulator = simulate_tiered(&policy, 1000, 10);
+        let stats = simulator.stats();
+
+        assert!(stats.merges_completed > 0);
+        assert!(stats.write_amplification() <= 1.0 + (DEFAULT_FLOOR_SEGMENT_DOCS as f64) / 3.0, "{:?}", stats);
+        assert_no_tier_due(&policy, &simulator);
+    }
+
+    #[test]
+    fn expunge_deletes_at_the_ratio() {
+        // Ratios of 0.29, 0.3 and 0.31
+        let segments = segment_metas(&[(100, 29), (100, 30), (100, 31), (100, 0)]);
+        let policy = ExpungeDeletesMergePolicy::new(FixedPolicy::new(vec![]).as_box())
+            .with_expunge_deletes_ratio(0.3);
+
+        assert_eq!(
+            candidate_ids(&policy.compute_merge_candidates(&segments)),
+            vec![vec![segment_id(3), segment_id(2)]],
+        );
+
+        let policy = ExpungeDeletesMergePolicy::new(FixedPolicy::new(vec![]).as_box())
+            .with_expunge_deletes_ratio(0.32);
+
+        assert!(policy.compute_merge_candidates(&segments).is_empty());
+    }
+
+    #[test]
+    fn expunge_deletes_rewrites_a_lone_segment() {
+        let segments = segment_metas(&[(1000, 900), (100, 0), (200, 10)]);
+        let policy = ExpungeDeletesMergePolicy::new(FixedPolicy::new(vec![]).as_box());
+
+        assert_eq!(candidate_ids(&policy.compute_merge_candidates(&segments)), vec![vec![segment_id(1)]]);
+    }
+
+    #[test]
+    fn expunge_deletes_chunks_by_ratio() {
+        let segments = segment_metas(&[(100, 40), (100, 90), (100, 50), (100, 70), (100, 60), (100, 10)]);
+        let policy = ExpungeDeletesMergePolicy::new(FixedPolicy::new(vec![]).as_box())
+            .with_max_segments_per_merge(2);
+
+        assert_eq!(candidate_ids(&policy.compute_merge_candidates(&segments)), vec![
+            vec![segment_id(2), segment_id(4)],
+            vec![segment_id(5), segment_id(3)],
+            vec![segment_id(1)],
+        ]);
+    }
+
+    #[test]
+    fn expunge_deletes_leaves_the_remaining_segments_to_the_inner_policy() {
+        let segments = segment_metas(&[(100, 50), (100, 0), (100, 5), (100, 80)]);
+        let inner = FixedPolicy::new(vec![vec![segment_id(2), segment_id(3)]]);
+        let policy = ExpungeDeletesMergePolicy::new(inner.as_box());
+
+        // Expunged first, then the candidates of the inner policy
+        assert_eq!(candidate_ids(&policy.compute_merge_candidates(&segments)), vec![
+            vec![segment_id(4), segment_id(1)],
+            vec![segment_id(2), segment_id(3)],
+        ]);
+        assert_eq!(inner.calls(), vec![vec![segment_id(2), segment_id(3)]]);
+    }
+}
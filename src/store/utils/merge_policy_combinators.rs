@@ -0,0 +1,342 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::Mutex,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use tantivy::{
+    index::{
+        SegmentId,
+        SegmentMeta,
+    },
+    merge_policy::{
+        MergePolicy,
+        MergeCandidate,
+    },
+};
+
+// Chain
+
+/// Candidates of `first`, then candidates `second` computes from the segments
+/// `first` left untouched
+#[derive(Debug)]
+pub struct Chain<A: MergePolicy + ?Sized, B: MergePolicy + ?Sized> {
+    first: Box<A>,
+    second: Box<B>,
+}
+
+impl<A: MergePolicy + ?Sized, B: MergePolicy + ?Sized> Chain<A, B> {
+    pub fn new(first: Box<A>, second: Box<B>) -> Self {
+        Chain{
+            first,
+            second,
+        }
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+}
+
+impl<A: MergePolicy + ?Sized, B: MergePolicy + ?Sized> MergePolicy for Chain<A, B> {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let mut merge_candidates = self.first.compute_merge_candidates(segment_metas);
+
+        let mut used_segment_ids = merge_candidates
+            .iter()
+            .flat_map(|merge_candidate| merge_candidate.0.iter().copied())
+            .collect::<HashSet<SegmentId>>();
+
+        let remaining = segment_metas
+            .iter()
+            .filter(|segment_meta| !used_segment_ids.contains(&segment_meta.id()))
+            .cloned()
+            .collect::<Vec<SegmentMeta>>();
+
+        for merge_candidate in self.second.compute_merge_candidates(&remaining) {
+            let overlaps = merge_candidate.0
+                .iter()
+                .any(|segment_id| used_segment_ids.contains(segment_id));
+
+            if !overlaps {
+                used_segment_ids.extend(merge_candidate.0.iter().copied());
+                merge_candidates.push(merge_candidate);
+            }
+        }
+
+        merge_candidates
+    }
+}
+
+// Fallback
+
+/// Candidates of `primary`, or of `fallback` when `primary` has none
+#[derive(Debug)]
+pub struct Fallback<A: MergePolicy + ?Sized, B: MergePolicy + ?Sized> {
+    primary: Box<A>,
+    fallback: Box<B>,
+}
+
+impl<A: MergePolicy + ?Sized, B: MergePolicy + ?Sized> Fallback<A, B> {
+    pub fn new(primary: Box<A>, fallback: Box<B>) -> Self {
+        Fallback{
+            primary,
+            fallback,
+        }
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+}
+
+impl<A: MergePolicy + ?Sized, B: MergePolicy + ?Sized> MergePolicy for Fallback<A, B> {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let merge_candidates = self.primary.compute_merge_candidates(segment_metas);
+
+        if merge_candidates.is_empty() {
+            return self.fallback.compute_merge_candidates(segment_metas);
+        }
+
+        merge_candidates
+    }
+}
+
+// Filter
+
+/// Hides the segments not matching the predicate from the inner policy
+pub struct Filter<P: MergePolicy + ?Sized, F: Fn(&SegmentMeta) -> bool + Send + Sync + 'static> {
+    predicate: F,
+    inner: Box<P>,
+}
+
+impl<P: MergePolicy + ?Sized, F: Fn(&SegmentMeta) -> bool + Send + Sync + 'static> Filter<P, F> {
+    pub fn new(inner: Box<P>, predicate: F) -> Self {
+        Filter{
+            predicate,
+            inner,
+        }
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+}
+
+impl<P: MergePolicy + ?Sized, F: Fn(&SegmentMeta) -> bool + Send + Sync + 'static> fmt::Debug for Filter<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Filter")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: MergePolicy + ?Sized, F: Fn(&SegmentMeta) -> bool + Send + Sync + 'static> MergePolicy for Filter<P, F> {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let segment_metas = segment_metas
+            .iter()
+            .filter(|segment_meta| (self.predicate)(segment_meta))
+            .cloned()
+            .collect::<Vec<SegmentMeta>>();
+
+        self.inner.compute_merge_candidates(&segment_metas)
+    }
+}
+
+// Limit
+
+/// At most `max_candidates` of the inner policy candidates, in their original order
+#[derive(Debug)]
+pub struct Limit<P: MergePolicy + ?Sized> {
+    max_candidates: usize,
+    inner: Box<P>,
+}
+
+impl<P: MergePolicy + ?Sized> Limit<P> {
+    pub fn new(inner: Box<P>, max_candidates: usize) -> Self {
+        Limit{
+            max_candidates,
+            inner,
+        }
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+}
+
+impl<P: MergePolicy + ?Sized> MergePolicy for Limit<P> {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let mut merge_candidates = self.inner.compute_merge_candidates(segment_metas);
+        merge_candidates.truncate(self.max_candidates);
+        merge_candidates
+    }
+}
+
+// Cooldown
+
+/// Suppresses the inner policy candidates for `min_interval` after it last returned some
+#[derive(Debug)]
+pub struct Cooldown<P: MergePolicy + ?Sized> {
+    min_interval: Duration,
+    last_merge_at: Mutex<Option<Instant>>,
+    inner: Box<P>,
+}
+
+impl<P: MergePolicy + ?Sized> Cooldown<P> {
+    pub fn new(inner: Box<P>, min_interval: Duration) -> Self {
+        Cooldown{
+            min_interval,
+            last_merge_at: Mutex::new(None),
+            inner,
+        }
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+}
+
+impl<P: MergePolicy + ?Sized> MergePolicy for Cooldown<P> {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let mut last_merge_at = match self.last_merge_at.lock() {
+            Ok(last_merge_at) => last_merge_at,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if let Some(last_merge_at) = *last_merge_at {
+            if last_merge_at.elapsed() < self.min_interval {
+                return vec![];
+            }
+        }
+
+        let merge_candidates = self.inner.compute_merge_candidates(segment_metas);
+
+        if !merge_candidates.is_empty() {
+            *last_merge_at = Some(Instant::now());
+        }
+
+        merge_candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use super::super::test_policies::{
+        FixedPolicy,
+        segment_id,
+        segment_metas,
+        candidate_ids,
+    };
+
+    #[test]
+    fn chain_hands_the_untouched_segments_to_second() {
+        let segments = segment_metas(&[(10, 0), (20, 0), (30, 0), (40, 0)]);
+        let first = FixedPolicy::new(vec![vec![segment_id(1), segment_id(2)]]);
+        // Overlapping candidates of the second policy are dropped
+        let second = FixedPolicy::new(vec![
+            vec![segment_id(2), segment_id(3)],
+            vec![segment_id(3), segment_id(4)],
+        ]);
+
+        let policy = Chain::new(first.as_box(), second.as_box());
+
+        assert_eq!(candidate_ids(&policy.compute_merge_candidates(&segments)), vec![
+            vec![segment_id(1), segment_id(2)],
+            vec![segment_id(3), segment_id(4)],
+        ]);
+        assert_eq!(second.calls(), vec![vec![segment_id(3), segment_id(4)]]);
+    }
+
+    #[test]
+    fn fallback_only_runs_when_primary_has_no_candidates() {
+        let segments = segment_metas(&[(10, 0), (20, 0), (30, 0)]);
+        let fallback = FixedPolicy::new(vec![vec![segment_id(2), segment_id(3)]]);
+
+        let primary = FixedPolicy::new(vec![vec![segment_id(1), segment_id(2)]]);
+        let policy = Fallback::new(primary.as_box(), fallback.as_box());
+
+        assert_eq!(candidate_ids(&policy.compute_merge_candidates(&segments)), vec![
+            vec![segment_id(1), segment_id(2)],
+        ]);
+        assert!(fallback.calls().is_empty());
+
+        let primary = FixedPolicy::new(vec![]);
+        let policy = Fallback::new(primary.as_box(), fallback.as_box());
+
+        assert_eq!(candidate_ids(&policy.compute_merge_candidates(&segments)), vec![
+            vec![segment_id(2), segment_id(3)],
+        ]);
+        assert_eq!(primary.calls().len(), 1);
+        assert_eq!(fallback.calls().len(), 1);
+    }
+
+    #[test]
+    fn filter_hides_segments_from_the_inner_policy() {
+        let segments = segment_metas(&[(10, 0), (20, 5), (30, 0), (40, 10)]);
+        let inner = FixedPolicy::new(vec![]);
+
+        let policy = Filter::new(inner.as_box(), |segment_meta: &SegmentMeta| segment_meta.has_deletes());
+        policy.compute_merge_candidates(&segments);
+
+        assert_eq!(inner.calls(), vec![vec![segment_id(2), segment_id(4)]]);
+    }
+
+    #[test]
+    fn limit_truncates_in_order() {
+        let segments = segment_metas(&[(10, 0); 6]);
+        let candidates = vec![
+            vec![segment_id(1), segment_id(2)],
+            vec![segment_id(3), segment_id(4)],
+            vec![segment_id(5), segment_id(6)],
+        ];
+        let inner = FixedPolicy::new(candidates.clone());
+
+        for (max_candidates, expected) in [(0, 0), (2, 2), (3, 3), (5, 3)] {
+            let policy = Limit::new(inner.as_box(), max_candidates);
+
+            assert_eq!(candidate_ids(&policy.compute_merge_candidates(&segments)), candidates[..expected]);
+        }
+    }
+
+    #[test]
+    fn cooldown_suppresses_candidates_after_a_merge() {
+        let segments = segment_metas(&[(10, 0), (20, 0)]);
+        let inner = FixedPolicy::new(vec![vec![segment_id(1), segment_id(2)]]);
+
+        let policy = Cooldown::new(inner.as_box(), Duration::from_millis(200));
+
+        assert_eq!(policy.compute_merge_candidates(&segments).len(), 1);
+
+        // The inner policy isn't even asked during the cooldown
+        for _ in 0..3 {
+            assert!(policy.compute_merge_candidates(&segments).is_empty());
+        }
+        assert_eq!(inner.calls().len(), 1);
+
+        thread::sleep(Duration::from_millis(250));
+
+        assert_eq!(policy.compute_merge_candidates(&segments).len(), 1);
+        assert!(policy.compute_merge_candidates(&segments).is_empty());
+        assert_eq!(inner.calls().len(), 2);
+    }
+
+    #[test]
+    fn cooldown_only_starts_with_candidates() {
+        let segments = segment_metas(&[(10, 0)]);
+        let inner = FixedPolicy::new(vec![]);
+
+        let policy = Cooldown::new(inner.as_box(), Duration::from_secs(60));
+
+        for _ in 0..3 {
+            assert!(policy.compute_merge_candidates(&segments).is_empty());
+        }
+        assert_eq!(inner.calls().len(), 3);
+    }
+}
@@ -0,0 +1,1048 @@
+use std::{
+    collections::BTreeMap,
+    error,
+    fmt,
+    fs,
+    io,
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use tantivy::{
+    Index,
+    directory::DirectoryClone,
+    merge_policy::{
+        LogMergePolicy,
+        MergePolicy,
+        NoMergePolicy,
+    },
+};
+
+use super::{
+    merge_policy::{
+        TargetDocsPerSegmentPolicy,
+        DEFAULT_TARGET_DOCS_PER_SEGMENT,
+        PackingStrategy,
+        TargetBytesPerSegmentPolicy,
+        DEFAULT_MAX_SEGMENTS_PER_MERGE,
+        TieredMergePolicy,
+        DEFAULT_MERGE_FACTOR,
+        DEFAULT_FLOOR_SEGMENT_DOCS,
+        DEFAULT_MAX_MERGE_AT_ONCE,
+        DEFAULT_MAX_MERGED_SEGMENT_DOCS,
+        ExpungeDeletesMergePolicy,
+        DEFAULT_EXPUNGE_DELETES_RATIO,
+        MergeWheneverPossiblePolicy,
+    },
+    merge_policy_combinators::{
+        Chain,
+        Fallback,
+        Limit,
+        Cooldown,
+    },
+    merge_policy_throttle::{
+        ThrottledMergePolicy,
+        ThrottleWindow,
+        MergeBudget,
+        MergeBudgetUnit,
+    },
+};
+
+// MergePolicyConfigError
+
+#[derive(Debug)]
+pub enum MergePolicyConfigError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    /// `named` policy missing from the registry
+    UnknownPolicy(String),
+    /// `named` policy referring back to itself
+    CyclicPolicy(String),
+    InvalidValue(String),
+}
+
+impl fmt::Display for MergePolicyConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergePolicyConfigError::Io(err) => write!(f, "Failed to read merge policy config: {}", err),
+            MergePolicyConfigError::Json(err) => write!(f, "Invalid JSON merge policy config: {}", err),
+            MergePolicyConfigError::Toml(err) => write!(f, "Invalid TOML merge policy config: {}", err),
+            MergePolicyConfigError::UnknownPolicy(name) => write!(f, "Unknown merge policy {:?}", name),
+            MergePolicyConfigError::CyclicPolicy(name) => write!(f, "Merge policy {:?} refers to itself", name),
+            MergePolicyConfigError::InvalidValue(message) => write!(f, "Invalid merge policy config: {}", message),
+        }
+    }
+}
+
+impl error::Error for MergePolicyConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            MergePolicyConfigError::Io(err) => Some(err),
+            MergePolicyConfigError::Json(err) => Some(err),
+            MergePolicyConfigError::Toml(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MergePolicyConfigError {
+    fn from(err: io::Error) -> Self {
+        MergePolicyConfigError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for MergePolicyConfigError {
+    fn from(err: serde_json::Error) -> Self {
+        MergePolicyConfigError::Json(err)
+    }
+}
+
+impl From<toml::de::Error> for MergePolicyConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        MergePolicyConfigError::Toml(err)
+    }
+}
+
+fn invalid_value(message: String) -> MergePolicyConfigError {
+    MergePolicyConfigError::InvalidValue(message)
+}
+
+// MergePolicyConfig
+
+fn default_max_segments_per_merge() -> usize {
+    DEFAULT_MAX_SEGMENTS_PER_MERGE
+}
+
+fn default_merge_factor() -> usize {
+    DEFAULT_MERGE_FACTOR
+}
+
+fn default_floor_segment_docs() -> u32 {
+    DEFAULT_FLOOR_SEGMENT_DOCS
+}
+
+fn default_max_merge_at_once() -> usize {
+    DEFAULT_MAX_MERGE_AT_ONCE
+}
+
+fn default_max_merged_segment_docs() -> u32 {
+    DEFAULT_MAX_MERGED_SEGMENT_DOCS
+}
+
+fn default_expunge_deletes_ratio() -> f32 {
+    DEFAULT_EXPUNGE_DELETES_RATIO
+}
+
+/// Serializable description of a merge policy, e.g. in TOML
+///
+/// ```toml
+/// [merge_policy.expunge_deletes]
+/// expunge_deletes_ratio = 0.2
+/// inner = { tiered = { merge_factor = 8 } }
+/// ```
+///
+/// Omitted tuning values fall back to the defaults of the policy.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicyConfig {
+    #[serde(alias = "merge_whenever")]
+    Whenever,
+    NoMerge,
+    TargetDocs {
+        target_docs_per_segment: u32,
+        #[serde(default)]
+        packing: PackingStrategy,
+    },
+    TargetBytes {
+        max_merged_segment_bytes: u64,
+        #[serde(default = "default_max_segments_per_merge")]
+        max_segments_per_merge: usize,
+    },
+    Tiered {
+        #[serde(default = "default_merge_factor")]
+        merge_factor: usize,
+        #[serde(default = "default_floor_segment_docs")]
+        floor_segment_docs: u32,
+        #[serde(default = "default_max_merge_at_once")]
+        max_merge_at_once: usize,
+        #[serde(default = "default_max_merged_segment_docs")]
+        max_merged_segment_docs: u32,
+    },
+    /// Tantivy's `LogMergePolicy`
+    Log {
+        #[serde(default)]
+        min_num_segments: Option<usize>,
+        #[serde(default)]
+        max_docs_before_merge: Option<usize>,
+        #[serde(default)]
+        min_layer_size: Option<u32>,
+        #[serde(default)]
+        level_log_size: Option<f64>,
+        #[serde(default)]
+        del_docs_ratio_before_merge: Option<f32>,
+    },
+    ExpungeDeletes {
+        inner: Box<MergePolicyConfig>,
+        #[serde(default = "default_expunge_deletes_ratio")]
+        expunge_deletes_ratio: f32,
+        #[serde(default = "default_max_segments_per_merge")]
+        max_segments_per_merge: usize,
+    },
+    Chain {
+        first: Box<MergePolicyConfig>,
+        second: Box<MergePolicyConfig>,
+    },
+    Fallback {
+        primary: Box<MergePolicyConfig>,
+        fallback: Box<MergePolicyConfig>,
+    },
+    Limit {
+        inner: Box<MergePolicyConfig>,
+        max_candidates: usize,
+    },
+    Cooldown {
+        inner: Box<MergePolicyConfig>,
+        min_interval_millis: u64,
+    },
+    Throttled {
+        inner: Box<MergePolicyConfig>,
+        #[serde(default)]
+        windows: Vec<ThrottleWindow>,
+        #[serde(default)]
+        utc_offset_minutes: i32,
+        #[serde(default)]
+        budget: Option<MergeBudget>,
+    },
+    /// Policy registered under this name in the `MergePolicyRegistry`
+    Named(String),
+}
+
+impl MergePolicyConfig {
+    pub fn from_json(value: &str) -> Result<MergePolicyConfig, MergePolicyConfigError> {
+        Ok(serde_json::from_str(value)?)
+    }
+
+    pub fn from_toml(value: &str) -> Result<MergePolicyConfig, MergePolicyConfigError> {
+        Ok(toml::from_str(value)?)
+    }
+
+    /// Reads a `.toml` file, or JSON for any other extension
+    pub fn read<T: AsRef<Path>>(path: T) -> Result<MergePolicyConfig, MergePolicyConfigError> {
+        let content = fs::read_to_string(path.as_ref())?;
+
+        if is_toml(path.as_ref()) {
+            MergePolicyConfig::from_toml(&content)
+        } else {
+            MergePolicyConfig::from_json(&content)
+        }
+    }
+
+    /// Builds the policy, `named` policies resolved from the built-in ones
+    pub fn build(&self, index: &Index) -> Result<Box<dyn MergePolicy>, MergePolicyConfigError> {
+        MergePolicyRegistry::default().build(self, index)
+    }
+}
+
+fn is_toml(path: &Path) -> bool {
+    match path.extension() {
+        Some(extension) => extension == "toml",
+        None => false,
+    }
+}
+
+impl fmt::Display for MergePolicyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergePolicyConfig::Whenever => write!(f, "whenever"),
+            MergePolicyConfig::NoMerge => write!(f, "no_merge"),
+            MergePolicyConfig::TargetDocs{ target_docs_per_segment, packing } => match packing {
+                PackingStrategy::FirstFit => write!(f, "target_docs:{}", target_docs_per_segment),
+                PackingStrategy::BestFit => write!(f, "target_docs:{}:best_fit", target_docs_per_segment),
+            },
+            MergePolicyConfig::TargetBytes{ max_merged_segment_bytes, max_segments_per_merge } => {
+                write!(f, "target_bytes:{}:{}", max_merged_segment_bytes, max_segments_per_merge)
+            },
+            MergePolicyConfig::Tiered{ merge_factor, floor_segment_docs, max_merge_at_once, max_merged_segment_docs } => {
+                write!(
+                    f,
+                    "tiered:{}:{}:{}:{}",
+                    merge_factor,
+                    floor_segment_docs,
+                    max_merge_at_once,
+                    max_merged_segment_docs,
+                )
+            },
+            MergePolicyConfig::Log{
+                min_num_segments,
+                max_docs_before_merge,
+                min_layer_size,
+                level_log_size,
+                del_docs_ratio_before_merge,
+            } => {
+                let values = [
+                    min_num_segments.map(|value| value.to_string()),
+                    max_docs_before_merge.map(|value| value.to_string()),
+                    min_layer_size.map(|value| value.to_string()),
+                    level_log_size.map(|value| value.to_string()),
+                    del_docs_ratio_before_merge.map(|value| value.to_string()),
+                ];
+
+                write!(f, "log")?;
+
+                // Unset values are left empty, all of them being left out by default
+                if values.iter().any(|value| value.is_some()) {
+                    for value in values.iter() {
+                        write!(f, ":{}", value.as_deref().unwrap_or_default())?;
+                    }
+                }
+
+                Ok(())
+            },
+            MergePolicyConfig::ExpungeDeletes{ inner, expunge_deletes_ratio, max_segments_per_merge } => {
+                write!(f, "expunge_deletes:{}:{}({})", expunge_deletes_ratio, max_segments_per_merge, inner)
+            },
+            MergePolicyConfig::Chain{ first, second } => write!(f, "chain({},{})", first, second),
+            MergePolicyConfig::Fallback{ primary, fallback } => write!(f, "fallback({},{})", primary, fallback),
+            MergePolicyConfig::Limit{ inner, max_candidates } => write!(f, "limit:{}({})", max_candidates, inner),
+            MergePolicyConfig::Cooldown{ inner, min_interval_millis } => {
+                write!(f, "cooldown:{}ms({})", min_interval_millis, inner)
+            },
+            MergePolicyConfig::Throttled{ inner, windows, utc_offset_minutes, budget } => {
+                write!(f, "throttled")?;
+
+                if *utc_offset_minutes != 0 {
+                    write!(f, ":utc{:+}", utc_offset_minutes)?;
+                }
+
+                if !windows.is_empty() {
+                    let windows = windows
+                        .iter()
+                        .map(|window| window.to_string())
+                        .collect::<Vec<String>>();
+                    write!(f, ":{}", windows.join(","))?;
+                }
+
+                if let Some(budget) = budget {
+                    let unit = match budget.unit {
+                        MergeBudgetUnit::Docs => "docs",
+                        MergeBudgetUnit::Bytes => "bytes",
+                    };
+                    write!(f, ":{}={}~{}", unit, budget.rate_per_second, budget.burst)?;
+                }
+
+                write!(f, "({})", inner)
+            },
+            MergePolicyConfig::Named(name) => write!(f, "named:{}", name),
+        }
+    }
+}
+
+fn parse_spec_value<T: FromStr>(spec: &str, value: Option<&str>, default: T) -> Result<T, MergePolicyConfigError> {
+    match value {
+        None | Some("") => Ok(default),
+        Some(value) => match value.replace('_', "").parse::<T>() {
+            Ok(value) => Ok(value),
+            Err(_) => Err(invalid_value(format!("{:?} is not a number in {:?}", value, spec))),
+        },
+    }
+}
+
+/// Like `parse_spec_value`, an omitted value being left unset
+fn parse_optional_spec_value<T: FromStr>(spec: &str, value: Option<&str>) -> Result<Option<T>, MergePolicyConfigError> {
+    match value {
+        None | Some("") => Ok(None),
+        Some(value) => match value.replace('_', "").parse::<T>() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(invalid_value(format!("{:?} is not a number in {:?}", value, spec))),
+        },
+    }
+}
+
+/// Parses the short form printed by `Display` for the policies without inner ones,
+/// e.g. `target_docs:10000:best_fit`, `tiered:10:1000` or `log:8::::0.3`, omitted
+/// values falling back to the defaults. Any other name refers to a registered policy, while JSON is parsed as is.
+impl FromStr for MergePolicyConfig {
+    type Err = MergePolicyConfigError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let spec = spec.trim();
+
+        if spec.starts_with('{') || spec.starts_with('"') {
+            return MergePolicyConfig::from_json(spec);
+        }
+
+        let mut parts = spec.split(':');
+        let name = parts.next().unwrap_or_default();
+        let values = parts.collect::<Vec<&str>>();
+        let value = |index: usize| values.get(index).copied();
+
+        let max_values = match name {
+            "whenever" | "merge_whenever" | "no_merge" => 0,
+            "named" => 1,
+            "target_docs" => 2,
+            "target_bytes" => 2,
+            "tiered" => 4,
+            "log" => 5,
+            _ => 0,
+        };
+
+        if values.len() > max_values {
+            return Err(invalid_value(format!("too many values in {:?}", spec)));
+        }
+
+        let merge_policy_config = match name {
+            "whenever" | "merge_whenever" => MergePolicyConfig::Whenever,
+            "no_merge" => MergePolicyConfig::NoMerge,
+            "log" => MergePolicyConfig::Log{
+                min_num_segments: parse_optional_spec_value(spec, value(0))?,
+                max_docs_before_merge: parse_optional_spec_value(spec, value(1))?,
+                min_layer_size: parse_optional_spec_value(spec, value(2))?,
+                level_log_size: parse_optional_spec_value(spec, value(3))?,
+                del_docs_ratio_before_merge: parse_optional_spec_value(spec, value(4))?,
+            },
+            "target_docs" => MergePolicyConfig::TargetDocs{
+                target_docs_per_segment: parse_spec_value(spec, value(0), DEFAULT_TARGET_DOCS_PER_SEGMENT)?,
+                packing: match value(1) {
+                    None | Some("") | Some("first_fit") => PackingStrategy::FirstFit,
+                    Some("best_fit") => PackingStrategy::BestFit,
+                    Some(packing) => {
+                        return Err(invalid_value(format!("{:?} is not a packing strategy in {:?}", packing, spec)));
+                    },
+                },
+            },
+            "target_bytes" => MergePolicyConfig::TargetBytes{
+                max_merged_segment_bytes: match value(0) {
+                    Some(_) => parse_spec_value(spec, value(0), 0)?,
+                    None => {
+                        return Err(invalid_value(format!("{:?} is missing the max merged segment bytes", spec)));
+                    },
+                },
+                max_segments_per_merge: parse_spec_value(spec, value(1), DEFAULT_MAX_SEGMENTS_PER_MERGE)?,
+            },
+            "tiered" => MergePolicyConfig::Tiered{
+                merge_factor: parse_spec_value(spec, value(0), DEFAULT_MERGE_FACTOR)?,
+                floor_segment_docs: parse_spec_value(spec, value(1), DEFAULT_FLOOR_SEGMENT_DOCS)?,
+                max_merge_at_once: parse_spec_value(spec, value(2), DEFAULT_MAX_MERGE_AT_ONCE)?,
+                max_merged_segment_docs: parse_spec_value(spec, value(3), DEFAULT_MAX_MERGED_SEGMENT_DOCS)?,
+            },
+            "named" => match value(0) {
+                Some(name) if !name.is_empty() => MergePolicyConfig::Named(name.to_string()),
+                _ => {
+                    return Err(invalid_value(format!("{:?} is missing the policy name", spec)));
+                },
+            },
+            "" => {
+                return Err(invalid_value("empty merge policy".to_string()));
+            },
+            name => MergePolicyConfig::Named(name.to_string()),
+        };
+
+        Ok(merge_policy_config)
+    }
+}
+
+// MergePolicyRegistry
+
+/// Named merge policies, `MergePolicyRegistry::default()` holding `whenever`,
+/// `no_merge`, `target_docs`, `tiered` and `log` with their default tuning
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MergePolicyRegistry {
+    policies: BTreeMap<String, MergePolicyConfig>,
+}
+
+impl Default for MergePolicyRegistry {
+    fn default() -> Self {
+        MergePolicyRegistry::empty()
+            .with_policy("whenever", MergePolicyConfig::Whenever)
+            .with_policy("no_merge", MergePolicyConfig::NoMerge)
+            .with_policy("target_docs", MergePolicyConfig::TargetDocs{
+                target_docs_per_segment: DEFAULT_TARGET_DOCS_PER_SEGMENT,
+                packing: PackingStrategy::default(),
+            })
+            .with_policy("tiered", MergePolicyConfig::Tiered{
+                merge_factor: DEFAULT_MERGE_FACTOR,
+                floor_segment_docs: DEFAULT_FLOOR_SEGMENT_DOCS,
+                max_merge_at_once: DEFAULT_MAX_MERGE_AT_ONCE,
+                max_merged_segment_docs: DEFAULT_MAX_MERGED_SEGMENT_DOCS,
+            })
+            .with_policy("log", MergePolicyConfig::Log{
+                min_num_segments: None,
+                max_docs_before_merge: None,
+                min_layer_size: None,
+                level_log_size: None,
+                del_docs_ratio_before_merge: None,
+            })
+    }
+}
+
+impl MergePolicyRegistry {
+    pub fn empty() -> Self {
+        MergePolicyRegistry{
+            policies: BTreeMap::new(),
+        }
+    }
+
+    /// Registers or replaces the policy named `name`
+    pub fn with_policy(mut self, name: &str, config: MergePolicyConfig) -> Self {
+        self.register(name, config);
+        self
+    }
+
+    pub fn register(&mut self, name: &str, config: MergePolicyConfig) {
+        self.policies.insert(name.to_string(), config);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MergePolicyConfig> {
+        self.policies.get(name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.policies
+            .keys()
+            .map(|name| name.as_str())
+            .collect::<Vec<&str>>()
+    }
+
+    /// Registers every policy of a table mapping names to configs, e.g. in TOML
+    ///
+    /// ```toml
+    /// [production.tiered]
+    /// merge_factor = 8
+    ///
+    /// [nightly.chain]
+    /// first = { named = "production" }
+    /// second = { target_docs = { target_docs_per_segment = 100000 } }
+    /// ```
+    pub fn extend_from_toml(&mut self, value: &str) -> Result<(), MergePolicyConfigError> {
+        let policies: BTreeMap<String, MergePolicyConfig> = toml::from_str(value)?;
+        self.policies.extend(policies);
+        Ok(())
+    }
+
+    pub fn extend_from_json(&mut self, value: &str) -> Result<(), MergePolicyConfigError> {
+        let policies: BTreeMap<String, MergePolicyConfig> = serde_json::from_str(value)?;
+        self.policies.extend(policies);
+        Ok(())
+    }
+
+    /// Reads a `.toml` file, or JSON for any other extension
+    pub fn extend_from_file<T: AsRef<Path>>(&mut self, path: T) -> Result<(), MergePolicyConfigError> {
+        let content = fs::read_to_string(path.as_ref())?;
+
+        if is_toml(path.as_ref()) {
+            self.extend_from_toml(&content)
+        } else {
+            self.extend_from_json(&content)
+        }
+    }
+
+    pub fn build_named(&self, name: &str, index: &Index) -> Result<Box<dyn MergePolicy>, MergePolicyConfigError> {
+        self.build(&MergePolicyConfig::Named(name.to_string()), index)
+    }
+
+    /// Validates the config and builds the policy, resolving `named` policies
+    /// from this registry. `index` provides the directory of the size-based policies.
+    pub fn build(&self, config: &MergePolicyConfig, index: &Index) -> Result<Box<dyn MergePolicy>, MergePolicyConfigError> {
+        self.build_resolving(config, index, &mut Vec::new())
+    }
+
+    fn build_resolving(
+        &self,
+        config: &MergePolicyConfig,
+        index: &Index,
+        resolving: &mut Vec<String>,
+    ) -> Result<Box<dyn MergePolicy>, MergePolicyConfigError> {
+        let merge_policy: Box<dyn MergePolicy> = match config {
+            MergePolicyConfig::Whenever => MergeWheneverPossiblePolicy::new().as_box(),
+            MergePolicyConfig::NoMerge => Box::new(NoMergePolicy),
+            MergePolicyConfig::TargetDocs{ target_docs_per_segment, packing } => {
+                if *target_docs_per_segment == 0 {
+                    return Err(invalid_value("target_docs_per_segment must be positive".to_string()));
+                }
+
+                TargetDocsPerSegmentPolicy::new(*target_docs_per_segment)
+                    .with_packing(*packing)
+                    .as_box()
+            },
+            MergePolicyConfig::TargetBytes{ max_merged_segment_bytes, max_segments_per_merge } => {
+                if *max_merged_segment_bytes == 0 {
+                    return Err(invalid_value("max_merged_segment_bytes must be positive".to_string()));
+                }
+
+                if *max_segments_per_merge < 2 {
+                    return Err(invalid_value("max_segments_per_merge must be at least 2".to_string()));
+                }
+
+                TargetBytesPerSegmentPolicy::new(index.directory().box_clone(), *max_merged_segment_bytes)
+                    .with_max_segments_per_merge(*max_segments_per_merge)
+                    .as_box()
+            },
+            MergePolicyConfig::Tiered{ merge_factor, floor_segment_docs, max_merge_at_once, max_merged_segment_docs } => {
+                if *merge_factor < 2 {
+                    return Err(invalid_value("merge_factor must be at least 2".to_string()));
+                }
+
+                if *max_merge_at_once < 2 {
+                    return Err(invalid_value("max_merge_at_once must be at least 2".to_string()));
+                }
+
+                TieredMergePolicy::new()
+                    .with_merge_factor(*merge_factor)
+                    .with_floor_segment_docs(*floor_segment_docs)
+                    .with_max_merge_at_once(*max_merge_at_once)
+                    .with_max_merged_segment_docs(*max_merged_segment_docs)
+                    .as_box()
+            },
+            MergePolicyConfig::Log{
+                min_num_segments,
+                max_docs_before_merge,
+                min_layer_size,
+                level_log_size,
+                del_docs_ratio_before_merge,
+            } => {
+                let mut log_merge_policy = LogMergePolicy::default();
+
+                if let Some(min_num_segments) = min_num_segments {
+                    log_merge_policy.set_min_num_segments(*min_num_segments);
+                }
+
+                if let Some(max_docs_before_merge) = max_docs_before_merge {
+                    log_merge_policy.set_max_docs_before_merge(*max_docs_before_merge);
+                }
+
+                if let Some(min_layer_size) = min_layer_size {
+                    log_merge_policy.set_min_layer_size(*min_layer_size);
+                }
+
+                if let Some(level_log_size) = level_log_size {
+                    log_merge_policy.set_level_log_size(*level_log_size);
+                }
+
+                if let Some(del_docs_ratio_before_merge) = del_docs_ratio_before_merge {
+                    // LogMergePolicy asserts the same bounds
+                    if *del_docs_ratio_before_merge <= 0.0 || *del_docs_ratio_before_merge > 1.0 {
+                        return Err(invalid_value("del_docs_ratio_before_merge must be in (0, 1]".to_string()));
+                    }
+
+                    log_merge_policy.set_del_docs_ratio_before_merge(*del_docs_ratio_before_merge);
+                }
+
+                Box::new(log_merge_policy)
+            },
+            MergePolicyConfig::ExpungeDeletes{ inner, expunge_deletes_ratio, max_segments_per_merge } => {
+                if *expunge_deletes_ratio <= 0.0 || *expunge_deletes_ratio > 1.0 {
+                    return Err(invalid_value("expunge_deletes_ratio must be in (0, 1]".to_string()));
+                }
+
+                ExpungeDeletesMergePolicy::new(self.build_resolving(inner, index, resolving)?)
+                    .with_expunge_deletes_ratio(*expunge_deletes_ratio)
+                    .with_max_segments_per_merge(*max_segments_per_merge)
+                    .as_box()
+            },
+            MergePolicyConfig::Chain{ first, second } => {
+                Chain::new(
+                    self.build_resolving(first, index, resolving)?,
+                    self.build_resolving(second, index, resolving)?,
+                ).as_box()
+            },
+            MergePolicyConfig::Fallback{ primary, fallback } => {
+                Fallback::new(
+                    self.build_resolving(primary, index, resolving)?,
+                    self.build_resolving(fallback, index, resolving)?,
+                ).as_box()
+            },
+            MergePolicyConfig::Limit{ inner, max_candidates } => {
+                Limit::new(self.build_resolving(inner, index, resolving)?, *max_candidates).as_box()
+            },
+            MergePolicyConfig::Cooldown{ inner, min_interval_millis } => {
+                Cooldown::new(
+                    self.build_resolving(inner, index, resolving)?,
+                    Duration::from_millis(*min_interval_millis),
+                ).as_box()
+            },
+            MergePolicyConfig::Throttled{ inner, windows, utc_offset_minutes, budget } => {
+                let mut throttled = ThrottledMergePolicy::new(self.build_resolving(inner, index, resolving)?)
+                    .with_utc_offset_minutes(*utc_offset_minutes);
+
+                for window in windows.iter() {
+                    if window.start_minute >= 24 * 60 || window.end_minute > 24 * 60 {
+                        return Err(invalid_value(format!("throttle window {:?} out of the day", window)));
+                    }
+
+                    throttled = throttled.with_window(*window);
+                }
+
+                if let Some(budget) = budget {
+                    if budget.rate_per_second <= 0.0 || budget.burst <= 0.0 {
+                        return Err(invalid_value("merge budget rate and burst must be positive".to_string()));
+                    }
+
+                    if budget.unit == MergeBudgetUnit::Bytes {
+                        throttled = throttled.with_directory(index.directory().box_clone());
+                    }
+
+                    throttled = throttled.with_budget(*budget);
+                }
+
+                throttled.validate().map_err(invalid_value)?;
+                throttled.as_box()
+            },
+            MergePolicyConfig::Named(name) => {
+                if resolving.contains(name) {
+                    return Err(MergePolicyConfigError::CyclicPolicy(name.clone()));
+                }
+
+                let config = match self.policies.get(name) {
+                    Some(config) => config,
+                    None => {
+                        return Err(MergePolicyConfigError::UnknownPolicy(name.clone()));
+                    },
+                };
+
+                resolving.push(name.clone());
+                let merge_policy = self.build_resolving(config, index, resolving)?;
+                resolving.pop();
+
+                merge_policy
+            },
+        };
+
+        Ok(merge_policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use tantivy::schema::Schema;
+
+    use super::*;
+
+    fn index() -> Index {
+        Index::create_in_ram(Schema::builder().build())
+    }
+
+    fn tiered(merge_factor: usize, max_merge_at_once: usize) -> MergePolicyConfig {
+        MergePolicyConfig::Tiered{
+            merge_factor,
+            floor_segment_docs: DEFAULT_FLOOR_SEGMENT_DOCS,
+            max_merge_at_once,
+            max_merged_segment_docs: DEFAULT_MAX_MERGED_SEGMENT_DOCS,
+        }
+    }
+
+    fn nested() -> MergePolicyConfig {
+        MergePolicyConfig::Throttled{
+            inner: Box::new(MergePolicyConfig::Chain{
+                first: Box::new(MergePolicyConfig::ExpungeDeletes{
+                    inner: Box::new(MergePolicyConfig::Named("production".to_string())),
+                    expunge_deletes_ratio: 0.25,
+                    max_segments_per_merge: 4,
+                }),
+                second: Box::new(MergePolicyConfig::Limit{
+                    inner: Box::new(MergePolicyConfig::Cooldown{
+                        inner: Box::new(MergePolicyConfig::TargetDocs{
+                            target_docs_per_segment: 5000,
+                            packing: PackingStrategy::BestFit,
+                        }),
+                        min_interval_millis: 250,
+                    }),
+                    max_candidates: 2,
+                }),
+            }),
+            windows: vec![ThrottleWindow{
+                start_minute: 22 * 60,
+                end_minute: 6 * 60,
+                max_candidates: 0,
+            }],
+            utc_offset_minutes: 60,
+            budget: Some(MergeBudget{
+                unit: MergeBudgetUnit::Bytes,
+                rate_per_second: 1_000_000.0,
+                burst: 50_000_000.0,
+            }),
+        }
+    }
+
+    #[test]
+    fn short_forms_round_trip() {
+        let configs = vec![
+            MergePolicyConfig::Whenever,
+            MergePolicyConfig::NoMerge,
+            MergePolicyConfig::TargetDocs{
+                target_docs_per_segment: 2500,
+                packing: PackingStrategy::default(),
+            },
+            MergePolicyConfig::TargetBytes{
+                max_merged_segment_bytes: 1 << 30,
+                max_segments_per_merge: 6,
+            },
+            MergePolicyConfig::TargetDocs{
+                target_docs_per_segment: 2500,
+                packing: PackingStrategy::BestFit,
+            },
+            tiered(8, 12),
+            MergePolicyConfig::Log{
+                min_num_segments: None,
+                max_docs_before_merge: None,
+                min_layer_size: None,
+                level_log_size: None,
+                del_docs_ratio_before_merge: None,
+            },
+            MergePolicyConfig::Log{
+                min_num_segments: Some(8),
+                max_docs_before_merge: None,
+                min_layer_size: Some(1000),
+                level_log_size: Some(0.75),
+                del_docs_ratio_before_merge: Some(0.3),
+            },
+            MergePolicyConfig::Named("production".to_string()),
+        ];
+
+        for config in configs {
+            assert_eq!(config.to_string().parse::<MergePolicyConfig>().unwrap(), config, "{}", config);
+        }
+    }
+
+    #[test]
+    fn short_forms_fall_back_to_the_defaults() {
+        let parse = |spec: &str| spec.parse::<MergePolicyConfig>().unwrap();
+
+        assert_eq!(parse(" merge_whenever "), MergePolicyConfig::Whenever);
+        assert_eq!(parse("target_docs"), MergePolicyConfig::TargetDocs{
+            target_docs_per_segment: DEFAULT_TARGET_DOCS_PER_SEGMENT,
+            packing: PackingStrategy::default(),
+        });
+        assert_eq!(parse("target_docs:10_000"), MergePolicyConfig::TargetDocs{
+            target_docs_per_segment: 10000,
+            packing: PackingStrategy::default(),
+        });
+        assert_eq!(parse("target_bytes:1000"), MergePolicyConfig::TargetBytes{
+            max_merged_segment_bytes: 1000,
+            max_segments_per_merge: DEFAULT_MAX_SEGMENTS_PER_MERGE,
+        });
+        assert_eq!(parse("tiered::500"), MergePolicyConfig::Tiered{
+            merge_factor: DEFAULT_MERGE_FACTOR,
+            floor_segment_docs: 500,
+            max_merge_at_once: DEFAULT_MAX_MERGE_AT_ONCE,
+            max_merged_segment_docs: DEFAULT_MAX_MERGED_SEGMENT_DOCS,
+        });
+        assert_eq!(parse("production"), MergePolicyConfig::Named("production".to_string()));
+        assert_eq!(parse(r#"{"limit":{"inner":"whenever","max_candidates":1}}"#), MergePolicyConfig::Limit{
+            inner: Box::new(MergePolicyConfig::Whenever),
+            max_candidates: 1,
+        });
+    }
+
+    #[test]
+    fn rejects_invalid_short_forms() {
+        for spec in [
+            "",
+            "whenever:1",
+            "target_docs:1:2",
+            "target_docs:1:best_fit:2",
+            "target_docs:many",
+            "target_bytes",
+            "tiered:1:2:3:4:5",
+            "log:1:2:3:4:5:6",
+            "log::x",
+            "named:",
+            "{",
+        ] {
+            assert!(spec.parse::<MergePolicyConfig>().is_err(), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn display_tells_every_parameter_apart() {
+        let log = |min_num_segments: Option<usize>| MergePolicyConfig::Log{
+            min_num_segments,
+            max_docs_before_merge: None,
+            min_layer_size: None,
+            level_log_size: None,
+            del_docs_ratio_before_merge: None,
+        };
+        let throttled = |utc_offset_minutes: i32, max_candidates: usize, burst: f64| MergePolicyConfig::Throttled{
+            inner: Box::new(MergePolicyConfig::Whenever),
+            windows: vec![ThrottleWindow{
+                start_minute: 22 * 60,
+                end_minute: 6 * 60,
+                max_candidates,
+            }],
+            utc_offset_minutes,
+            budget: Some(MergeBudget{
+                unit: MergeBudgetUnit::Docs,
+                rate_per_second: 100.0,
+                burst,
+            }),
+        };
+        let expunge_deletes = |max_segments_per_merge: usize| MergePolicyConfig::ExpungeDeletes{
+            inner: Box::new(MergePolicyConfig::Whenever),
+            expunge_deletes_ratio: 0.3,
+            max_segments_per_merge,
+        };
+
+        assert_eq!(log(None).to_string(), "log");
+        assert_eq!(log(Some(4)).to_string(), "log:4::::");
+        assert_eq!(throttled(60, 1, 500.0).to_string(), "throttled:utc+60:22:00-06:00=1:docs=100~500(whenever)");
+        assert_eq!(
+            MergePolicyConfig::Throttled{
+                inner: Box::new(MergePolicyConfig::Whenever),
+                windows: vec![],
+                utc_offset_minutes: 0,
+                budget: None,
+            }.to_string(),
+            "throttled(whenever)",
+        );
+
+        let configs = [
+            log(None),
+            log(Some(4)),
+            log(Some(8)),
+            throttled(0, 1, 500.0),
+            throttled(60, 1, 500.0),
+            throttled(0, 2, 500.0),
+            throttled(0, 1, 1000.0),
+            expunge_deletes(4),
+            expunge_deletes(8),
+        ];
+        let names = configs
+            .iter()
+            .map(|config| config.to_string())
+            .collect::<HashSet<String>>();
+
+        assert_eq!(names.len(), configs.len(), "{:?}", names);
+        // The names end up in file names
+        assert!(names.iter().all(|name| !name.contains('/')), "{:?}", names);
+    }
+
+    #[test]
+    fn json_round_trips() {
+        for config in [MergePolicyConfig::Whenever, tiered(8, 12), nested()] {
+            let json = serde_json::to_string(&config).unwrap();
+
+            assert_eq!(MergePolicyConfig::from_json(&json).unwrap(), config, "{}", json);
+            assert_eq!(json.parse::<MergePolicyConfig>().unwrap(), config, "{}", json);
+        }
+    }
+
+    #[test]
+    fn toml_round_trips() {
+        for config in [tiered(8, 12), nested()] {
+            let toml = toml::to_string(&config).unwrap();
+
+            assert_eq!(MergePolicyConfig::from_toml(&toml).unwrap(), config, "{}", toml);
+        }
+
+        let config = MergePolicyConfig::from_toml("
+            [expunge_deletes]
+            expunge_deletes_ratio = 0.2
+            inner = { tiered = { merge_factor = 8 } }
+        ").unwrap();
+
+        assert_eq!(config, MergePolicyConfig::ExpungeDeletes{
+            inner: Box::new(tiered(8, DEFAULT_MAX_MERGE_AT_ONCE)),
+            expunge_deletes_ratio: 0.2,
+            max_segments_per_merge: DEFAULT_MAX_SEGMENTS_PER_MERGE,
+        });
+    }
+
+    #[test]
+    fn registry_resolves_named_policies() {
+        let mut registry = MergePolicyRegistry::default();
+        registry.extend_from_toml("
+            [production.tiered]
+            merge_factor = 8
+
+            [nightly.chain]
+            first = { named = \"production\" }
+            second = { target_docs = { target_docs_per_segment = 100000 } }
+        ").unwrap();
+        registry.extend_from_json(r#"{"careful": {"limit": {"inner": {"named": "nightly"}, "max_candidates": 1}}}"#).unwrap();
+
+        assert_eq!(registry.names(), vec!["careful", "log", "nightly", "no_merge", "production", "target_docs", "tiered", "whenever"]);
+        assert_eq!(registry.get("production"), Some(&tiered(8, DEFAULT_MAX_MERGE_AT_ONCE)));
+
+        let index = index();
+        for name in registry.names() {
+            assert!(registry.build_named(name, &index).is_ok(), "{}", name);
+        }
+        assert!(registry.build(&nested(), &index).is_ok());
+
+        // The registry itself round-trips
+        let toml = toml::to_string(&registry).unwrap();
+        assert_eq!(toml::from_str::<MergePolicyRegistry>(&toml).unwrap(), registry);
+    }
+
+    #[test]
+    fn named_cycles_are_errors() {
+        let registry = MergePolicyRegistry::default()
+            .with_policy("itself", MergePolicyConfig::Named("itself".to_string()))
+            .with_policy("a", MergePolicyConfig::Chain{
+                first: Box::new(MergePolicyConfig::Whenever),
+                second: Box::new(MergePolicyConfig::Named("b".to_string())),
+            })
+            .with_policy("b", MergePolicyConfig::Limit{
+                inner: Box::new(MergePolicyConfig::Named("a".to_string())),
+                max_candidates: 1,
+            })
+            // Referring to the same policy twice isn't a cycle
+            .with_policy("twice", MergePolicyConfig::Fallback{
+                primary: Box::new(MergePolicyConfig::Named("tiered".to_string())),
+                fallback: Box::new(MergePolicyConfig::Named("tiered".to_string())),
+            });
+
+        let index = index();
+
+        for (name, cyclic) in [("itself", "itself"), ("a", "a"), ("b", "b")] {
+            match registry.build_named(name, &index) {
+                Err(MergePolicyConfigError::CyclicPolicy(policy)) => assert_eq!(policy, cyclic),
+                other => panic!("{} built into {:?}", name, other),
+            }
+        }
+
+        assert!(registry.build_named("twice", &index).is_ok());
+        assert!(matches!(registry.build_named("missing", &index), Err(MergePolicyConfigError::UnknownPolicy(_))));
+    }
+
+    #[test]
+    fn build_validates_values() {
+        let index = index();
+        let invalid = [
+            MergePolicyConfig::TargetDocs{
+                target_docs_per_segment: 0,
+                packing: PackingStrategy::default(),
+            },
+            MergePolicyConfig::TargetBytes{
+                max_merged_segment_bytes: 1000,
+                max_segments_per_merge: 1,
+            },
+            tiered(1, 10),
+            tiered(10, 1),
+            MergePolicyConfig::Throttled{
+                inner: Box::new(MergePolicyConfig::Whenever),
+                windows: vec![],
+                utc_offset_minutes: 0,
+                budget: Some(MergeBudget{
+                    unit: MergeBudgetUnit::Docs,
+                    rate_per_second: 0.0,
+                    burst: 10.0,
+                }),
+            },
+        ];
+
+        for config in invalid.iter() {
+            assert!(matches!(config.build(&index), Err(MergePolicyConfigError::InvalidValue(_))), "{:?}", config);
+        }
+
+        assert!(tiered(10, 10).build(&index).is_ok());
+        // Groups are cut at max_merge_at_once when it's below merge_factor
+        assert!(tiered(10, 4).build(&index).is_ok());
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    fs,
+    io::{
+        self,
+        BufRead,
+        BufReader,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+        atomic::{
+            AtomicBool,
+            AtomicU64,
+            Ordering,
+        },
+    },
+    time::Instant,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use tantivy::{
+    index::SegmentMeta,
+    merge_policy::{
+        MergePolicy,
+        MergeCandidate,
+    },
+};
+
+// MergePolicyCall
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedSegment {
+    pub id: String,
+    pub num_docs: u32,
+    pub num_deleted_docs: u32,
+}
+
+impl From<&SegmentMeta> for RecordedSegment {
+    fn from(segment_meta: &SegmentMeta) -> Self {
+        RecordedSegment{
+            id: segment_meta.id().uuid_string(),
+            num_docs: segment_meta.num_docs(),
+            num_deleted_docs: segment_meta.num_deleted_docs(),
+        }
+    }
+}
+
+/// Single `compute_merge_candidates` invocation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MergePolicyCall {
+    /// Microseconds since the recorder was created
+    pub timestamp_micros: u64,
+    /// Number of commits finished before the call started
+    #[serde(default)]
+    pub commit: u64,
+    pub segments: Vec<RecordedSegment>,
+    /// Segment ids of every returned merge candidate
+    pub candidates: Vec<Vec<String>>,
+    pub elapsed_micros: u64,
+}
+
+// MergePolicyRecorder
+
+/// Thread-safe buffer of merge policy calls, shared between the policy (owned by
+/// the writer) and whoever inspects the calls afterwards
+#[derive(Debug, Clone)]
+pub struct MergePolicyRecorder {
+    started_at: Instant,
+    commits: Arc<AtomicU64>,
+    stopped: Arc<AtomicBool>,
+    calls: Arc<Mutex<Vec<MergePolicyCall>>>,
+}
+
+impl Default for MergePolicyRecorder {
+    fn default() -> Self {
+        MergePolicyRecorder::new()
+    }
+}
+
+impl MergePolicyRecorder {
+    pub fn new() -> Self {
+        MergePolicyRecorder{
+            started_at: Instant::now(),
+            commits: Arc::new(AtomicU64::new(0)),
+            stopped: Arc::new(AtomicBool::new(false)),
+            calls: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<MergePolicyCall>> {
+        // A panicking merge policy must not take the recorded calls down with it
+        match self.calls.lock() {
+            Ok(calls) => calls,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn record(&self, call: MergePolicyCall) {
+        self.lock().push(call);
+    }
+
+    /// Marks the end of a commit, calls recorded afterwards belong to the next one
+    pub fn mark_commit(&self) {
+        self.commits.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn commits(&self) -> u64 {
+        self.commits.load(Ordering::SeqCst)
+    }
+
+    /// Makes the recording policy return no candidates from now on, without calling
+    /// the inner policy or recording the call, so that the writer runs out of merges
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn last(&self) -> Option<MergePolicyCall> {
+        self.lock().last().cloned()
+    }
+
+    /// Copy of all the calls recorded so far
+    pub fn calls(&self) -> Vec<MergePolicyCall> {
+        self.lock().clone()
+    }
+
+    /// Writes the calls recorded so far as JSON lines
+    pub fn write_jsonl<T: AsRef<Path>>(&self, path: T) -> Result<(), io::Error> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+
+        for call in self.calls() {
+            serde_json::to_writer(&mut writer, &call)?;
+            writer.write_all(b"\n")?;
+        }
+
+        writer.flush()
+    }
+}
+
+/// Reads calls previously written by `MergePolicyRecorder::write_jsonl`, empty lines are skipped
+pub fn read_merge_policy_calls<T: AsRef<Path>>(path: T) -> Result<Vec<MergePolicyCall>, io::Error> {
+    let reader = BufReader::new(fs::File::open(path)?);
+
+    let mut calls: Vec<MergePolicyCall> = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<MergePolicyCall>(&line) {
+            Ok(call) => calls.push(call),
+            Err(err) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid merge policy call on line {}: {}", index + 1, err),
+                ));
+            },
+        }
+    }
+
+    Ok(calls)
+}
+
+// RecordingMergePolicy
+
+#[derive(Debug)]
+pub struct RecordingMergePolicy<P: MergePolicy + ?Sized> {
+    recorder: MergePolicyRecorder,
+    inner: Box<P>,
+}
+
+impl<P: MergePolicy + ?Sized> RecordingMergePolicy<P> {
+    pub fn new(inner: Box<P>, recorder: MergePolicyRecorder) -> Self {
+        RecordingMergePolicy{
+            recorder,
+            inner,
+        }
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+}
+
+impl<P: MergePolicy + ?Sized> MergePolicy for RecordingMergePolicy<P> {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        if self.recorder.is_stopped() {
+            return Vec::new();
+        }
+
+        let start_instant = Instant::now();
+        let merge_candidates = self.inner.compute_merge_candidates(segment_metas);
+        let elapsed = start_instant.elapsed();
+
+        self.recorder.record(MergePolicyCall{
+            timestamp_micros: start_instant.duration_since(self.recorder.started_at).as_micros() as u64,
+            commit: self.recorder.commits(),
+            segments: segment_metas
+                .iter()
+                .map(RecordedSegment::from)
+                .collect::<Vec<RecordedSegment>>(),
+            candidates: merge_candidates
+                .iter()
+                .map(|merge_candidate| {
+                    merge_candidate.0
+                        .iter()
+                        .map(|segment_id| segment_id.uuid_string())
+                        .collect::<Vec<String>>()
+                })
+                .collect::<Vec<Vec<String>>>(),
+            elapsed_micros: elapsed.as_micros() as u64,
+        });
+
+        merge_candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_policies::{
+        FixedPolicy,
+        segment_id,
+        segment_metas,
+        candidate_ids,
+    };
+
+    #[test]
+    fn stopped_policy_returns_no_candidates() {
+        let inner = FixedPolicy::new(vec![vec![segment_id(1), segment_id(2)]]);
+        let recorder = MergePolicyRecorder::new();
+        let policy = RecordingMergePolicy::new(inner.as_box(), recorder.clone());
+        let segment_metas = segment_metas(&[(10, 0), (10, 0)]);
+
+        assert_eq!(candidate_ids(&policy.compute_merge_candidates(&segment_metas)), vec![vec![segment_id(1), segment_id(2)]]);
+
+        recorder.stop();
+
+        assert!(recorder.is_stopped());
+        assert!(policy.compute_merge_candidates(&segment_metas).is_empty());
+        // Neither the inner policy nor the recorder see the calls once stopped
+        assert_eq!(inner.calls().len(), 1);
+        assert_eq!(recorder.len(), 1);
+    }
+
+    #[test]
+    fn records_calls_in_order_with_their_commit() {
+        let inner = FixedPolicy::new(vec![vec![segment_id(2), segment_id(3)]]);
+        let recorder = MergePolicyRecorder::new();
+        let policy = RecordingMergePolicy::new(inner.as_box(), recorder.clone());
+
+        policy.compute_merge_candidates(&segment_metas(&[(10, 0)]));
+        recorder.mark_commit();
+        policy.compute_merge_candidates(&segment_metas(&[(10, 0), (20, 5), (30, 0)]));
+        policy.compute_merge_candidates(&segment_metas(&[(10, 0), (20, 5)]));
+        recorder.mark_commit();
+        recorder.mark_commit();
+        policy.compute_merge_candidates(&[]);
+
+        let calls = recorder.calls();
+
+        assert_eq!(recorder.commits(), 3);
+        assert_eq!(calls.iter().map(|call| call.commit).collect::<Vec<u64>>(), vec![0, 1, 1, 3]);
+        assert_eq!(calls.iter().map(|call| call.segments.len()).collect::<Vec<usize>>(), vec![1, 3, 2, 0]);
+        assert!(calls.windows(2).all(|pair| pair[0].timestamp_micros <= pair[1].timestamp_micros));
+
+        assert_eq!(calls[1].segments[1], RecordedSegment{
+            id: segment_id(2).uuid_string(),
+            num_docs: 15,
+            num_deleted_docs: 5,
+        });
+        for call in &calls {
+            assert_eq!(call.candidates, vec![vec![segment_id(2).uuid_string(), segment_id(3).uuid_string()]]);
+        }
+        assert_eq!(inner.calls().len(), 4);
+    }
+
+    #[test]
+    fn calls_are_a_snapshot() {
+        let recorder = MergePolicyRecorder::new();
+        let policy = RecordingMergePolicy::new(FixedPolicy::default().as_box(), recorder.clone());
+
+        policy.compute_merge_candidates(&segment_metas(&[(10, 0)]));
+        let snapshot = recorder.calls();
+
+        policy.compute_merge_candidates(&segment_metas(&[(10, 0)]));
+
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(recorder.len(), 2);
+        assert_eq!(recorder.last().unwrap().timestamp_micros, recorder.calls()[1].timestamp_micros);
+        assert!(snapshot[0].candidates.is_empty());
+    }
+
+    #[test]
+    fn written_calls_read_back() {
+        let recorder = MergePolicyRecorder::new();
+        let policy = RecordingMergePolicy::new(FixedPolicy::new(vec![vec![segment_id(1)]]).as_box(), recorder.clone());
+
+        policy.compute_merge_candidates(&segment_metas(&[(10, 2)]));
+        recorder.mark_commit();
+        policy.compute_merge_candidates(&segment_metas(&[(10, 2), (5, 0)]));
+
+        let path = std::env::temp_dir().join(format!("merge-policy-recorder-{}.jsonl", std::process::id()));
+        recorder.write_jsonl(&path).unwrap();
+        let calls = read_merge_policy_calls(&path);
+        let _ = fs::remove_file(&path);
+
+        let calls = calls.unwrap();
+        assert_eq!(calls.len(), 2);
+        for (read, recorded) in calls.iter().zip(recorder.calls()) {
+            assert_eq!(read.commit, recorded.commit);
+            assert_eq!(read.segments, recorded.segments);
+            assert_eq!(read.candidates, recorded.candidates);
+            assert_eq!(read.timestamp_micros, recorded.timestamp_micros);
+        }
+    }
+}
@@ -0,0 +1,538 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::Mutex,
+    time::{
+        Instant,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+use serde::{
+    Serialize,
+    Deserialize,
+    Deserializer,
+    de,
+};
+use tantivy::{
+    directory::Directory,
+    index::SegmentMeta,
+    merge_policy::{
+        MergePolicy,
+        MergeCandidate,
+    },
+};
+
+use super::merge_policy::segment_file_bytes;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+// ThrottleWindow
+
+/// Daily time window limiting the number of merges started per call, `max_candidates`
+/// of `0` suppressing merges altogether.
+///
+/// Deserializes from its fields or from a `HH:MM-HH:MM[=<max_candidates>]` spec.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThrottleWindow {
+    /// Minute of the day the window starts at, inclusive
+    pub start_minute: u32,
+    /// Minute of the day the window ends at, exclusive, windows may span midnight
+    pub end_minute: u32,
+    pub max_candidates: usize,
+}
+
+fn parse_minute_of_day(value: &str) -> Result<u32, String> {
+    let invalid = || format!("Invalid time {:?}, expected HH:MM", value.trim());
+
+    let (hours, minutes) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let hours = hours.parse::<u32>().map_err(|_| invalid())?;
+    let minutes = minutes.parse::<u32>().map_err(|_| invalid())?;
+
+    if hours > 24 || minutes > 59 || (hours == 24 && minutes > 0) {
+        return Err(invalid());
+    }
+
+    Ok(hours * 60 + minutes)
+}
+
+impl ThrottleWindow {
+    /// Parses `HH:MM-HH:MM`, e.g. `08:00-18:00`
+    pub fn parse(value: &str, max_candidates: usize) -> Result<ThrottleWindow, String> {
+        let (start, end) = match value.split_once('-') {
+            Some(times) => times,
+            None => {
+                return Err(format!("Invalid window {:?}, expected HH:MM-HH:MM", value));
+            },
+        };
+
+        Ok(ThrottleWindow{
+            start_minute: parse_minute_of_day(start)?,
+            end_minute: parse_minute_of_day(end)?,
+            max_candidates,
+        })
+    }
+
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        if self.start_minute <= self.end_minute {
+            self.start_minute <= minute_of_day && minute_of_day < self.end_minute
+        } else {
+            minute_of_day >= self.start_minute || minute_of_day < self.end_minute
+        }
+    }
+}
+
+impl fmt::Display for ThrottleWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}={}",
+            self.start_minute / 60,
+            self.start_minute % 60,
+            self.end_minute / 60,
+            self.end_minute % 60,
+            self.max_candidates,
+        )
+    }
+}
+
+impl FromStr for ThrottleWindow {
+    type Err = String;
+
+    /// Parses `HH:MM-HH:MM[=<max_candidates>]`, e.g. `22:00-06:00=1`, merges being
+    /// suppressed when the limit is left out
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (times, max_candidates) = match spec.split_once('=') {
+            Some((times, max_candidates)) => match max_candidates.trim().parse::<usize>() {
+                Ok(max_candidates) => (times, max_candidates),
+                Err(_) => {
+                    return Err(format!("Invalid max candidates {:?} of window {:?}", max_candidates, spec));
+                },
+            },
+            None => (spec, 0),
+        };
+
+        ThrottleWindow::parse(times, max_candidates)
+    }
+}
+
+impl<'de> Deserialize<'de> for ThrottleWindow {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum WindowValue {
+            Spec(String),
+            #[serde(rename_all = "camelCase")]
+            Fields {
+                start_minute: u32,
+                end_minute: u32,
+                max_candidates: usize,
+            },
+        }
+
+        match WindowValue::deserialize(deserializer)? {
+            WindowValue::Spec(spec) => spec.parse::<ThrottleWindow>().map_err(de::Error::custom),
+            WindowValue::Fields{ start_minute, end_minute, max_candidates } => Ok(ThrottleWindow{
+                start_minute,
+                end_minute,
+                max_candidates,
+            }),
+        }
+    }
+}
+
+// MergeBudget
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeBudgetUnit {
+    /// Docs (deletes excluded) of the merged segments
+    Docs,
+    /// Size of the merged segment files
+    Bytes,
+}
+
+/// Token bucket refilled with `rate_per_second` units up to `burst`, every started
+/// merge taking as many tokens as docs or bytes it rewrites
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeBudget {
+    pub unit: MergeBudgetUnit,
+    pub rate_per_second: f64,
+    pub burst: f64,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, budget: &MergeBudget, now: Instant) {
+        let refill = now.saturating_duration_since(self.refilled_at).as_secs_f64() * budget.rate_per_second;
+        self.tokens = (self.tokens + refill).min(budget.burst);
+        self.refilled_at = now;
+    }
+}
+
+// ThrottledMergePolicy
+
+/// Limits the merges of the inner policy during the configured daily windows and
+/// to the merge budget.
+///
+/// Tantivy only asks for merges on commits and finished merges, candidates dropped
+/// here are therefore only reconsidered on the next such event.
+pub struct ThrottledMergePolicy<P: MergePolicy + ?Sized> {
+    windows: Vec<ThrottleWindow>,
+    utc_offset_minutes: i32,
+    budget: Option<MergeBudget>,
+    bucket: Mutex<TokenBucket>,
+    directory: Option<Box<dyn Directory>>,
+    inner: Box<P>,
+}
+
+impl<P: MergePolicy + ?Sized> ThrottledMergePolicy<P> {
+    pub fn new(inner: Box<P>) -> Self {
+        ThrottledMergePolicy{
+            windows: Vec::new(),
+            utc_offset_minutes: 0,
+            budget: None,
+            bucket: Mutex::new(TokenBucket{
+                tokens: 0.0,
+                refilled_at: Instant::now(),
+            }),
+            directory: None,
+            inner,
+        }
+    }
+
+    pub fn with_window(mut self, window: ThrottleWindow) -> Self {
+        self.windows.push(window);
+        self
+    }
+
+    /// Offset of the local time the windows are expressed in, UTC by default
+    pub fn with_utc_offset_minutes(mut self, utc_offset_minutes: i32) -> Self {
+        self.utc_offset_minutes = utc_offset_minutes;
+        self
+    }
+
+    /// The bucket starts full. A budget in `MergeBudgetUnit::Bytes` also needs
+    /// `with_directory`, see `validate`.
+    pub fn with_budget(mut self, budget: MergeBudget) -> Self {
+        self.budget = Some(budget);
+        self.bucket = Mutex::new(TokenBucket{
+            tokens: budget.burst,
+            refilled_at: Instant::now(),
+        });
+        self
+    }
+
+    pub fn with_directory(mut self, directory: Box<dyn Directory>) -> Self {
+        self.directory = Some(directory);
+        self
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+
+    /// Checks a budget in bytes has the directory of the index to measure the merges
+    /// with, without it the budget would never throttle
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.budget, self.directory.as_ref()) {
+            (Some(MergeBudget{ unit: MergeBudgetUnit::Bytes, .. }), None) => {
+                Err("A merge budget in bytes requires the directory of the index".to_string())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Local minute of the day at `seconds` since the Unix epoch
+    fn minute_of_day(&self, seconds: i64) -> u32 {
+        let minutes = seconds.div_euclid(60) + self.utc_offset_minutes as i64;
+        minutes.rem_euclid(MINUTES_PER_DAY as i64) as u32
+    }
+
+    /// Smallest limit of the windows in effect at `minute_of_day`
+    fn window_limit(&self, minute_of_day: u32) -> Option<usize> {
+        self.windows
+            .iter()
+            .filter(|window| window.contains(minute_of_day))
+            .map(|window| window.max_candidates)
+            .min()
+    }
+
+    fn merge_cost(&self, unit: MergeBudgetUnit, merge_candidate: &MergeCandidate, segment_metas: &[SegmentMeta]) -> f64 {
+        segment_metas
+            .iter()
+            .filter(|segment_meta| merge_candidate.0.contains(&segment_meta.id()))
+            .map(|segment_meta| match (unit, self.directory.as_ref()) {
+                (MergeBudgetUnit::Docs, _) => segment_meta.num_docs() as u64,
+                (MergeBudgetUnit::Bytes, Some(directory)) => segment_file_bytes(directory.as_ref(), segment_meta),
+                // Ruled out by `validate`
+                (MergeBudgetUnit::Bytes, None) => 0,
+            })
+            .sum::<u64>() as f64
+    }
+}
+
+impl<P: MergePolicy + ?Sized> fmt::Debug for ThrottledMergePolicy<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThrottledMergePolicy")
+            .field("windows", &self.windows)
+            .field("utc_offset_minutes", &self.utc_offset_minutes)
+            .field("budget", &self.budget)
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: MergePolicy + ?Sized> MergePolicy for ThrottledMergePolicy<P> {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let seconds = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(_) => 0,
+        };
+
+        let window_limit = self.window_limit(self.minute_of_day(seconds));
+        if window_limit == Some(0) {
+            return vec![];
+        }
+
+        let mut merge_candidates = self.inner.compute_merge_candidates(segment_metas);
+
+        if let Some(window_limit) = window_limit {
+            merge_candidates.truncate(window_limit);
+        }
+
+        let budget = match self.budget {
+            Some(budget) => budget,
+            None => {
+                return merge_candidates;
+            },
+        };
+
+        let mut bucket = match self.bucket.lock() {
+            Ok(bucket) => bucket,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        bucket.refill(&budget, Instant::now());
+
+        let mut allowed: Vec<MergeCandidate> = Vec::new();
+
+        for merge_candidate in merge_candidates {
+            let cost = self.merge_cost(budget.unit, &merge_candidate, segment_metas);
+
+            // Merges bigger than the whole bucket still get through once it's full
+            let affordable = cost <= bucket.tokens || bucket.tokens >= budget.burst;
+            if !affordable {
+                break;
+            }
+
+            bucket.tokens = (bucket.tokens - cost).max(0.0);
+            allowed.push(merge_candidate);
+        }
+
+        allowed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tantivy::directory::RamDirectory;
+
+    use super::*;
+    use super::super::test_policies::{
+        FixedPolicy,
+        segment_id,
+        segment_metas,
+    };
+
+    fn minute(value: &str) -> u32 {
+        parse_minute_of_day(value).unwrap()
+    }
+
+    #[test]
+    fn parses_windows() {
+        assert_eq!(ThrottleWindow::parse("08:00-18:30", 2), Ok(ThrottleWindow{
+            start_minute: 8 * 60,
+            end_minute: 18 * 60 + 30,
+            max_candidates: 2,
+        }));
+        assert_eq!(ThrottleWindow::parse(" 22:00 - 24:00 ", 0).map(|window| window.end_minute), Ok(MINUTES_PER_DAY));
+
+        for value in ["08:00", "8-18", "25:00-26:00", "08:60-09:00", "24:01-01:00", "aa:00-01:00"] {
+            assert!(ThrottleWindow::parse(value, 0).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn window_specs_round_trip() {
+        let window = "22:00-06:30=2".parse::<ThrottleWindow>().unwrap();
+
+        assert_eq!(window, ThrottleWindow{
+            start_minute: 22 * 60,
+            end_minute: 6 * 60 + 30,
+            max_candidates: 2,
+        });
+        assert_eq!(window.to_string().parse::<ThrottleWindow>(), Ok(window));
+        assert_eq!("08:00-18:00".parse::<ThrottleWindow>().map(|window| window.max_candidates), Ok(0));
+
+        for spec in ["08:00-18:00=", "08:00-18:00=x", "08:00=1", "08:00-25:00=1"] {
+            assert!(spec.parse::<ThrottleWindow>().is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn deserializes_windows_from_fields_or_specs() {
+        let windows: Vec<ThrottleWindow> = serde_json::from_str(
+            r#"["22:00-06:00=1", {"startMinute": 480, "endMinute": 1080, "maxCandidates": 0}]"#,
+        ).unwrap();
+
+        assert_eq!(windows, vec![
+            ThrottleWindow::parse("22:00-06:00", 1).unwrap(),
+            ThrottleWindow::parse("08:00-18:00", 0).unwrap(),
+        ]);
+
+        // An invalid time is an error rather than a panic
+        let err = serde_json::from_str::<ThrottleWindow>(r#""25:00-06:00""#).unwrap_err();
+        assert!(err.to_string().contains("Invalid time \"25:00\""), "{}", err);
+    }
+
+    #[test]
+    fn windows_wrap_around_midnight() {
+        let day = ThrottleWindow::parse("08:00-18:00", 0).unwrap();
+        let night = ThrottleWindow::parse("22:00-06:00", 0).unwrap();
+
+        for (time, in_day, in_night) in [
+            ("00:00", false, true),
+            ("05:59", false, true),
+            ("06:00", false, false),
+            ("08:00", true, false),
+            ("17:59", true, false),
+            ("18:00", false, false),
+            ("21:59", false, false),
+            ("22:00", false, true),
+            ("23:59", false, true),
+        ] {
+            assert_eq!(day.contains(minute(time)), in_day, "{}", time);
+            assert_eq!(night.contains(minute(time)), in_night, "{}", time);
+        }
+    }
+
+    #[test]
+    fn minute_of_day_applies_the_utc_offset() {
+        // 2024-01-01T23:30:45Z
+        let seconds = 1_704_151_845;
+        let at_offset = |utc_offset_minutes: i32| {
+            ThrottledMergePolicy::new(FixedPolicy::new(vec![]).as_box())
+                .with_utc_offset_minutes(utc_offset_minutes)
+                .minute_of_day(seconds)
+        };
+
+        assert_eq!(at_offset(0), minute("23:30"));
+        assert_eq!(at_offset(60), minute("00:30"));
+        assert_eq!(at_offset(-90), minute("22:00"));
+        assert_eq!(at_offset(-24 * 60), minute("23:30"));
+        assert_eq!(at_offset(13 * 60 + 45), minute("13:15"));
+    }
+
+    #[test]
+    fn smallest_window_limit_wins() {
+        let policy = ThrottledMergePolicy::new(FixedPolicy::new(vec![]).as_box())
+            .with_window(ThrottleWindow::parse("20:00-04:00", 3).unwrap())
+            .with_window(ThrottleWindow::parse("23:00-01:00", 1).unwrap());
+
+        assert_eq!(policy.window_limit(minute("12:00")), None);
+        assert_eq!(policy.window_limit(minute("21:00")), Some(3));
+        assert_eq!(policy.window_limit(minute("00:30")), Some(1));
+        assert_eq!(policy.window_limit(minute("03:59")), Some(3));
+    }
+
+    #[test]
+    fn token_bucket_refills_up_to_the_burst() {
+        let budget = MergeBudget{
+            unit: MergeBudgetUnit::Docs,
+            rate_per_second: 100.0,
+            burst: 500.0,
+        };
+        let start = Instant::now();
+        let mut bucket = TokenBucket{
+            tokens: 0.0,
+            refilled_at: start,
+        };
+
+        bucket.refill(&budget, start + Duration::from_millis(1500));
+        assert_eq!(bucket.tokens, 150.0);
+
+        bucket.tokens -= 100.0;
+        bucket.refill(&budget, start + Duration::from_secs(2));
+        assert_eq!(bucket.tokens, 100.0);
+
+        bucket.refill(&budget, start + Duration::from_secs(60));
+        assert_eq!(bucket.tokens, 500.0);
+
+        // A clock going backwards doesn't take tokens away
+        bucket.refill(&budget, start);
+        assert_eq!(bucket.tokens, 500.0);
+    }
+
+    #[test]
+    fn budget_drops_candidates_it_cannot_afford() {
+        let segments = segment_metas(&[(30, 0), (30, 10), (40, 0), (40, 0), (100, 0), (100, 0)]);
+        let inner = FixedPolicy::new(vec![
+            vec![segment_id(1), segment_id(2)],
+            vec![segment_id(3), segment_id(4)],
+            vec![segment_id(5), segment_id(6)],
+        ]);
+        // Next to no refill while the test runs
+        let policy = ThrottledMergePolicy::new(inner.as_box()).with_budget(MergeBudget{
+            unit: MergeBudgetUnit::Docs,
+            rate_per_second: 0.001,
+            burst: 150.0,
+        });
+
+        // 50 live docs, then 80, leaving 20 tokens
+        assert_eq!(policy.compute_merge_candidates(&segments).len(), 2);
+        assert!(policy.compute_merge_candidates(&segments).is_empty());
+
+        // Merges bigger than the burst still get through once the bucket is full
+        let policy = ThrottledMergePolicy::new(FixedPolicy::new(vec![vec![segment_id(5), segment_id(6)]]).as_box())
+            .with_budget(MergeBudget{
+                unit: MergeBudgetUnit::Docs,
+                rate_per_second: 0.001,
+                burst: 150.0,
+            });
+
+        assert_eq!(policy.compute_merge_candidates(&segments).len(), 1);
+        assert!(policy.compute_merge_candidates(&segments).is_empty());
+    }
+
+    #[test]
+    fn budget_in_bytes_requires_a_directory() {
+        let budget = MergeBudget{
+            unit: MergeBudgetUnit::Bytes,
+            rate_per_second: 1.0,
+            burst: 1.0,
+        };
+        let policy = || ThrottledMergePolicy::new(FixedPolicy::new(vec![]).as_box());
+
+        // In either order
+        assert!(policy().with_directory(Box::new(RamDirectory::create())).with_budget(budget).validate().is_ok());
+        assert!(policy().with_budget(budget).with_directory(Box::new(RamDirectory::create())).validate().is_ok());
+
+        let err = policy().with_budget(budget).validate().unwrap_err();
+        assert!(err.contains("requires the directory"), "{}", err);
+
+        assert!(policy().with_budget(MergeBudget{
+            unit: MergeBudgetUnit::Docs,
+            ..budget
+        }).validate().is_ok());
+    }
+}
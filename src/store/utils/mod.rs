@@ -9,5 +9,69 @@ pub use index_value::{
 mod merge_policy;
 pub use merge_policy::{
     TargetDocsPerSegmentPolicy,
+    DEFAULT_TARGET_DOCS_PER_SEGMENT,
+    PackingStrategy,
+    TargetBytesPerSegmentPolicy,
+    segment_file_bytes,
+    DEFAULT_MAX_SEGMENTS_PER_MERGE,
+    TieredMergePolicy,
+    DEFAULT_MERGE_FACTOR,
+    DEFAULT_FLOOR_SEGMENT_DOCS,
+    DEFAULT_MAX_MERGE_AT_ONCE,
+    DEFAULT_MAX_MERGED_SEGMENT_DOCS,
+    ExpungeDeletesMergePolicy,
+    DEFAULT_EXPUNGE_DELETES_RATIO,
+    deleted_docs_ratio,
     MergeWheneverPossiblePolicy,
 };
+
+mod segment_inventory;
+pub use segment_inventory::{
+    SegmentInventory,
+    InventorySegment,
+    SegmentComponentBytes,
+    OrphanFile,
+};
+
+mod merge_policy_recorder;
+pub use merge_policy_recorder::{
+    RecordingMergePolicy,
+    MergePolicyRecorder,
+    MergePolicyCall,
+    RecordedSegment,
+    read_merge_policy_calls,
+};
+
+mod validating_merge_policy;
+pub use validating_merge_policy::{
+    ValidatingMergePolicy,
+    MergePolicyValidation,
+    MergePolicyFindings,
+};
+
+mod merge_policy_combinators;
+pub use merge_policy_combinators::{
+    Chain,
+    Fallback,
+    Filter,
+    Limit,
+    Cooldown,
+};
+
+mod merge_policy_throttle;
+pub use merge_policy_throttle::{
+    ThrottledMergePolicy,
+    ThrottleWindow,
+    MergeBudget,
+    MergeBudgetUnit,
+};
+
+mod merge_policy_config;
+pub use merge_policy_config::{
+    MergePolicyConfig,
+    MergePolicyConfigError,
+    MergePolicyRegistry,
+};
+
+#[cfg(test)]
+mod test_policies;
@@ -0,0 +1,404 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use tantivy::{
+    HasLen,
+    Index,
+    TantivyError,
+    directory::Directory,
+    index::{
+        SegmentComponent,
+        SegmentMeta,
+    },
+};
+
+/// Files of the index itself rather than of a segment
+const INDEX_FILES: [&str; 2] = ["meta.json", ".managed.json"];
+
+// SegmentComponentBytes
+
+/// Bytes of every component file of a segment, `0` when the file doesn't exist
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentComponentBytes {
+    /// `.idx`
+    pub postings: u64,
+    /// `.pos`
+    pub positions: u64,
+    /// `.fast`
+    pub fast_fields: u64,
+    /// `.fieldnorm`
+    pub field_norms: u64,
+    /// `.term`
+    pub terms: u64,
+    /// `.store`
+    pub store: u64,
+    /// `.store.temp`, only present while the segment is being written
+    pub temp_store: u64,
+    /// `.<opstamp>.del`
+    pub delete: u64,
+}
+
+impl SegmentComponentBytes {
+    pub fn total(&self) -> u64 {
+        self.postings
+            + self.positions
+            + self.fast_fields
+            + self.field_norms
+            + self.terms
+            + self.store
+            + self.temp_store
+            + self.delete
+    }
+
+    fn component_mut(&mut self, component: SegmentComponent) -> &mut u64 {
+        match component {
+            SegmentComponent::Postings => &mut self.postings,
+            SegmentComponent::Positions => &mut self.positions,
+            SegmentComponent::FastFields => &mut self.fast_fields,
+            SegmentComponent::FieldNorms => &mut self.field_norms,
+            SegmentComponent::Terms => &mut self.terms,
+            SegmentComponent::Store => &mut self.store,
+            SegmentComponent::TempStore => &mut self.temp_store,
+            SegmentComponent::Delete => &mut self.delete,
+        }
+    }
+}
+
+// SegmentInventory
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InventorySegment {
+    pub id: String,
+    pub num_docs: u32,
+    pub num_deleted_docs: u32,
+    /// Opstamp of the `.del` file, `None` without deletes
+    pub delete_opstamp: Option<u64>,
+    pub component_bytes: SegmentComponentBytes,
+    /// Component files of the segment, as listed by its meta, which exist
+    pub files: Vec<PathBuf>,
+}
+
+/// File in the index directory which doesn't belong to any live segment
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Tracked by tantivy and therefore due to be garbage collected
+    pub managed: bool,
+}
+
+/// Live segments of the last commit, read from the `IndexMeta` of `meta.json`,
+/// along with the files left around them
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentInventory {
+    pub segments: Vec<InventorySegment>,
+    pub orphan_files: Vec<OrphanFile>,
+}
+
+/// Size on disk when the index has a directory, otherwise the size tantivy reads,
+/// i.e. without the footer. Files not written by tantivy are only sized on disk as
+/// opening them through the index directory means parsing a footer they don't have.
+fn file_bytes(directory: &dyn Directory, index_path: Option<&Path>, path: &Path) -> Option<u64> {
+    match index_path {
+        Some(index_path) => match fs::metadata(index_path.join(path)) {
+            Ok(metadata) => Some(metadata.len()),
+            Err(_) => None,
+        },
+        None => match directory.open_read(path) {
+            Ok(file_slice) => Some(file_slice.len() as u64),
+            Err(_) => None,
+        },
+    }
+}
+
+fn inventory_segment(directory: &dyn Directory, index_path: Option<&Path>, segment_meta: &SegmentMeta) -> InventorySegment {
+    let mut component_bytes = SegmentComponentBytes::default();
+    let mut files: Vec<PathBuf> = Vec::new();
+
+    for component in SegmentComponent::iterator() {
+        // Without deletes the path of the `.del` file is made up, it never exists
+        if *component == SegmentComponent::Delete && segment_meta.delete_opstamp().is_none() {
+            continue;
+        }
+
+        let path = segment_meta.relative_path(*component);
+
+        if let Some(bytes) = file_bytes(directory, index_path, &path) {
+            *component_bytes.component_mut(*component) = bytes;
+            files.push(path);
+        }
+    }
+
+    InventorySegment{
+        id: segment_meta.id().uuid_string(),
+        num_docs: segment_meta.num_docs(),
+        num_deleted_docs: segment_meta.num_deleted_docs(),
+        delete_opstamp: segment_meta.delete_opstamp(),
+        component_bytes,
+        files,
+    }
+}
+
+fn is_index_file(path: &Path) -> bool {
+    match path.file_name().and_then(|file_name| file_name.to_str()) {
+        Some(file_name) => {
+            INDEX_FILES.contains(&file_name) || (file_name.starts_with(".tantivy-") && file_name.ends_with(".lock"))
+        },
+        None => false,
+    }
+}
+
+impl SegmentInventory {
+    /// Reads the live segments of `index`. Files tracked by tantivy but not part of
+    /// them are reported as orphans, as are the untracked files of `index_path`, the
+    /// directory of the index when on disk.
+    pub fn read(index: &Index, index_path: Option<&Path>) -> Result<SegmentInventory, TantivyError> {
+        let directory = index.directory();
+
+        let segments = index
+            .searchable_segment_metas()?
+            .iter()
+            .map(|segment_meta| inventory_segment(directory, index_path, segment_meta))
+            .collect::<Vec<InventorySegment>>();
+
+        let live_files = segments
+            .iter()
+            .flat_map(|segment| segment.files.iter().cloned())
+            .collect::<BTreeSet<PathBuf>>();
+
+        let managed_files = directory
+            .list_managed_files()
+            .into_iter()
+            .collect::<BTreeSet<PathBuf>>();
+
+        let mut files = managed_files.clone();
+
+        if let Some(index_path) = index_path {
+            for entry in fs::read_dir(index_path)? {
+                let entry = entry?;
+
+                if entry.file_type()?.is_file() {
+                    files.insert(PathBuf::from(entry.file_name()));
+                }
+            }
+        }
+
+        let orphan_files = files
+            .into_iter()
+            .filter(|path| !live_files.contains(path) && !is_index_file(path))
+            .map(|path| OrphanFile{
+                bytes: file_bytes(directory, index_path, &path).unwrap_or(0),
+                managed: managed_files.contains(&path),
+                path,
+            })
+            .collect::<Vec<OrphanFile>>();
+
+        Ok(SegmentInventory{
+            segments,
+            orphan_files,
+        })
+    }
+
+    pub fn num_docs(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.num_docs as u64).sum()
+    }
+
+    pub fn num_deleted_docs(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.num_deleted_docs as u64).sum()
+    }
+
+    /// Bytes of the live segments, orphans excluded
+    pub fn segment_bytes(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.component_bytes.total()).sum()
+    }
+
+    pub fn orphan_bytes(&self) -> u64 {
+        self.orphan_files.iter().map(|orphan_file| orphan_file.bytes).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        io::Write,
+        process,
+    };
+    use tantivy::{
+        IndexWriter,
+        Term,
+        directory::TerminatingWrite,
+        doc,
+        merge_policy::NoMergePolicy,
+        schema::{
+            Schema,
+            STORED,
+            STRING,
+            TEXT,
+        },
+    };
+
+    use super::*;
+
+    /// Two segments without merges, the first one with a delete, and a managed file
+    /// no segment refers to. The writer is handed back to keep holding its lock.
+    fn populate(index: &Index) -> IndexWriter {
+        let id = index.schema().get_field("id").unwrap();
+        let name = index.schema().get_field("name").unwrap();
+
+        let mut writer: IndexWriter = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        writer.set_merge_policy(Box::new(NoMergePolicy));
+
+        for (person_id, person_name) in [("a", "Ada"), ("b", "Bob"), ("c", "Cy")] {
+            writer.add_document(doc!(id => person_id, name => person_name)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        for (person_id, person_name) in [("d", "Dee"), ("e", "Eve")] {
+            writer.add_document(doc!(id => person_id, name => person_name)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        writer.delete_term(Term::from_field_text(id, "a"));
+        writer.commit().unwrap();
+
+        // Written after the commits, which would garbage collect it
+        let mut orphan = index.directory().open_write(Path::new("orphan.store")).unwrap();
+        orphan.write_all(b"stray").unwrap();
+        orphan.terminate().unwrap();
+
+        writer
+    }
+
+    fn schema() -> Schema {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("id", STRING | STORED);
+        schema_builder.add_text_field("name", TEXT | STORED);
+        schema_builder.build()
+    }
+
+    fn assert_segments(segment_inventory: &SegmentInventory) {
+        assert_eq!(segment_inventory.segments.len(), 2);
+        assert_eq!(segment_inventory.num_docs(), 4);
+        assert_eq!(segment_inventory.num_deleted_docs(), 1);
+
+        for segment in &segment_inventory.segments {
+            let delete_files = segment.files
+                .iter()
+                .filter(|path| path.extension().is_some_and(|extension| extension == "del"))
+                .count();
+
+            // Only the segment with deletes lists its `.del` file
+            match segment.delete_opstamp {
+                Some(_) => {
+                    assert_eq!((segment.num_docs, segment.num_deleted_docs), (2, 1));
+                    assert_eq!(delete_files, 1);
+                    assert!(segment.component_bytes.delete > 0);
+                },
+                None => {
+                    assert_eq!((segment.num_docs, segment.num_deleted_docs), (2, 0));
+                    assert_eq!(delete_files, 0);
+                    assert_eq!(segment.component_bytes.delete, 0);
+                },
+            }
+
+            assert!(segment.component_bytes.store > 0);
+            assert_eq!(segment.component_bytes.temp_store, 0);
+            assert!(segment.files.iter().all(|path| path.to_string_lossy().starts_with(&segment.id)));
+        }
+    }
+
+    #[test]
+    fn reads_the_segments_of_a_ram_index() {
+        let index = Index::create_in_ram(schema());
+        let _writer = populate(&index);
+
+        let segment_inventory = SegmentInventory::read(&index, None).unwrap();
+        assert_segments(&segment_inventory);
+
+        // Sized as tantivy reads the files, footer excluded
+        for segment in &segment_inventory.segments {
+            let bytes = segment.files
+                .iter()
+                .map(|path| index.directory().open_read(path).unwrap().len() as u64)
+                .sum::<u64>();
+
+            assert_eq!(segment.component_bytes.total(), bytes);
+        }
+
+        assert_eq!(segment_inventory.orphan_files, vec![OrphanFile{
+            path: PathBuf::from("orphan.store"),
+            bytes: 5,
+            managed: true,
+        }]);
+        assert_eq!(segment_inventory.orphan_bytes(), 5);
+    }
+
+    #[test]
+    fn reads_the_segments_of_an_index_on_disk() {
+        let index_path = env::temp_dir().join(format!("segment-inventory-{}", process::id()));
+        let _ = fs::remove_dir_all(&index_path);
+        fs::create_dir_all(&index_path).unwrap();
+
+        let index = Index::create_in_dir(&index_path, schema()).unwrap();
+        let writer = populate(&index);
+        fs::write(index_path.join("untracked.txt"), "left behind").unwrap();
+
+        let segment_inventory = SegmentInventory::read(&index, Some(&index_path));
+        let lock_held = index_path.join(".tantivy-writer.lock").exists();
+        drop(writer);
+        drop(index);
+
+        let on_disk = |path: &Path| fs::metadata(index_path.join(path)).unwrap().len();
+        let segment_inventory = segment_inventory.unwrap();
+        assert_segments(&segment_inventory);
+        assert!(lock_held);
+
+        // Sized on disk, footer included
+        for segment in &segment_inventory.segments {
+            let bytes = segment.files.iter().map(|path| on_disk(path)).sum::<u64>();
+
+            assert_eq!(segment.component_bytes.total(), bytes);
+        }
+
+        // Neither the metas nor the locks are orphans
+        assert_eq!(segment_inventory.orphan_files, vec![
+            OrphanFile{
+                path: PathBuf::from("orphan.store"),
+                bytes: on_disk(Path::new("orphan.store")),
+                managed: true,
+            },
+            OrphanFile{
+                path: PathBuf::from("untracked.txt"),
+                bytes: 11,
+                managed: false,
+            },
+        ]);
+        assert!(segment_inventory.orphan_files[0].bytes > 5);
+
+        let _ = fs::remove_dir_all(&index_path);
+    }
+
+    #[test]
+    fn tells_index_files_apart() {
+        for path in ["meta.json", ".managed.json", ".tantivy-writer.lock", ".tantivy-meta.lock"] {
+            assert!(is_index_file(Path::new(path)), "{}", path);
+        }
+        for path in ["meta.json.tmp", "tantivy-writer.lock", "00000000000000000000000000000001.store"] {
+            assert!(!is_index_file(Path::new(path)), "{}", path);
+        }
+    }
+}
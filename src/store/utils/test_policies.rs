@@ -0,0 +1,99 @@
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use tantivy::{
+    Index,
+    index::{
+        SegmentId,
+        SegmentMeta,
+    },
+    merge_policy::{
+        MergePolicy,
+        MergeCandidate,
+    },
+    schema::Schema,
+};
+
+// Segments
+
+pub fn segment_id(seq: u128) -> SegmentId {
+    match SegmentId::from_uuid_string(&format!("{:032x}", seq)) {
+        Ok(segment_id) => segment_id,
+        Err(err) => panic!("Failed to build segment id {}: {}", seq, err),
+    }
+}
+
+/// Segments of `(max_doc, num_deleted_docs)`, numbered from 1 in the given order
+pub fn segment_metas(docs: &[(u32, u32)]) -> Vec<SegmentMeta> {
+    let index = Index::create_in_ram(Schema::builder().build());
+
+    docs
+        .iter()
+        .enumerate()
+        .map(|(seq, (max_doc, num_deleted_docs))| {
+            let segment_meta = index.new_segment_meta(segment_id(seq as u128 + 1), *max_doc);
+
+            match num_deleted_docs {
+                0 => segment_meta,
+                num_deleted_docs => segment_meta.with_delete_meta(*num_deleted_docs, 0),
+            }
+        })
+        .collect()
+}
+
+pub fn candidate_ids(merge_candidates: &[MergeCandidate]) -> Vec<Vec<SegmentId>> {
+    merge_candidates
+        .iter()
+        .map(|merge_candidate| merge_candidate.0.clone())
+        .collect()
+}
+
+// FixedPolicy
+
+/// Returns the same candidates on every call, recording the segments it was given
+#[derive(Debug, Clone, Default)]
+pub struct FixedPolicy {
+    candidates: Vec<Vec<SegmentId>>,
+    calls: Arc<Mutex<Vec<Vec<SegmentId>>>>,
+}
+
+impl FixedPolicy {
+    pub fn new(candidates: Vec<Vec<SegmentId>>) -> Self {
+        FixedPolicy{
+            candidates,
+            calls: Arc::default(),
+        }
+    }
+
+    /// Ids of the segments passed to every call so far
+    pub fn calls(&self) -> Vec<Vec<SegmentId>> {
+        match self.calls.lock() {
+            Ok(calls) => calls.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    pub fn as_box(&self) -> Box<Self> {
+        Box::new(self.clone())
+    }
+}
+
+impl MergePolicy for FixedPolicy {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let segment_ids = segment_metas
+            .iter()
+            .map(|segment_meta| segment_meta.id())
+            .collect::<Vec<SegmentId>>();
+
+        match self.calls.lock() {
+            Ok(mut calls) => calls.push(segment_ids),
+            Err(poisoned) => poisoned.into_inner().push(segment_ids),
+        }
+
+        self.candidates
+            .iter()
+            .map(|segment_ids| MergeCandidate(segment_ids.clone()))
+            .collect()
+    }
+}
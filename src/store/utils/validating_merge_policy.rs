@@ -0,0 +1,345 @@
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+    },
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use tantivy::{
+    index::{
+        SegmentId,
+        SegmentMeta,
+    },
+    merge_policy::{
+        MergePolicy,
+        MergeCandidate,
+    },
+};
+
+// MergePolicyFindings
+
+/// Degenerate candidates returned by a merge policy
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MergePolicyFindings {
+    pub calls: u64,
+    pub empty_candidates: u64,
+    /// Single segment without deletes, merging it only rewrites the same docs
+    pub single_segment_candidates: u64,
+    /// Segment ids appearing more than once across the candidates of a call
+    pub duplicate_segment_ids: u64,
+    /// Segment ids not passed to the policy
+    pub unknown_segment_ids: u64,
+    pub filtered_candidates: u64,
+    /// Calls returning the same output as an earlier call with the same input shape
+    pub repeated_outputs: u64,
+    pub max_repeated_output: u64,
+}
+
+impl MergePolicyFindings {
+    pub fn has_degenerate_candidates(&self) -> bool {
+        self.empty_candidates > 0
+            || self.single_segment_candidates > 0
+            || self.duplicate_segment_ids > 0
+            || self.unknown_segment_ids > 0
+    }
+}
+
+/// Doc counts of the input segments and of every returned candidate, segment ids
+/// are left out as every merge produces a new one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CallShape {
+    input: Vec<(u32, u32)>,
+    output: Vec<Vec<(u32, u32)>>,
+}
+
+#[derive(Debug, Default)]
+struct ValidationState {
+    findings: MergePolicyFindings,
+    shapes: HashMap<CallShape, u64>,
+}
+
+// MergePolicyValidation
+
+#[derive(Debug, Clone, Default)]
+pub struct MergePolicyValidation {
+    state: Arc<Mutex<ValidationState>>,
+}
+
+impl MergePolicyValidation {
+    pub fn new() -> Self {
+        MergePolicyValidation::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ValidationState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn findings(&self) -> MergePolicyFindings {
+        self.lock().findings.clone()
+    }
+}
+
+// ValidatingMergePolicy
+
+#[derive(Debug)]
+pub struct ValidatingMergePolicy<P: MergePolicy + ?Sized> {
+    validation: MergePolicyValidation,
+    filter_degenerate_candidates: bool,
+    inner: Box<P>,
+}
+
+impl<P: MergePolicy + ?Sized> ValidatingMergePolicy<P> {
+    pub fn new(inner: Box<P>, validation: MergePolicyValidation) -> Self {
+        ValidatingMergePolicy{
+            validation,
+            filter_degenerate_candidates: false,
+            inner,
+        }
+    }
+
+    /// Drops the flagged candidates instead of only counting them
+    pub fn filter_degenerate_candidates(mut self, filter_degenerate_candidates: bool) -> Self {
+        self.filter_degenerate_candidates = filter_degenerate_candidates;
+        self
+    }
+
+    pub fn as_box(self) -> Box<Self> {
+        Box::new(self)
+    }
+}
+
+impl<P: MergePolicy + ?Sized> MergePolicy for ValidatingMergePolicy<P> {
+    fn compute_merge_candidates(&self, segment_metas: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let merge_candidates = self.inner.compute_merge_candidates(segment_metas);
+
+        let segments = segment_metas
+            .iter()
+            .map(|segment_meta| (segment_meta.id(), segment_meta))
+            .collect::<HashMap<SegmentId, &SegmentMeta>>();
+
+        let mut state = self.validation.lock();
+        state.findings.calls += 1;
+
+        let mut seen_segment_ids: HashSet<SegmentId> = HashSet::new();
+        let mut valid_candidates: Vec<MergeCandidate> = Vec::new();
+
+        for merge_candidate in merge_candidates.iter() {
+            let mut degenerate = false;
+
+            if merge_candidate.0.is_empty() {
+                state.findings.empty_candidates += 1;
+                degenerate = true;
+            }
+
+            if merge_candidate.0.len() == 1 {
+                let no_deletes = match segments.get(&merge_candidate.0[0]) {
+                    Some(segment_meta) => !segment_meta.has_deletes(),
+                    None => false,
+                };
+
+                if no_deletes {
+                    state.findings.single_segment_candidates += 1;
+                    degenerate = true;
+                }
+            }
+
+            for segment_id in merge_candidate.0.iter() {
+                if !seen_segment_ids.insert(*segment_id) {
+                    state.findings.duplicate_segment_ids += 1;
+                    degenerate = true;
+                }
+
+                if !segments.contains_key(segment_id) {
+                    state.findings.unknown_segment_ids += 1;
+                    degenerate = true;
+                }
+            }
+
+            if degenerate && self.filter_degenerate_candidates {
+                state.findings.filtered_candidates += 1;
+            } else {
+                valid_candidates.push(MergeCandidate(merge_candidate.0.clone()));
+            }
+        }
+
+        let shape_of = |segment_id: &SegmentId| match segments.get(segment_id) {
+            Some(segment_meta) => (segment_meta.max_doc(), segment_meta.num_deleted_docs()),
+            None => (0, 0),
+        };
+
+        let mut input = segment_metas
+            .iter()
+            .map(|segment_meta| shape_of(&segment_meta.id()))
+            .collect::<Vec<(u32, u32)>>();
+        input.sort_unstable();
+
+        let output = merge_candidates
+            .iter()
+            .map(|merge_candidate| {
+                let mut candidate = merge_candidate.0
+                    .iter()
+                    .map(shape_of)
+                    .collect::<Vec<(u32, u32)>>();
+                candidate.sort_unstable();
+                candidate
+            })
+            .collect::<Vec<Vec<(u32, u32)>>>();
+
+        // Returning nothing for the same input is the expected steady state
+        if !output.is_empty() {
+            let repeats = {
+                let repeats = state.shapes.entry(CallShape{ input, output }).or_insert(0);
+                *repeats += 1;
+                *repeats - 1
+            };
+
+            if repeats > 0 {
+                state.findings.repeated_outputs += 1;
+                state.findings.max_repeated_output = state.findings.max_repeated_output.max(repeats);
+            }
+        }
+
+        valid_candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_policies::{
+        FixedPolicy,
+        segment_id,
+        segment_metas,
+        candidate_ids,
+    };
+
+    fn validate(
+        candidates: Vec<Vec<SegmentId>>,
+        segment_metas: &[SegmentMeta],
+        filter_degenerate_candidates: bool,
+    ) -> (Vec<Vec<SegmentId>>, MergePolicyFindings) {
+        let validation = MergePolicyValidation::new();
+        let policy = ValidatingMergePolicy::new(FixedPolicy::new(candidates).as_box(), validation.clone())
+            .filter_degenerate_candidates(filter_degenerate_candidates);
+
+        let merge_candidates = policy.compute_merge_candidates(segment_metas);
+
+        (candidate_ids(&merge_candidates), validation.findings())
+    }
+
+    #[test]
+    fn valid_candidates_pass_through() {
+        let segments = segment_metas(&[(10, 0), (20, 0), (30, 5)]);
+        let candidates = vec![vec![segment_id(1), segment_id(2)], vec![segment_id(3)]];
+
+        let (merge_candidates, findings) = validate(candidates.clone(), &segments, true);
+
+        assert_eq!(merge_candidates, candidates);
+        assert_eq!(findings.calls, 1);
+        assert!(!findings.has_degenerate_candidates());
+        assert_eq!(findings.filtered_candidates, 0);
+    }
+
+    #[test]
+    fn flags_empty_candidates() {
+        let segments = segment_metas(&[(10, 0), (20, 0)]);
+
+        let (merge_candidates, findings) = validate(vec![vec![]], &segments, false);
+
+        assert_eq!(merge_candidates, vec![Vec::<SegmentId>::new()]);
+        assert_eq!(findings.empty_candidates, 1);
+        assert!(findings.has_degenerate_candidates());
+    }
+
+    #[test]
+    fn flags_single_segments_without_deletes_only() {
+        let segments = segment_metas(&[(10, 0), (20, 4)]);
+        let candidates = vec![vec![segment_id(1)], vec![segment_id(2)]];
+
+        let (merge_candidates, findings) = validate(candidates, &segments, true);
+
+        // Merging the segment with deletes expunges them
+        assert_eq!(merge_candidates, vec![vec![segment_id(2)]]);
+        assert_eq!(findings.single_segment_candidates, 1);
+        assert_eq!(findings.filtered_candidates, 1);
+    }
+
+    #[test]
+    fn flags_duplicate_segment_ids() {
+        let segments = segment_metas(&[(10, 0), (20, 0), (30, 0)]);
+        let candidates = vec![
+            vec![segment_id(1), segment_id(2)],
+            vec![segment_id(2), segment_id(3)],
+            vec![segment_id(3), segment_id(3)],
+        ];
+
+        let (merge_candidates, findings) = validate(candidates, &segments, true);
+
+        assert_eq!(merge_candidates, vec![vec![segment_id(1), segment_id(2)]]);
+        assert_eq!(findings.duplicate_segment_ids, 3);
+        assert_eq!(findings.filtered_candidates, 2);
+    }
+
+    #[test]
+    fn flags_unknown_segment_ids() {
+        let segments = segment_metas(&[(10, 0), (20, 0)]);
+        let candidates = vec![vec![segment_id(1), segment_id(9)]];
+
+        let (merge_candidates, findings) = validate(candidates.clone(), &segments, false);
+
+        assert_eq!(merge_candidates, candidates);
+        assert_eq!(findings.unknown_segment_ids, 1);
+        assert_eq!(findings.single_segment_candidates, 0);
+        assert_eq!(findings.filtered_candidates, 0);
+    }
+
+    #[test]
+    fn counts_repeated_outputs_for_the_same_input_shape() {
+        let validation = MergePolicyValidation::new();
+        let policy = ValidatingMergePolicy::new(
+            FixedPolicy::new(vec![vec![segment_id(1), segment_id(2)]]).as_box(),
+            validation.clone(),
+        );
+
+        // Same doc counts under new segment ids every time, like a merge rewriting them
+        for _ in 0..3 {
+            policy.compute_merge_candidates(&segment_metas(&[(10, 0), (20, 0)]));
+        }
+        policy.compute_merge_candidates(&segment_metas(&[(10, 0), (25, 0)]));
+
+        let findings = validation.findings();
+        assert_eq!(findings.calls, 4);
+        assert_eq!(findings.repeated_outputs, 2);
+        assert_eq!(findings.max_repeated_output, 2);
+    }
+
+    #[test]
+    fn empty_outputs_are_not_repeats() {
+        let validation = MergePolicyValidation::new();
+        let inner = FixedPolicy::new(vec![]);
+        let policy = ValidatingMergePolicy::new(inner.as_box(), validation.clone());
+
+        for _ in 0..3 {
+            policy.compute_merge_candidates(&segment_metas(&[(10, 0)]));
+        }
+
+        assert_eq!(inner.calls(), vec![vec![segment_id(1)]; 3]);
+
+        assert_eq!(validation.findings(), MergePolicyFindings{
+            calls: 3,
+            ..MergePolicyFindings::default()
+        });
+    }
+}